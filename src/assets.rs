@@ -13,10 +13,13 @@ use std::sync::RwLock;
 use crate::constants::*;
 use crate::game::resources::*;
 
+use crate::game::body_plan::BodyPlanRegistry;
+use crate::game::damage::DamageRegistry;
 use crate::game::dialog::Dialog;
-use crate::game::map::MapLocation;
-use crate::game::map::TileLocation;
+use crate::game::enemy::Enemy;
 use crate::game::npc::NPC;
+use crate::game::terrain::TerrainRegistry;
+use crate::game::trap::TrapRegistry;
 
 pub struct AssetsPlugin;
 
@@ -44,11 +47,7 @@ fn generate_example_specs_system() {
             ("Ahhhhh!".to_string(), None),
         ])),
     );
-    let npc = NPC::new(
-        "Example NPC".to_string(),
-        MapLocation::new(0, TileLocation::new(4, 5)),
-        example_dialog,
-    );
+    let npc = NPC::new("Example NPC".to_string(), example_dialog);
 
     let npc_string = serde_json::to_string(&npc).expect("Error serializing npc");
 
@@ -67,9 +66,39 @@ fn load_assets_system(mut commands: Commands) {
         .into_iter()
         .map(|(_name, npc)| npc)
         .collect::<Vec<NPC>>();
+    let enemies = FileSystem::<Enemy>::new_directory(ENEMY_DIRECTORY)
+        .load_all()
+        .expect("We should be able to load enemies.")
+        .into_values()
+        .collect::<Vec<Enemy>>();
+
+    // Otherwise a typo in a body plan would quietly make the character humanoid.
+    let body_plans = BodyPlanRegistry::global();
+    let unknown_body_plan = npcs
+        .iter()
+        .map(|npc| (&npc.name, npc.get_body_plan()))
+        .chain(
+            enemies
+                .iter()
+                .map(|enemy| (&enemy.name, enemy.get_body_plan())),
+        )
+        .find_map(|(name, body_plan)| {
+            body_plan
+                .filter(|id| !body_plans.contains(id))
+                .map(|id| (name, id))
+        });
+    if let Some((name, body_plan)) = unknown_body_plan {
+        panic!("{} has an unknown body plan {}.", name, body_plan);
+    }
 
     commands.insert_resource(NPCSpecs::from_vec(npcs));
-    let rng = StdRng::seed_from_u64(12345);
+    commands.insert_resource(EnemySpecs::from_vec(enemies));
+    // Load damage, terrain and traps up front so a bad spec fails here rather than on the first
+    // map drawn.
+    DamageRegistry::global();
+    TerrainRegistry::global();
+    TrapRegistry::global();
+    let rng = StdRng::from_entropy();
     commands.insert_resource(RngResource(RwLock::new(rng)));
     commands.insert_resource(NextState(Some(GameState::LoadingUI)));
 }
@@ -102,6 +131,19 @@ impl<T: Clone + Serialize + DeserializeOwned> FileSystem<T> {
         }
     }
 
+    // For specs that other specs and maps refer to, like terrain and traps.  There's one file per
+    // spec, and the file name without ".json" is the id it's referred to by.
+    pub fn load_all_by_id(&self) -> Result<HashMap<String, T>, FileSystemError> {
+        Ok(self
+            .load_all()?
+            .into_iter()
+            .map(|(filename, o)| match filename.strip_suffix(".json") {
+                Some(id) => (id.to_string(), o),
+                None => (filename, o),
+            })
+            .collect())
+    }
+
     pub fn save(&mut self, filename: &str, o: T) -> Result<(), FileSystemError> {
         match self {
             Self::Stub(map) => {
@@ -166,7 +208,8 @@ impl<T: Clone + Serialize + DeserializeOwned> FileSystem<T> {
     }
 
     fn write_file_to_directory(path: &PathBuf, o: T) -> Result<(), FileSystemError> {
-        match serde_json::to_string(&o) {
+        // Pretty printed so that saved specs diff line by line.
+        match serde_json::to_string_pretty(&o) {
             Err(e) => {
                 return Err(FileSystemError::CouldntSerialize);
             }
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
 use crate::constants::*;
+use crate::game::chunk::RenderedTileGrid;
+use crate::game::map::{TileGrid, TileLocation};
 use crate::game::{events::CameraMovementEvent, resources::GameState};
 
 pub struct CameraMovementPlugin;
@@ -18,14 +20,26 @@ impl Plugin for CameraMovementPlugin {
 
 // Systems
 
+// The camera can't be moved past the edges of the layer being shown.
 fn move_camera_system(
     mut camera_movement_event_reader: EventReader<CameraMovementEvent>,
     mut query: Query<&mut Transform, With<Camera2d>>,
+    rendered_grid: Option<Res<RenderedTileGrid>>,
 ) {
     let mut transform = query.single_mut();
     for event in camera_movement_event_reader.iter() {
         transform.translation += event.0.as_vector().extend(0.) * CAMERA_MOVE_SPEED;
     }
+    if let Some(rendered_grid) = rendered_grid {
+        let max = TileGrid::tile_to_world_coordinates(TileLocation::new(
+            rendered_grid.width().saturating_sub(1) as i32,
+            rendered_grid.height().saturating_sub(1) as i32,
+        ));
+        let clamped = transform.translation.truncate().clamp(Vec2::ZERO, max);
+        if clamped != transform.translation.truncate() {
+            transform.translation = clamped.extend(transform.translation.z);
+        }
+    }
 }
 
 // End Systems
@@ -1,6 +1,30 @@
 // Filepaths
 pub const NPC_DIRECTORY: &str = "assets/specs/npcs/";
+pub const ENEMY_DIRECTORY: &str = "assets/specs/enemies/";
 pub const MAP_DIRECTORY: &str = "assets/specs/maps/";
+pub const STARTING_MAP_FILENAME: &str = "start.json";
+pub const ASCII_MAP_DIRECTORY: &str = "assets/specs/maps/ascii/";
+pub const TERRAIN_DIRECTORY: &str = "assets/specs/terrain/";
+pub const TRAP_DIRECTORY: &str = "assets/specs/traps/";
+pub const BODY_PLAN_DIRECTORY: &str = "assets/specs/body_plans/";
+pub const DAMAGE_DIRECTORY: &str = "assets/specs/damage/";
+pub const ASCII_MAP_EXTENSION: &str = "txt";
+pub const TILESET_DIRECTORY: &str = "assets/specs/tilesets/";
+pub const DEFAULT_TILESET_FILENAME: &str = "default.json";
+pub const GROUND_TERRAIN: &str = "ground";
+pub const WALL_TERRAIN: &str = "wall";
+pub const FIREPLACE_TERRAIN: &str = "fireplace";
+pub const STAIRS_UP_TERRAIN: &str = "stairs_up";
+pub const STAIRS_DOWN_TERRAIN: &str = "stairs_down";
+pub const PORTAL_TERRAIN: &str = "portal";
+pub const DOOR_CLOSED_TERRAIN: &str = "door_closed";
+pub const DOOR_OPEN_TERRAIN: &str = "door_open";
+pub const DOOR_LOCKED_TERRAIN: &str = "door_locked";
+pub const BOOKSHELF_TERRAIN: &str = "bookshelf";
+pub const BURNING_BOOKSHELF_TERRAIN: &str = "burning_bookshelf";
+pub const ASH_TERRAIN: &str = "ash";
+pub const SPIKE_TRAP: &str = "spike_trap";
+pub const HUMANOID_BODY_PLAN: &str = "humanoid";
 
 // Game Parameters
 pub const DEFAULT_BODY_SIZE: u8 = 40;
@@ -9,8 +33,35 @@ pub const DEFAULT_ARM_SIZE: u8 = 15;
 pub const DEFAULT_LEG_SIZE: u8 = 20;
 pub const DEFAULT_HAND_SIZE: u8 = 5;
 pub const DEFAULT_FOOT_SIZE: u8 = 5;
+pub const DEFAULT_TAIL_SIZE: u8 = 10;
+pub const DEFAULT_WING_SIZE: u8 = 15;
+pub const DEFAULT_TENTACLE_SIZE: u8 = 10;
 pub const MOVEMENT_TICKS: u8 = 10;
+// How long a move takes with no working legs or feet.
+pub const CRAWLING_MOVEMENT_TICKS: u8 = 40;
 pub const AI_SPEAK_TICKS: u8 = 10;
+pub const DOOR_TICKS: u8 = 10;
+pub const DEFAULT_SIGHT_RADIUS: u32 = 8;
+// Status effects progress once a turn, with these chances out of 100.
+pub const MAX_BLOOD: u8 = 100;
+pub const BLEEDING_BLOOD_LOSS: u8 = 2;
+pub const BLEEDING_STOP_CHANCE: u8 = 5;
+pub const INFECTION_SPREAD_CHANCE: u8 = 5;
+pub const CANCER_PROGRESSION_CHANCE: u8 = 2;
+pub const NATURAL_HEALING_CHANCE: u8 = 1;
+pub const SPLINT_HEALING_CHANCE: u8 = 10;
+// Blood comes back a little every turn that nothing is bleeding.
+pub const BLOOD_REGENERATION: u8 = 1;
+pub const TREATMENT_TICKS: u8 = 20;
+pub const LAMP_LIGHT_RADIUS: u32 = 5;
+// Unlit tiles are drawn this dimly, and can only be seen from this close.
+pub const AMBIENT_LIGHT_LEVEL: f32 = 0.25;
+pub const DARK_SIGHT_RADIUS: u32 = 1;
+pub const MIN_VISIBLE_LIGHT_LEVEL: f32 = 0.1;
+// Smoke thins out by SMOKE_DECAY every turn and blocks sight once it's SMOKE_OPAQUE_DENSITY thick.
+pub const SMOKE_DECAY: u8 = 1;
+pub const SMOKE_OPAQUE_DENSITY: u8 = 4;
+pub const MAX_SMOKE_DENSITY: u8 = 12;
 
 // UI
 pub const DEFAULT_FONT_SIZE: f32 = 24.;
@@ -20,6 +71,10 @@ pub const LOG_WINDOW_SIZE: (f32, f32) = (540., 200.);
 pub const LOG_TEXT_SIZE: f32 = 14.;
 pub const KEY_HOLD_DELAY_IN_MILLIS: u64 = 400;
 pub const TOAST_MESSAGE_TIME_IN_SECONDS: f32 = 3.;
+// Minimap cells shrink on big maps so that the minimap never gets bigger than MINIMAP_MAX_SIZE.
+pub const MINIMAP_CELL_SIZE: f32 = 3.;
+pub const MINIMAP_MAX_SIZE: f32 = 200.;
+pub const MINIMAP_OFFSET: [f32; 2] = [-10., 10.];
 
 // Map
 pub const DEFAULT_MAP_WIDTH_IN_TILES: usize = 50;
@@ -27,9 +82,23 @@ pub const DEFAULT_MAP_HEIGHT_IN_TILES: usize = 50;
 pub const TILE_WIDTH: usize = 32;
 pub const TILE_HEIGHT: usize = 32;
 pub const ASCII_TILE_FONT_SIZE: f32 = 40.;
+pub const DEFAULT_MAP_LAYERS: usize = 2;
+pub const MAP_FILE_VERSION: u32 = 1;
+pub const DEFAULT_ROOM_COUNT: usize = 12;
+pub const DEFAULT_MIN_ROOM_SIZE: usize = 4;
+pub const DEFAULT_MAX_ROOM_SIZE: usize = 10;
+pub const ROOM_PLACEMENT_ATTEMPTS_PER_ROOM: usize = 20;
+pub const DEFAULT_CAVE_WALL_PERCENT: u8 = 45;
+pub const DEFAULT_CAVE_SMOOTHING_ITERATIONS: usize = 5;
+pub const CAVE_WALL_NEIGHBOURHOOD_THRESHOLD: usize = 5;
+pub const DEFAULT_PATTERN_SIZE: usize = 3;
+pub const WAVE_FUNCTION_COLLAPSE_ATTEMPTS: usize = 10;
 pub const CAMERA_ZOOM_LOG_BASE: f32 = 2.0;
 pub const CAMERA_ZOOM_SPEED: f32 = 0.2;
 pub const CAMERA_MOVE_SPEED: f32 = 10.;
+pub const REMEMBERED_TILE_ALPHA: f32 = 0.3;
+pub const CHUNK_SIZE_IN_TILES: i32 = 16;
+pub const CHUNK_MARGIN: i32 = 1;
 
 // Colors
 pub const BLUEPRINT_BLUE: (f32, f32, f32) = (0.25, 0.25, 0.75);
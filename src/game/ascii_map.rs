@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::map::{Map, MapLayer, ObjectTile, SurfaceTile, Tile, TileAppearance};
+use super::map_metadata::MapMetadata;
+use super::terrain::TerrainRegistry;
+use crate::constants::*;
+
+// Plain-text maps, e.g.
+//
+//   [layer 0]
+//   #####
+//   #·>·#
+//   #####
+//
+//   [legend]
+//   · = ground
+//   # = wall
+//   > = {"terrain":"stairs_down","destination":{"map_layer":1,"tile_location":{"i":2,"j":2}}}
+//   T = ground + Trap
+//
+//   [metadata]
+//   {"name":"Cellar","player_spawns":[{"map_layer":0,"tile_location":{"i":1,"j":1}}]}
+//
+// The top line of a layer is its highest row, so the text looks the way the layer is drawn.  The
+// legend maps each glyph to a terrain id followed by the objects stacked on it; glyphs that aren't
+// in the legend fall back to the defaults of TileAppearance::from_tile.  The optional metadata
+// section is the map's MapMetadata as JSON.
+
+const LAYER_HEADER: &str = "[layer";
+const LEGEND_HEADER: &str = "[legend]";
+const METADATA_HEADER: &str = "[metadata]";
+const FALLBACK_GLYPHS: &str = "ABCDEFGHIJKLMNOPQRSTUVWYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+// Structs
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsciiMapError {
+    NoLayers,
+    RaggedLayer(usize),
+    MissingLegendEntry(char),
+    InvalidLegendEntry(String),
+    InvalidMetadata(String),
+    UnexpectedLine(String),
+    TooManyTiles,
+    CouldntReadFile(String),
+    CouldntWriteFile(String),
+}
+
+impl AsciiMapError {
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::NoLayers => "The map has no layers.".to_string(),
+            Self::RaggedLayer(layer) => format!("Layer {} isn't rectangular.", layer),
+            Self::MissingLegendEntry(glyph) => format!("No legend entry for '{}'.", glyph),
+            Self::InvalidLegendEntry(line) => format!("Invalid legend entry: {}", line),
+            Self::InvalidMetadata(error) => format!("Invalid metadata: {}", error),
+            Self::UnexpectedLine(line) => format!("Unexpected line: {}", line),
+            Self::TooManyTiles => "Ran out of glyphs for distinct tiles.".to_string(),
+            Self::CouldntReadFile(path) => format!("Couldn't read {}.", path),
+            Self::CouldntWriteFile(path) => format!("Couldn't write {}.", path),
+        }
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+// Each file is read on its own, so one broken sketch doesn't hide the rest.  A missing directory
+// just means nobody has sketched a map yet.
+pub fn load_ascii_maps(directory: &str) -> HashMap<String, Result<Map, AsciiMapError>> {
+    let mut maps = HashMap::new();
+    let read_dir = match fs::read_dir(directory) {
+        Ok(read_dir) => read_dir,
+        Err(_) => return maps,
+    };
+    for dir_entry in read_dir.filter_map(|dir_entry| dir_entry.ok()) {
+        let path = dir_entry.path();
+        if path.extension() != Some(OsStr::new(ASCII_MAP_EXTENSION)) {
+            continue;
+        }
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .expect("The filename should be a legitimate string.")
+            .to_string();
+        let map = fs::read_to_string(&path)
+            .map_err(|_| AsciiMapError::CouldntReadFile(path.display().to_string()))
+            .and_then(|contents| map_from_ascii(&contents));
+        maps.insert(name, map);
+    }
+    maps
+}
+
+pub fn save_ascii_map(directory: &str, name: &str, map: &Map) -> Result<(), AsciiMapError> {
+    let text = map_to_ascii(map)?;
+    let path = Path::new(directory).join(format!("{}.{}", name, ASCII_MAP_EXTENSION));
+    fs::create_dir_all(directory)
+        .and_then(|_| fs::write(&path, text))
+        .map_err(|_| AsciiMapError::CouldntWriteFile(path.display().to_string()))
+}
+
+pub fn map_to_ascii(map: &Map) -> Result<String, AsciiMapError> {
+    let mut legend: Vec<(char, Tile)> = Vec::new();
+    let mut glyphs: HashMap<Tile, char> = HashMap::new();
+    let mut text = String::new();
+
+    for (index, map_layer) in map.get_layers().iter().enumerate() {
+        text.push_str(&format!("{} {}]\n", LAYER_HEADER, index));
+        for j in (0..map_layer.height()).rev() {
+            for i in 0..map_layer.width() {
+                let tile = map_layer
+                    .get(i, j)
+                    .expect("We're iterating within the layer.");
+                let glyph = match glyphs.get(tile) {
+                    Some(glyph) => *glyph,
+                    None => {
+                        let glyph = choose_glyph(tile, &legend)?;
+                        glyphs.insert(tile.clone(), glyph);
+                        legend.push((glyph, tile.clone()));
+                        glyph
+                    }
+                };
+                text.push(glyph);
+            }
+            text.push('\n');
+        }
+        text.push('\n');
+    }
+
+    text.push_str(LEGEND_HEADER);
+    text.push('\n');
+    for (glyph, tile) in legend {
+        text.push_str(&format!("{} = {}\n", glyph, tile_to_legend_entry(&tile)));
+    }
+
+    if !map.get_metadata().is_empty() {
+        let metadata = serde_json::to_string_pretty(map.get_metadata())
+            .map_err(|e| AsciiMapError::InvalidMetadata(e.to_string()))?;
+        text.push_str(&format!("\n{}\n{}\n", METADATA_HEADER, metadata));
+    }
+    Ok(text)
+}
+
+pub fn map_from_ascii(text: &str) -> Result<Map, AsciiMapError> {
+    let mut layers: Vec<Vec<Vec<char>>> = Vec::new();
+    let mut legend = get_default_legend();
+    let mut metadata_text = String::new();
+    let mut in_legend = false;
+    let mut in_metadata = false;
+
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with(LAYER_HEADER) && line.ends_with(']') {
+            layers.push(Vec::new());
+            in_legend = false;
+            in_metadata = false;
+        } else if line.trim() == LEGEND_HEADER {
+            in_legend = true;
+            in_metadata = false;
+        } else if line.trim() == METADATA_HEADER {
+            in_legend = false;
+            in_metadata = true;
+        } else if in_metadata {
+            metadata_text.push_str(line);
+            metadata_text.push('\n');
+        } else if in_legend {
+            let (glyph, tile) = parse_legend_entry(line)?;
+            legend.insert(glyph, tile);
+        } else {
+            match layers.last_mut() {
+                Some(rows) => rows.push(line.chars().collect()),
+                None => return Err(AsciiMapError::UnexpectedLine(line.to_string())),
+            }
+        }
+    }
+
+    if layers.is_empty() {
+        return Err(AsciiMapError::NoLayers);
+    }
+
+    let map_layers = layers
+        .into_iter()
+        .enumerate()
+        .map(|(index, rows)| {
+            let width = rows.first().map_or(0, |row| row.len());
+            if rows.iter().any(|row| row.len() != width) {
+                return Err(AsciiMapError::RaggedLayer(index));
+            }
+            // Rows are written top down, but j counts up from the bottom.
+            (0..width)
+                .map(|i| {
+                    rows.iter()
+                        .rev()
+                        .map(|row| {
+                            legend
+                                .get(&row[i])
+                                .cloned()
+                                .ok_or(AsciiMapError::MissingLegendEntry(row[i]))
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(MapLayer::new)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let metadata = match metadata_text.trim() {
+        "" => MapMetadata::default(),
+        json => {
+            serde_json::from_str(json).map_err(|e| AsciiMapError::InvalidMetadata(e.to_string()))?
+        }
+    };
+    Ok(Map::with_metadata(map_layers, metadata))
+}
+
+fn get_default_legend() -> HashMap<char, Tile> {
+    vec![
+        Tile::empty_ground(),
+        Tile::wall(),
+        Tile::empty(SurfaceTile::new(FIREPLACE_TERRAIN)),
+        Tile::empty(SurfaceTile::new(DOOR_CLOSED_TERRAIN)),
+        Tile::empty(SurfaceTile::new(DOOR_OPEN_TERRAIN)),
+        Tile::object(ObjectTile::Key),
+    ]
+    .into_iter()
+    .map(|tile| (get_default_glyph(&tile), tile))
+    .collect()
+}
+
+fn get_default_glyph(tile: &Tile) -> char {
+    match TileAppearance::from_tile(tile) {
+        TileAppearance::Ascii(appearance) => appearance.get_character(),
+        TileAppearance::Sprite(_) => FALLBACK_GLYPHS
+            .chars()
+            .next()
+            .expect("There are fallback glyphs."),
+    }
+}
+
+// Tiles keep the glyph they're drawn with unless another tile already took it.
+fn choose_glyph(tile: &Tile, legend: &Vec<(char, Tile)>) -> Result<char, AsciiMapError> {
+    let is_taken = |glyph: &char| legend.iter().any(|(taken, _tile)| taken == glyph);
+    std::iter::once(get_default_glyph(tile))
+        .chain(FALLBACK_GLYPHS.chars())
+        .chain(GREEK_ALPHABET.iter().copied())
+        .find(|glyph| !is_taken(glyph))
+        .ok_or(AsciiMapError::TooManyTiles)
+}
+
+fn tile_to_legend_entry(tile: &Tile) -> String {
+    std::iter::once(to_legend_value(tile.get_surface()))
+        .chain(tile.get_stack().iter().map(to_legend_value))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+fn parse_legend_entry(line: &str) -> Result<(char, Tile), AsciiMapError> {
+    let invalid = || AsciiMapError::InvalidLegendEntry(line.to_string());
+    let glyph = line.chars().next().ok_or_else(invalid)?;
+    let entry = line[glyph.len_utf8()..]
+        .trim_start()
+        .strip_prefix('=')
+        .ok_or_else(invalid)?;
+
+    let mut values = entry.split('+').map(|value| value.trim());
+    let surface = values
+        .next()
+        .and_then(|value| from_legend_value::<SurfaceTile>(value))
+        .filter(|surface| TerrainRegistry::global().contains(surface.get_terrain_id()))
+        .ok_or_else(invalid)?;
+    let stack = values
+        .map(|value| from_legend_value::<ObjectTile>(value))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid)?;
+    Ok((glyph, Tile::new(surface, stack)))
+}
+
+// Terrain ids and objects without data are written as bare names, everything else as JSON.
+fn to_legend_value<T: Serialize>(value: &T) -> String {
+    let json = serde_json::to_string(value).expect("Tiles always serialize.");
+    json.trim_matches('"').to_string()
+}
+
+fn from_legend_value<T: DeserializeOwned>(value: &str) -> Option<T> {
+    if value.starts_with('{') {
+        serde_json::from_str(value).ok()
+    } else {
+        serde_json::from_str(&format!("\"{}\"", value)).ok()
+    }
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::{MapLocation, TileLocation};
+    use crate::game::trap::Trap;
+
+    #[test]
+    fn test_ascii_map_round_trips() {
+        let mut upper = MapLayer::fill(5, 3, Tile::empty_ground());
+        upper.update_edges(&Tile::wall());
+        let down = MapLocation::new(1, TileLocation::new(1, 0));
+        upper
+            .update(
+                Tile::empty(SurfaceTile::linked(STAIRS_DOWN_TERRAIN, down)),
+                1,
+                1,
+            )
+            .unwrap();
+        upper
+            .update(
+                Tile::new(
+                    SurfaceTile::ground(),
+                    vec![ObjectTile::Trap(Trap::new(SPIKE_TRAP))],
+                ),
+                3,
+                1,
+            )
+            .unwrap();
+        let mut lower = MapLayer::fill(2, 1, Tile::empty_ground());
+        lower
+            .update(
+                Tile::empty(SurfaceTile::linked(
+                    STAIRS_UP_TERRAIN,
+                    MapLocation::new(0, TileLocation::new(1, 1)),
+                )),
+                1,
+                0,
+            )
+            .unwrap();
+        let mut metadata = MapMetadata::new("Cellar");
+        metadata.add_player_spawn(MapLocation::new(0, TileLocation::new(2, 1)));
+        let map = Map::with_metadata(vec![upper, lower], metadata);
+
+        let text = map_to_ascii(&map).unwrap();
+        assert!(text.starts_with("[layer 0]\n#####\n#>\u{00B7}^#\n#####\n"));
+        assert!(text.contains("[layer 1]\n\u{00B7}<\n"));
+        assert!(
+            text.contains("^ = ground + {\"Trap\":{\"spec\":\"spike_trap\",\"revealed\":true}}\n")
+        );
+        assert!(text.contains(
+            "> = {\"terrain\":\"stairs_down\",\"destination\":{\"map_layer\":1,\"tile_location\":{\"i\":1,\"j\":0}}}\n"
+        ));
+        assert!(text.contains("[metadata]\n{\n  \"name\": \"Cellar\""));
+        assert_eq!(map_from_ascii(&text).unwrap(), map);
+    }
+
+    #[test]
+    fn test_ascii_map_reads_sketches_with_default_glyphs() {
+        let text = "[layer 0]\n###\n#\u{00B7}X\n#T#\n\n[legend]\nT = ground + Item + Key\n";
+        let map = map_from_ascii(text).unwrap();
+        let map_layer = map.get_layer(0).unwrap();
+        assert_eq!(map_layer.width(), 3);
+        assert_eq!(map_layer.height(), 3);
+        assert_eq!(map_layer.get(1, 2).unwrap(), &Tile::wall());
+        assert_eq!(map_layer.get(1, 1).unwrap(), &Tile::empty_ground());
+        assert_eq!(
+            map_layer.get(2, 1).unwrap(),
+            &Tile::empty(SurfaceTile::new(FIREPLACE_TERRAIN))
+        );
+        assert_eq!(
+            map_layer.get(1, 0).unwrap(),
+            &Tile::new(
+                SurfaceTile::ground(),
+                vec![ObjectTile::Item, ObjectTile::Key]
+            )
+        );
+    }
+
+    #[test]
+    fn test_ascii_map_reports_errors() {
+        assert_eq!(map_from_ascii(""), Err(AsciiMapError::NoLayers));
+        assert_eq!(
+            map_from_ascii("###\n"),
+            Err(AsciiMapError::UnexpectedLine("###".to_string()))
+        );
+        assert_eq!(
+            map_from_ascii("[layer 0]\n###\n##\n"),
+            Err(AsciiMapError::RaggedLayer(0))
+        );
+        assert_eq!(
+            map_from_ascii("[layer 0]\n#?#\n"),
+            Err(AsciiMapError::MissingLegendEntry('?'))
+        );
+        assert_eq!(
+            map_from_ascii("[layer 0]\n#\n[legend]\n# = Lava\n"),
+            Err(AsciiMapError::InvalidLegendEntry("# = Lava".to_string()))
+        );
+        assert!(matches!(
+            map_from_ascii("[layer 0]\n#\n[metadata]\n{\"name\": 3}\n"),
+            Err(AsciiMapError::InvalidMetadata(_))
+        ));
+    }
+
+    #[test]
+    fn test_ascii_maps_load_per_file() {
+        let directory = std::env::temp_dir().join("test_ascii_maps_load_per_file");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("good.txt"), "[layer 0]\n#\n").unwrap();
+        fs::write(directory.join("bad.txt"), "###\n").unwrap();
+
+        let maps = load_ascii_maps(directory.to_str().unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(maps.len(), 2);
+        assert!(maps["good"].is_ok());
+        assert_eq!(
+            maps["bad"],
+            Err(AsciiMapError::UnexpectedLine("###".to_string()))
+        );
+    }
+}
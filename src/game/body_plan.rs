@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use super::character::{BodyPart, BodyPartTreeNode, BodyPartType};
+use crate::assets::{FileSystem, FileSystemError};
+use crate::constants::*;
+
+static BODY_PLAN_REGISTRY: OnceLock<BodyPlanRegistry> = OnceLock::new();
+
+// Structs
+
+// NPC and enemy specs refer to body plans by id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BodyPlanSpec {
+    name: String,
+    root: BodyPlanNode,
+}
+
+impl BodyPlanSpec {
+    pub fn new(name: &str, root: BodyPlanNode) -> Self {
+        Self {
+            name: name.to_string(),
+            root,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    // Every body built from a plan starts out unhurt.
+    pub fn build(&self) -> BodyPartTreeNode {
+        self.root.build()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BodyPlanNode {
+    part: BodyPartType,
+    // Falls back to the part type's default size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u8>,
+    // How many of this part hang off its parent, e.g. a quadruped's four legs.  Ignored on the root.
+    #[serde(default = "default_count")]
+    count: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    children: Vec<BodyPlanNode>,
+}
+
+impl BodyPlanNode {
+    pub fn new(part: BodyPartType, children: Vec<BodyPlanNode>) -> Self {
+        Self {
+            part,
+            size: None,
+            count: default_count(),
+            children,
+        }
+    }
+
+    pub fn with_size(mut self, size: u8) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    pub fn build(&self) -> BodyPartTreeNode {
+        let body_part = BodyPart::new_empty(self.part.clone()).with_size(self.size);
+        let children = self
+            .children
+            .iter()
+            .flat_map(|child| std::iter::repeat_with(|| child.build()).take(child.count))
+            .collect();
+        BodyPartTreeNode::new(body_part, children)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BodyPlanRegistry(HashMap<String, BodyPlanSpec>);
+
+impl BodyPlanRegistry {
+    pub fn new(body_plans: HashMap<String, BodyPlanSpec>) -> Self {
+        Self(body_plans)
+    }
+
+    pub fn from_directory(directory_name: &str) -> Result<Self, FileSystemError> {
+        let body_plans =
+            FileSystem::<BodyPlanSpec>::new_directory(directory_name).load_all_by_id()?;
+        Ok(Self::new(body_plans))
+    }
+
+    // Loaded up front by load_assets_system.
+    pub fn global() -> &'static Self {
+        BODY_PLAN_REGISTRY.get_or_init(|| {
+            Self::from_directory(BODY_PLAN_DIRECTORY)
+                .expect("We should be able to load the body plan specs.")
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&BodyPlanSpec> {
+        self.0.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.contains_key(id)
+    }
+
+    // Characters without a body plan are humanoid.  Specs naming an unknown one are rejected when
+    // they're loaded, so falling back here only matters for characters built in code.
+    pub fn build(&self, id: Option<&str>) -> BodyPartTreeNode {
+        self.get(id.unwrap_or(HUMANOID_BODY_PLAN))
+            .map_or_else(BodyPartTreeNode::new_humanoid, |spec| spec.build())
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+fn default_count() -> usize {
+    1
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_plan_registry_loads_built_in_body_plans() {
+        let registry = BodyPlanRegistry::global();
+        for id in [
+            HUMANOID_BODY_PLAN,
+            "quadruped",
+            "serpent",
+            "winged",
+            "construct",
+        ] {
+            assert!(registry.get(id).is_some(), "Missing body plan {}", id);
+        }
+
+        let humanoid = registry.build(None);
+        let expected = BodyPartTreeNode::new_humanoid();
+        assert_eq!(humanoid.get_size_recursive(), expected.get_size_recursive());
+        assert_eq!(humanoid.get_menu_text(), expected.get_menu_text());
+
+        let serpent = registry.build(Some("serpent"));
+        assert!(serpent
+            .get_menu_text()
+            .iter()
+            .any(|line| line.contains(&BodyPartType::Tail.to_string())));
+        assert_eq!(serpent.get_sight_radius(), DEFAULT_SIGHT_RADIUS);
+
+        let unknown = registry.build(Some("no_such_body_plan"));
+        assert_eq!(unknown.get_menu_text(), expected.get_menu_text());
+    }
+
+    #[test]
+    fn test_body_plan_repeats_parts_and_overrides_sizes() {
+        let plan = BodyPlanSpec::new(
+            "octopus",
+            BodyPlanNode::new(
+                BodyPartType::Body,
+                vec![BodyPlanNode::new(BodyPartType::Tentacle, Vec::new())
+                    .with_size(8)
+                    .with_count(8)],
+            )
+            .with_size(30)
+            .with_count(3),
+        );
+        let octopus = plan.build();
+
+        assert_eq!(octopus.get_size(), 30);
+        assert_eq!(octopus.get_total_children_size(), 64);
+        assert_eq!(octopus.get_menu_text().len(), 9);
+        assert_eq!(octopus.get_sight_radius(), 0);
+    }
+}
@@ -1,10 +1,13 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 use bevy::prelude::*;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use super::map::{MapLocation, TileLocation};
+use super::damage::DamageRegistry;
+use super::health::Treatment;
+use super::map::{MapLocation, ObjectTile, TileLocation};
 use super::resources::RngResource;
 use crate::constants::*;
 
@@ -15,6 +18,66 @@ pub struct BodyComponent(pub BodyPartTreeNode);
 #[derive(Component, Clone, Copy, PartialEq)]
 pub struct LocationComponent(pub MapLocation);
 
+// How much blood a character has left.  Bleeding drains it, and whoever runs out dies.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BloodComponent(pub u8);
+
+impl Default for BloodComponent {
+    fn default() -> Self {
+        Self(MAX_BLOOD)
+    }
+}
+
+impl BloodComponent {
+    pub fn drain(&mut self, amount: u8) {
+        self.0 = self.0.saturating_sub(amount);
+    }
+
+    pub fn regenerate(&mut self, amount: u8) {
+        self.0 = self.0.saturating_add(amount).min(MAX_BLOOD);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.0 >= MAX_BLOOD
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn get_menu_text(&self) -> String {
+        format!("Blood {} {}/{}", EM_DASH, self.0, MAX_BLOOD)
+    }
+}
+
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct InventoryComponent(pub Vec<ObjectTile>);
+
+impl InventoryComponent {
+    pub fn contains(&self, object: ObjectTile) -> bool {
+        self.0.contains(&object)
+    }
+
+    pub fn add(&mut self, object: ObjectTile, count: usize) {
+        self.0.extend(std::iter::repeat(object).take(count));
+    }
+
+    pub fn count(&self, object: &ObjectTile) -> usize {
+        self.0.iter().filter(|held| *held == object).count()
+    }
+
+    // Returns whether there was one to take.
+    pub fn take(&mut self, object: &ObjectTile) -> bool {
+        match self.0.iter().position(|held| held == object) {
+            Some(idx) => {
+                self.0.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
 impl LocationComponent {
     pub fn translate(&mut self, amount: TileLocation) {
         self.0.translate(amount);
@@ -57,7 +120,10 @@ impl ActionClockComponent {
 
 // Structs
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Saved as a DamageSpec, since JSON can't key a map by a pair of states.  Specs that can name one
+// of the damage profiles instead hold a DamageSource.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "DamageSpec", into = "DamageSpec")]
 pub struct Damage {
     state_transition_probabilities: HashMap<(BodyPartState, BodyPartState), Probability>,
     status_effect_probabilities: std::collections::HashMap<BodyPartStatusEffect, Probability>,
@@ -125,6 +191,87 @@ impl Damage {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DamageSpec {
+    #[serde(default)]
+    state_transitions: Vec<StateTransitionSpec>,
+    #[serde(default)]
+    status_effects: HashMap<BodyPartStatusEffect, Probability>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateTransitionSpec {
+    from: BodyPartState,
+    to: BodyPartState,
+    probability: Probability,
+}
+
+impl From<Damage> for DamageSpec {
+    fn from(damage: Damage) -> Self {
+        let mut state_transitions = damage
+            .state_transition_probabilities
+            .into_iter()
+            .map(|((from, to), probability)| StateTransitionSpec {
+                from,
+                to,
+                probability,
+            })
+            .collect::<Vec<_>>();
+        state_transitions.sort_by_key(|transition| {
+            (
+                transition.from as u8,
+                transition.to as u8,
+                transition.probability,
+            )
+        });
+        Self {
+            state_transitions,
+            status_effects: damage.status_effect_probabilities,
+        }
+    }
+}
+
+// Damage as traps and fires give it: the id of a damage profile or the damage spelled out.  It's
+// resolved when their registry loads, once the damage profiles have.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DamageSource {
+    Named(String),
+    Spec(DamageSpec),
+}
+
+impl DamageSource {
+    pub fn resolve(&self, registry: &DamageRegistry) -> Result<Damage, DamageError> {
+        match self {
+            Self::Named(id) => registry
+                .get(id)
+                .cloned()
+                .ok_or_else(|| DamageError::UnknownProfile(id.clone())),
+            Self::Spec(spec) => Damage::try_from(spec.clone()).map_err(DamageError::Probability),
+        }
+    }
+}
+
+impl From<Damage> for DamageSource {
+    fn from(damage: Damage) -> Self {
+        Self::Spec(damage.into())
+    }
+}
+
+impl TryFrom<DamageSpec> for Damage {
+    type Error = ProbabilityError;
+
+    fn try_from(spec: DamageSpec) -> Result<Self, Self::Error> {
+        Damage::new(
+            spec.state_transitions
+                .into_iter()
+                .map(|transition| ((transition.from, transition.to), transition.probability))
+                .collect(),
+            spec.status_effects,
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BodyPartTreeNode {
     body_part: BodyPart,
@@ -188,52 +335,194 @@ impl BodyPartTreeNode {
         self.add(Self::leaf(body_part))
     }
 
-    pub fn take_damage_recursive(&mut self, rng: &mut RngResource, damage: Damage) {
-        let own_size = self.get_size() as f32;
-        let children_size = self.get_total_children_size() as f32;
-        let p_own_damage = own_size / (own_size + children_size);
-        let self_takes_damage = Probability::from_f32(p_own_damage)
-            .expect("p_own_damage is guaranteed to be a valid probability")
-            .roll(rng);
-        if self_takes_damage {
-            self.take_damage(rng, damage);
-        } else {
-            self.take_damage_child(rng, damage);
+    // Lands the damage on a single part, with bigger parts more likely to be hit.
+    pub fn take_damage(&mut self, rng: &mut RngResource, damage: &Damage) -> DamageOutcome {
+        let hit_location = self.choose_hit_location(rng);
+        self.get_part_mut(hit_location)
+            .expect("Hit locations are chosen from the body's own parts.")
+            .take_damage(rng, damage)
+    }
+
+    // The index, in get_menu_text order, of the part that's hit.
+    pub fn choose_hit_location(&self, rng: &mut RngResource) -> usize {
+        let mut parts = Vec::new();
+        self.collect_parts(BodyPartState::Okay, &mut parts);
+        let sizes = parts
+            .iter()
+            .map(|(part, _state)| part.get_size() as u32)
+            .collect::<Vec<_>>();
+        let total_size = sizes.iter().sum::<u32>();
+        if total_size == 0 {
+            return 0;
         }
+
+        let mut roll = rng
+            .0
+            .write()
+            .expect("If a thread somewhere panicked, we should panic.")
+            .gen_range(0..total_size);
+        for (idx, size) in sizes.into_iter().enumerate() {
+            if roll < size {
+                return idx;
+            }
+            roll -= size;
+        }
+        panic!("Unreachable -- the roll is less than the total size.");
     }
 
-    pub fn take_damage_child(&mut self, rng: &mut RngResource, damage: Damage) {
-        let children_size = self.get_total_children_size() as f32;
-        let choice: usize = Probability::choose(
-            rng,
-            self.children
-                .iter()
-                .enumerate()
-                .map(|(child_idx, child)| {
-                    (
-                        child_idx,
-                        Probability::from_f32((child.get_size() as f32) / children_size)
-                            .expect("Given the computation, this has to be ok."),
-                    )
-                })
-                .collect(),
-        )
-        .expect("Given the computation, this has to be ok.");
-        self.children[choice].take_damage_recursive(rng, damage);
+    // One turn of status effects: bleeding parts lose blood until they clot, infections can spread
+    // to a neighbouring part and cancer can make its part worse.
+    pub fn progress_status_effects(&mut self, rng: &mut RngResource) -> StatusEffectProgress {
+        let mut progress = StatusEffectProgress::default();
+        self.progress_status_effects_recursive(rng, false, &mut progress);
+        progress
     }
 
-    pub fn take_damage(&mut self, rng: &mut RngResource, damage: Damage) {
-        let state_transitions = damage
-            .get_state_transitions_from(self.get_state())
-            .expect("State transitions are checked when Damage is instantiated.");
+    // Returns this part's type if its infection spreads to the parent.  Infections only land once
+    // the part they land on has had its own turn, so they move at most one part a turn.
+    fn progress_status_effects_recursive(
+        &mut self,
+        rng: &mut RngResource,
+        has_parent: bool,
+        progress: &mut StatusEffectProgress,
+    ) -> Option<BodyPartType> {
+        let body_part_type = self.body_part.body_part_type.clone();
 
-        self.body_part.state = Probability::choose(rng, state_transitions)
-            .expect("State transitions are checked when Damage is instantiated.");
+        if self
+            .body_part
+            .statuses
+            .contains(&BodyPartStatusEffect::Bleeding)
+        {
+            progress.blood_loss = progress.blood_loss.saturating_add(BLEEDING_BLOOD_LOSS);
+            if roll_chance(rng, BLEEDING_STOP_CHANCE) {
+                self.body_part
+                    .statuses
+                    .remove(&BodyPartStatusEffect::Bleeding);
+                progress
+                    .changes
+                    .push(StatusEffectChange::StoppedBleeding(body_part_type.clone()));
+            }
+        }
 
-        self.body_part.statuses.insert(
-            Probability::choose(rng, damage.status_effect_probabilities)
-                .expect("State transitions are checked when Damage is instantiated."),
-        );
+        if self
+            .body_part
+            .statuses
+            .contains(&BodyPartStatusEffect::Cancerous)
+            && roll_chance(rng, CANCER_PROGRESSION_CHANCE)
+        {
+            let worsened = self.body_part.state.worsened();
+            if worsened != self.body_part.state {
+                self.body_part.state = worsened;
+                progress.changes.push(StatusEffectChange::CancerWorsened(
+                    body_part_type.clone(),
+                    worsened,
+                ));
+            }
+        }
+
+        // Infected and cancerous parts don't heal until they're treated.
+        if !self
+            .body_part
+            .statuses
+            .contains(&BodyPartStatusEffect::Infected)
+            && !self
+                .body_part
+                .statuses
+                .contains(&BodyPartStatusEffect::Cancerous)
+        {
+            let splinted = self
+                .body_part
+                .statuses
+                .contains(&BodyPartStatusEffect::Splinted);
+            let healing_chance = self.body_part.state.get_healing_chance(splinted);
+            if healing_chance > 0 && roll_chance(rng, healing_chance) {
+                self.body_part.state = BodyPartState::Okay;
+                self.body_part
+                    .statuses
+                    .remove(&BodyPartStatusEffect::Splinted);
+                progress
+                    .changes
+                    .push(StatusEffectChange::Healed(body_part_type.clone()));
+            }
+        }
+
+        let mut spreads_to_parent = false;
+        let mut spreads_to_child = None;
+        if self
+            .body_part
+            .statuses
+            .contains(&BodyPartStatusEffect::Infected)
+            && roll_chance(rng, INFECTION_SPREAD_CHANCE)
+        {
+            let n_neighbours = self.children.len() + has_parent as usize;
+            if n_neighbours > 0 {
+                let neighbour = rng
+                    .0
+                    .write()
+                    .expect("If a thread somewhere panicked, we should panic.")
+                    .gen_range(0..n_neighbours);
+                if neighbour < self.children.len() {
+                    spreads_to_child = Some(neighbour);
+                } else {
+                    spreads_to_parent = true;
+                }
+            }
+        }
+
+        let mut infected_by = Vec::new();
+        for child in self.children.iter_mut() {
+            if let Some(child_type) = child.progress_status_effects_recursive(rng, true, progress) {
+                infected_by.push(child_type);
+            }
+        }
+        if let Some(child_idx) = spreads_to_child {
+            let child = &mut self.children[child_idx];
+            if child
+                .body_part
+                .add_status_effect(BodyPartStatusEffect::Infected)
+            {
+                progress.changes.push(StatusEffectChange::InfectionSpread(
+                    body_part_type.clone(),
+                    child.body_part.body_part_type.clone(),
+                ));
+            }
+        }
+        for child_type in infected_by {
+            if self
+                .body_part
+                .add_status_effect(BodyPartStatusEffect::Infected)
+            {
+                progress.changes.push(StatusEffectChange::InfectionSpread(
+                    child_type,
+                    body_part_type.clone(),
+                ));
+            }
+        }
+
+        if spreads_to_parent {
+            Some(body_part_type)
+        } else {
+            None
+        }
+    }
+
+    // Parts are numbered in the same order as get_menu_text lists them.
+    pub fn get_part_mut(&mut self, index: usize) -> Option<&mut BodyPart> {
+        let mut remaining = index;
+        self.get_part_mut_recursive(&mut remaining)
+    }
+
+    fn get_part_mut_recursive(&mut self, remaining: &mut usize) -> Option<&mut BodyPart> {
+        if *remaining == 0 {
+            return Some(&mut self.body_part);
+        }
+        *remaining -= 1;
+        for child in self.children.iter_mut() {
+            if let Some(body_part) = child.get_part_mut_recursive(remaining) {
+                return Some(body_part);
+            }
+        }
+        None
     }
 
     pub fn has_status_effect(&self, status_effect: BodyPartStatusEffect) -> PartialBool {
@@ -253,6 +542,30 @@ impl BodyPartTreeNode {
         self.body_part.get_size()
     }
 
+    pub fn get_sight_radius(&self) -> u32 {
+        self.get_capabilities().get_sight_radius()
+    }
+
+    pub fn get_capabilities(&self) -> Capabilities {
+        let mut parts = Vec::new();
+        self.collect_parts(BodyPartState::Okay, &mut parts);
+        Capabilities::from_parts(&parts)
+    }
+
+    // Each part along with its state, which is no better than that of the part it's attached to,
+    // e.g. a hand on a destroyed arm is as good as destroyed.
+    fn collect_parts<'a>(
+        &'a self,
+        attached_to: BodyPartState,
+        parts: &mut Vec<(&'a BodyPart, BodyPartState)>,
+    ) {
+        let state = self.get_state().max(attached_to);
+        parts.push((&self.body_part, state));
+        for child in self.children.iter() {
+            child.collect_parts(state, parts);
+        }
+    }
+
     pub fn get_total_children_size(&self) -> u8 {
         self.children
             .iter()
@@ -303,6 +616,9 @@ pub struct BodyPart {
     body_part_type: BodyPartType,
     state: BodyPartState,
     statuses: HashSet<BodyPartStatusEffect>,
+    // Body plans can make a part bigger or smaller than its type's default, e.g. a serpent's body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u8>,
 }
 
 impl From<BodyPartType> for BodyPart {
@@ -321,6 +637,7 @@ impl BodyPart {
             body_part_type,
             state,
             statuses,
+            size: None,
         }
     }
 
@@ -335,8 +652,62 @@ impl BodyPart {
         Self::new(body_part_type, BodyPartState::Okay, status_effects)
     }
 
+    pub fn with_size(mut self, size: Option<u8>) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn get_body_part_type(&self) -> &BodyPartType {
+        &self.body_part_type
+    }
+
+    pub fn take_damage(&mut self, rng: &mut RngResource, damage: &Damage) -> DamageOutcome {
+        let previous_state = self.state;
+        let state_transitions = damage
+            .get_state_transitions_from(self.state)
+            .expect("State transitions are checked when Damage is instantiated.");
+        self.state = Probability::choose(rng, state_transitions)
+            .expect("State transitions are checked when Damage is instantiated.");
+
+        // Each status effect is rolled for separately, in a fixed order so that seeds repeat.
+        let mut status_effect_probabilities = damage
+            .status_effect_probabilities
+            .iter()
+            .collect::<Vec<_>>();
+        status_effect_probabilities.sort_by_key(|(status_effect, _p)| status_effect.to_string());
+        let mut new_status_effects = Vec::new();
+        for (status_effect, p) in status_effect_probabilities {
+            if p.roll(rng) && self.add_status_effect(*status_effect) {
+                new_status_effects.push(*status_effect);
+            }
+        }
+
+        DamageOutcome {
+            body_part_type: self.body_part_type.clone(),
+            previous_state,
+            state: self.state,
+            new_status_effects,
+        }
+    }
+
+    // Returns whether the treatment did anything, so that items aren't used up for nothing.
+    pub fn treat(&mut self, treatment: Treatment) -> bool {
+        match treatment {
+            Treatment::Bandage => self.statuses.remove(&BodyPartStatusEffect::Bleeding),
+            Treatment::Medicine => self.statuses.remove(&BodyPartStatusEffect::Infected),
+            Treatment::Splint => {
+                self.state == BodyPartState::Nonfunctional
+                    && self.add_status_effect(BodyPartStatusEffect::Splinted)
+            }
+        }
+    }
+
     pub fn get_size(&self) -> u8 {
-        self.body_part_type.get_size()
+        self.size.unwrap_or(self.body_part_type.get_size())
+    }
+
+    pub fn get_state(&self) -> BodyPartState {
+        self.state
     }
 
     pub fn has_status_effect(&self, status_effect: BodyPartStatusEffect) -> PartialBool {
@@ -369,9 +740,36 @@ impl BodyPart {
         }
     }
 
+    // Returns whether the part didn't already have the status effect.
+    pub fn add_status_effect(&mut self, status_effect: BodyPartStatusEffect) -> bool {
+        self.body_part_type
+            .is_status_effect_applicable(status_effect)
+            && self.statuses.insert(status_effect)
+    }
+
     pub fn has_any_status_effect(&self) -> bool {
         self.statuses.len() != 0
     }
+
+    pub fn get_sight_radius(&self, state: BodyPartState) -> u32 {
+        if self.body_part_type != BodyPartType::Head
+            || self.has_status_effect(BodyPartStatusEffect::Blind) == PartialBool::True
+        {
+            return 0;
+        }
+
+        match state {
+            BodyPartState::Okay => DEFAULT_SIGHT_RADIUS,
+            BodyPartState::Nonfunctional => DEFAULT_SIGHT_RADIUS / 2,
+            BodyPartState::Destroyed => 0,
+        }
+    }
+
+    pub fn can_hear(&self, state: BodyPartState) -> bool {
+        self.body_part_type == BodyPartType::Head
+            && self.has_status_effect(BodyPartStatusEffect::Deaf) != PartialBool::True
+            && state != BodyPartState::Destroyed
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -382,6 +780,9 @@ pub enum BodyPartType {
     Hand,
     Leg,
     Foot,
+    Tail,
+    Wing,
+    Tentacle,
 }
 
 impl BodyPartType {
@@ -393,6 +794,9 @@ impl BodyPartType {
             Self::Hand,
             Self::Leg,
             Self::Foot,
+            Self::Tail,
+            Self::Wing,
+            Self::Tentacle,
         ]
     }
 
@@ -404,6 +808,9 @@ impl BodyPartType {
             Self::Hand => "Hand",
             Self::Leg => "Leg",
             Self::Foot => "Foot",
+            Self::Tail => "Tail",
+            Self::Wing => "Wing",
+            Self::Tentacle => "Tentacle",
         }
         .to_string()
     }
@@ -416,15 +823,22 @@ impl BodyPartType {
             Self::Leg => DEFAULT_LEG_SIZE,
             Self::Hand => DEFAULT_HAND_SIZE,
             Self::Foot => DEFAULT_FOOT_SIZE,
+            Self::Tail => DEFAULT_TAIL_SIZE,
+            Self::Wing => DEFAULT_WING_SIZE,
+            Self::Tentacle => DEFAULT_TENTACLE_SIZE,
         }
     }
 
+    pub fn is_limb(&self) -> bool {
+        !matches!(self, Self::Body | Self::Head)
+    }
+
     pub fn is_status_effect_applicable(&self, status_effect: BodyPartStatusEffect) -> bool {
         status_effect.get_applicable_bodyparts().contains(&self)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum BodyPartState {
     Okay,
     Nonfunctional,
@@ -432,6 +846,31 @@ pub enum BodyPartState {
 }
 
 impl BodyPartState {
+    // Out of 2, so that nonfunctional parts still do half the job.
+    pub fn get_function(&self) -> u32 {
+        match self {
+            Self::Okay => 2,
+            Self::Nonfunctional => 1,
+            Self::Destroyed => 0,
+        }
+    }
+
+    // Out of 100 each turn.  Destroyed parts never grow back.
+    pub fn get_healing_chance(&self, splinted: bool) -> u8 {
+        match self {
+            Self::Okay | Self::Destroyed => 0,
+            Self::Nonfunctional if splinted => SPLINT_HEALING_CHANCE,
+            Self::Nonfunctional => NATURAL_HEALING_CHANCE,
+        }
+    }
+
+    pub fn worsened(&self) -> Self {
+        match self {
+            Self::Okay => Self::Nonfunctional,
+            Self::Nonfunctional | Self::Destroyed => Self::Destroyed,
+        }
+    }
+
     pub fn to_string(&self) -> String {
         match self {
             Self::Okay => "Okay",
@@ -449,12 +888,18 @@ pub enum BodyPartStatusEffect {
     Bleeding,
     Infected,
     Cancerous,
+    Burned,
+    Splinted,
 }
 
 impl BodyPartStatusEffect {
     pub fn get_applicable_bodyparts(&self) -> Vec<BodyPartType> {
         match self {
             Self::Blind | Self::Deaf => vec![BodyPartType::Head],
+            Self::Splinted => BodyPartType::all()
+                .into_iter()
+                .filter(|body_part_type| body_part_type.is_limb())
+                .collect(),
             _ => BodyPartType::all(),
         }
     }
@@ -473,11 +918,173 @@ impl BodyPartStatusEffect {
             Self::Bleeding => "Bleeding",
             Self::Infected => "Infected",
             Self::Cancerous => "Cancerous",
+            Self::Burned => "Burned",
+            Self::Splinted => "Splinted",
         }
         .to_string()
     }
 }
 
+// What a body can still do given the state of its parts.  Bodies without legs or feet, like
+// serpents, get around at the normal speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    movement_ticks: u8,
+    can_manipulate: bool,
+    sight_radius: u32,
+    can_hear: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            movement_ticks: MOVEMENT_TICKS,
+            can_manipulate: true,
+            sight_radius: DEFAULT_SIGHT_RADIUS,
+            can_hear: true,
+        }
+    }
+}
+
+impl Capabilities {
+    pub fn from_parts(parts: &[(&BodyPart, BodyPartState)]) -> Self {
+        let locomotion = parts
+            .iter()
+            .filter(|(part, _state)| {
+                matches!(part.body_part_type, BodyPartType::Leg | BodyPartType::Foot)
+            })
+            .map(|(_part, state)| state.get_function())
+            .collect::<Vec<_>>();
+        let function = locomotion.iter().sum::<u32>();
+        let movement_ticks = if locomotion.is_empty() {
+            MOVEMENT_TICKS
+        } else if function == 0 {
+            CRAWLING_MOVEMENT_TICKS
+        } else {
+            let full_function = 2 * locomotion.len() as u32;
+            (MOVEMENT_TICKS as u32 * full_function / function).min(CRAWLING_MOVEMENT_TICKS as u32)
+                as u8
+        };
+
+        Self {
+            movement_ticks,
+            can_manipulate: parts.iter().any(|(part, state)| {
+                part.body_part_type == BodyPartType::Hand && *state == BodyPartState::Okay
+            }),
+            sight_radius: parts
+                .iter()
+                .map(|(part, state)| part.get_sight_radius(*state))
+                .max()
+                .unwrap_or(0),
+            can_hear: parts.iter().any(|(part, state)| part.can_hear(*state)),
+        }
+    }
+
+    pub fn get_movement_ticks(&self) -> u8 {
+        self.movement_ticks
+    }
+
+    // Stepping onto rough terrain takes as many times longer as pathfinding weighs the step.
+    pub fn get_movement_ticks_onto(&self, movement_cost: u32) -> u8 {
+        u8::try_from((self.movement_ticks as u32).saturating_mul(movement_cost)).unwrap_or(u8::MAX)
+    }
+
+    // Opening doors and carrying things both take a working hand.
+    pub fn can_manipulate(&self) -> bool {
+        self.can_manipulate
+    }
+
+    pub fn get_sight_radius(&self) -> u32 {
+        self.sight_radius
+    }
+
+    pub fn can_hear(&self) -> bool {
+        self.can_hear
+    }
+}
+
+// What a single hit did to the part it landed on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DamageOutcome {
+    body_part_type: BodyPartType,
+    previous_state: BodyPartState,
+    state: BodyPartState,
+    new_status_effects: Vec<BodyPartStatusEffect>,
+}
+
+impl DamageOutcome {
+    pub fn get_body_part_type(&self) -> &BodyPartType {
+        &self.body_part_type
+    }
+
+    pub fn get_state(&self) -> BodyPartState {
+        self.state
+    }
+
+    pub fn get_new_status_effects(&self) -> &Vec<BodyPartStatusEffect> {
+        &self.new_status_effects
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut message = format!(
+            "Your {} is hit.",
+            self.body_part_type.to_string().to_lowercase()
+        );
+        if self.state != self.previous_state {
+            message.push_str(&format!(
+                " It is now {}.",
+                self.state.to_string().to_lowercase()
+            ));
+        }
+        if !self.new_status_effects.is_empty() {
+            let status_effects = self
+                .new_status_effects
+                .iter()
+                .map(|status_effect| status_effect.to_string().to_lowercase())
+                .collect::<Vec<_>>();
+            message.push_str(&format!(" It is {}.", status_effects.join(", ")));
+        }
+        message
+    }
+}
+
+// What a turn of status effects did to a body.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusEffectProgress {
+    pub blood_loss: u8,
+    pub changes: Vec<StatusEffectChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusEffectChange {
+    StoppedBleeding(BodyPartType),
+    // From one part to another.
+    InfectionSpread(BodyPartType, BodyPartType),
+    CancerWorsened(BodyPartType, BodyPartState),
+    Healed(BodyPartType),
+}
+
+impl StatusEffectChange {
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::StoppedBleeding(part) => {
+                format!("Your {} stops bleeding.", part.to_string().to_lowercase())
+            }
+            Self::InfectionSpread(from, to) => format!(
+                "The infection in your {} spreads to your {}.",
+                from.to_string().to_lowercase(),
+                to.to_string().to_lowercase()
+            ),
+            Self::CancerWorsened(part, state) => format!(
+                "The cancer in your {} grows. It is now {}.",
+                part.to_string().to_lowercase(),
+                state.to_string().to_lowercase()
+            ),
+            Self::Healed(part) => format!("Your {} has healed.", part.to_string().to_lowercase()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PartialBool {
     True,
@@ -565,10 +1172,39 @@ pub enum ProbabilityError {
     OutOfBounds,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DamageError {
+    Probability(ProbabilityError),
+    UnknownProfile(String),
+}
+
+impl fmt::Display for DamageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Probability(e) => e.fmt(f),
+            Self::UnknownProfile(id) => write!(f, "There's no damage profile named {}.", id),
+        }
+    }
+}
+
+impl fmt::Display for ProbabilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds => write!(f, "Probabilities must be between 0 and 100."),
+        }
+    }
+}
+
 // End Structs
 
 // Helper Functions
 
+fn roll_chance(rng: &mut RngResource, chance: u8) -> bool {
+    Probability::new(chance)
+        .expect("Chances are constants between 0 and 100.")
+        .roll(rng)
+}
+
 // End Helper Functions
 
 #[cfg(test)]
@@ -620,6 +1256,173 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_body_part_sight_radius() {
+        let humanoid = BodyPartTreeNode::new_humanoid();
+        assert_eq!(humanoid.get_sight_radius(), DEFAULT_SIGHT_RADIUS);
+
+        let blind_head: BodyPartTreeNode = BodyPart::new_with_status_effects(
+            BodyPartType::Head,
+            vec![BodyPartStatusEffect::Blind].into_iter().collect(),
+        )
+        .into();
+        let mut body = BodyPartTreeNode::new(BodyPartType::Body.into(), vec![blind_head]);
+        assert_eq!(body.get_sight_radius(), 0);
+
+        body.add_leaf(BodyPart::new(
+            BodyPartType::Head,
+            BodyPartState::Nonfunctional,
+            HashSet::new(),
+        ));
+        assert_eq!(body.get_sight_radius(), DEFAULT_SIGHT_RADIUS / 2);
+
+        let headless = BodyPartTreeNode::new(
+            BodyPartType::Body.into(),
+            vec![BodyPartTreeNode::new_arm_and_hand()],
+        );
+        assert_eq!(headless.get_sight_radius(), 0);
+    }
+
+    #[test]
+    fn test_capabilities_follow_body_part_states() {
+        let humanoid = BodyPartTreeNode::new_humanoid();
+        assert_eq!(humanoid.get_capabilities(), Capabilities::default());
+
+        // A destroyed leg takes its foot with it and halves how fast the body gets around.
+        let destroyed_leg = BodyPartTreeNode::new(
+            BodyPart::new(BodyPartType::Leg, BodyPartState::Destroyed, HashSet::new()),
+            vec![BodyPartType::Foot.into()],
+        );
+        let body = BodyPartTreeNode::new(
+            BodyPartType::Body.into(),
+            vec![destroyed_leg.clone(), BodyPartTreeNode::new_leg_and_foot()],
+        );
+        assert_eq!(
+            body.get_capabilities().get_movement_ticks(),
+            2 * MOVEMENT_TICKS
+        );
+        assert!(!body.get_capabilities().can_manipulate());
+        assert!(!body.get_capabilities().can_hear());
+
+        let body = BodyPartTreeNode::new(
+            BodyPartType::Body.into(),
+            vec![destroyed_leg.clone(), destroyed_leg],
+        );
+        assert_eq!(
+            body.get_capabilities().get_movement_ticks(),
+            CRAWLING_MOVEMENT_TICKS
+        );
+
+        // Hands only work on working arms.
+        let limp_arm = BodyPartTreeNode::new(
+            BodyPart::new(
+                BodyPartType::Arm,
+                BodyPartState::Nonfunctional,
+                HashSet::new(),
+            ),
+            vec![BodyPartType::Hand.into()],
+        );
+        let mut body = BodyPartTreeNode::new(BodyPartType::Body.into(), vec![limp_arm]);
+        assert!(!body.get_capabilities().can_manipulate());
+        body.add(BodyPartTreeNode::new_arm_and_hand());
+        assert!(body.get_capabilities().can_manipulate());
+
+        let deaf_head: BodyPartTreeNode = BodyPart::new_with_status_effects(
+            BodyPartType::Head,
+            vec![BodyPartStatusEffect::Deaf].into_iter().collect(),
+        )
+        .into();
+        body.add(deaf_head);
+        assert!(!body.get_capabilities().can_hear());
+        assert_eq!(
+            body.get_capabilities().get_sight_radius(),
+            DEFAULT_SIGHT_RADIUS
+        );
+        body.add_leaf(BodyPartType::Head.into());
+        assert!(body.get_capabilities().can_hear());
+
+        // Legless bodies slither along at the usual speed.
+        let serpent = BodyPartTreeNode::new(
+            BodyPartType::Body.into(),
+            vec![BodyPartType::Head.into(), BodyPartType::Tail.into()],
+        );
+        assert_eq!(
+            serpent.get_capabilities().get_movement_ticks(),
+            MOVEMENT_TICKS
+        );
+
+        assert_eq!(
+            humanoid.get_capabilities().get_movement_ticks_onto(3),
+            3 * MOVEMENT_TICKS
+        );
+        assert_eq!(
+            humanoid
+                .get_capabilities()
+                .get_movement_ticks_onto(u32::MAX),
+            u8::MAX
+        );
+    }
+
+    #[test]
+    fn test_status_effects_progress_each_turn() {
+        let mut rng = RngResource::seeded(7);
+        let with_status_effect = |body_part_type, status_effect| {
+            BodyPart::new_with_status_effects(
+                body_part_type,
+                vec![status_effect].into_iter().collect(),
+            )
+        };
+
+        // Bleeding costs blood every turn until it stops, and then it stays stopped.
+        let mut bleeding: BodyPartTreeNode =
+            with_status_effect(BodyPartType::Arm, BodyPartStatusEffect::Bleeding).into();
+        for _ in 0..1000 {
+            if bleeding.has_status_effect(BodyPartStatusEffect::Bleeding) != PartialBool::True {
+                break;
+            }
+            let progress = bleeding.progress_status_effects(&mut rng);
+            assert_eq!(progress.blood_loss, BLEEDING_BLOOD_LOSS);
+        }
+        assert_eq!(
+            bleeding.has_status_effect(BodyPartStatusEffect::Bleeding),
+            PartialBool::False
+        );
+        assert_eq!(bleeding.progress_status_effects(&mut rng).blood_loss, 0);
+
+        // Infections only move one part a turn, but eventually reach the whole body.
+        let infected = BodyPartTreeNode::new(
+            BodyPartType::Body.into(),
+            vec![BodyPartTreeNode::new(
+                BodyPartType::Arm.into(),
+                vec![with_status_effect(BodyPartType::Hand, BodyPartStatusEffect::Infected).into()],
+            )],
+        );
+        for _ in 0..200 {
+            let mut body = infected.clone();
+            body.progress_status_effects(&mut rng);
+            assert_eq!(
+                body.has_status_effect(BodyPartStatusEffect::Infected),
+                PartialBool::False
+            );
+        }
+        let mut body = infected.clone();
+        for _ in 0..1000 {
+            body.progress_status_effects(&mut rng);
+        }
+        assert!(body.has_status_effect_recursive(BodyPartStatusEffect::Infected));
+
+        // Cancer only ever makes things worse.
+        let mut cancerous: BodyPartTreeNode =
+            with_status_effect(BodyPartType::Leg, BodyPartStatusEffect::Cancerous).into();
+        let mut state = cancerous.get_state();
+        for _ in 0..1000 {
+            cancerous.progress_status_effects(&mut rng);
+            assert!(cancerous.get_state() as u8 >= state as u8);
+            state = cancerous.get_state();
+        }
+        assert_eq!(state, BodyPartState::Destroyed);
+    }
+
     #[test]
     fn test_action_clock_ticks() {
         let action_clock = ActionClockComponent(0);
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use super::map::{MapError, MapTileComponent, TileAppearance, TileGrid, TileLocation};
+use super::resources::{GameState, LoadedFont};
+use crate::constants::*;
+
+pub struct ChunkPlugin;
+
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        let compound_state = || {
+            in_state(GameState::Exploring)
+                .or_else(in_state(GameState::NonPlayerTurns))
+                .or_else(in_state(GameState::EditingMap))
+        };
+        app.add_systems(
+            Update,
+            stream_chunks_system
+                .run_if(compound_state().and_then(resource_exists::<RenderedTileGrid>())),
+        );
+    }
+}
+
+// Resources
+
+// The appearance of every tile on the layer being shown.  Only the chunks near the camera have
+// tile entities; the rest are spawned from here as the camera reaches them.
+#[derive(Debug, Clone, PartialEq, Eq, Resource)]
+pub struct RenderedTileGrid {
+    grid: TileGrid,
+    bound_state: GameState,
+}
+
+impl RenderedTileGrid {
+    pub fn new(grid: TileGrid, bound_state: GameState) -> Self {
+        Self { grid, bound_state }
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.height()
+    }
+
+    pub fn get(&self, location: TileLocation) -> Option<TileAppearance> {
+        if location.i < 0 || location.j < 0 {
+            return None;
+        }
+        self.grid.get(location.i as usize, location.j as usize)
+    }
+
+    pub fn update(
+        &mut self,
+        location: TileLocation,
+        appearance: TileAppearance,
+    ) -> Result<(), MapError> {
+        if location.i < 0 || location.j < 0 {
+            return Err(MapError::OutOfBounds);
+        }
+        self.grid
+            .update(location.i as usize, location.j as usize, appearance)
+    }
+
+    fn render_chunk(&self, commands: &mut Commands, font: Handle<Font>, chunk: ChunkLocation) {
+        for location in chunk.get_tile_locations() {
+            if let Some(appearance) = self.get(location) {
+                appearance.render(
+                    &mut commands.spawn(MapTileComponent(location)),
+                    font.clone(),
+                    self.bound_state,
+                    TileGrid::tile_to_world_coordinates(location),
+                );
+            }
+        }
+    }
+}
+
+// End Resources
+
+// Systems
+
+fn stream_chunks_system(
+    mut commands: Commands,
+    rendered_grid: Res<RenderedTileGrid>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    window_query: Query<&Window>,
+    tile_query: Query<(Entity, &MapTileComponent)>,
+    font: Res<LoadedFont>,
+) {
+    let (camera_transform, projection) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let window = match window_query.get_single() {
+        Ok(window) => window,
+        Err(_) => return,
+    };
+    let half_size = Vec2::new(window.width(), window.height()) * projection.scale / 2.;
+    let wanted = get_chunks_in_view(
+        camera_transform.translation.truncate(),
+        half_size,
+        rendered_grid.grid.width(),
+        rendered_grid.grid.height(),
+    );
+
+    let mut spawned: HashMap<ChunkLocation, Vec<Entity>> = HashMap::new();
+    for (entity, MapTileComponent(location)) in tile_query.iter() {
+        spawned
+            .entry(ChunkLocation::from_tile_location(*location))
+            .or_default()
+            .push(entity);
+    }
+
+    for (chunk, entities) in spawned.iter() {
+        if !wanted.contains(chunk) {
+            entities
+                .iter()
+                .for_each(|entity| commands.entity(*entity).despawn_recursive());
+        }
+    }
+    for chunk in wanted {
+        if !spawned.contains_key(&chunk) {
+            rendered_grid.render_chunk(&mut commands, font.0.clone(), chunk);
+        }
+    }
+}
+
+// End Systems
+
+// Structs
+
+// A square of CHUNK_SIZE_IN_TILES tiles on a side, counted from the bottom left of the layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkLocation {
+    i: i32,
+    j: i32,
+}
+
+impl ChunkLocation {
+    pub fn new(i: i32, j: i32) -> Self {
+        Self { i, j }
+    }
+
+    pub fn from_tile_location(location: TileLocation) -> Self {
+        Self::new(
+            location.i.div_euclid(CHUNK_SIZE_IN_TILES),
+            location.j.div_euclid(CHUNK_SIZE_IN_TILES),
+        )
+    }
+
+    pub fn get_tile_locations(&self) -> Vec<TileLocation> {
+        let (start_i, start_j) = (self.i * CHUNK_SIZE_IN_TILES, self.j * CHUNK_SIZE_IN_TILES);
+        (start_i..start_i + CHUNK_SIZE_IN_TILES)
+            .flat_map(|i| {
+                (start_j..start_j + CHUNK_SIZE_IN_TILES).map(move |j| TileLocation::new(i, j))
+            })
+            .collect()
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+// Every chunk of a width x height layer that overlaps the camera's view, plus a margin so that
+// chunks are ready before they scroll into view.
+pub fn get_chunks_in_view(
+    center: Vec2,
+    half_size: Vec2,
+    width: usize,
+    height: usize,
+) -> HashSet<ChunkLocation> {
+    if width == 0 || height == 0 {
+        return HashSet::new();
+    }
+    let bottom_left = TileGrid::world_to_tile_coordinates(center - half_size);
+    let top_right = TileGrid::world_to_tile_coordinates(center + half_size);
+    let min_chunk = ChunkLocation::from_tile_location(bottom_left);
+    let max_chunk = ChunkLocation::from_tile_location(top_right);
+    let last_chunk =
+        ChunkLocation::from_tile_location(TileLocation::new(width as i32 - 1, height as i32 - 1));
+
+    let i_range =
+        (min_chunk.i - CHUNK_MARGIN).max(0)..=(max_chunk.i + CHUNK_MARGIN).min(last_chunk.i);
+    let j_range =
+        (min_chunk.j - CHUNK_MARGIN).max(0)..=(max_chunk.j + CHUNK_MARGIN).min(last_chunk.j);
+    i_range
+        .flat_map(|i| j_range.clone().map(move |j| ChunkLocation::new(i, j)))
+        .collect()
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_location_covers_its_tiles() {
+        let chunk = ChunkLocation::from_tile_location(TileLocation::new(CHUNK_SIZE_IN_TILES, 3));
+        assert_eq!(chunk, ChunkLocation::new(1, 0));
+        assert_eq!(
+            ChunkLocation::from_tile_location(TileLocation::new(-1, 0)),
+            ChunkLocation::new(-1, 0)
+        );
+
+        let tiles = chunk.get_tile_locations();
+        assert_eq!(
+            tiles.len(),
+            (CHUNK_SIZE_IN_TILES * CHUNK_SIZE_IN_TILES) as usize
+        );
+        assert!(tiles
+            .iter()
+            .all(|tile| ChunkLocation::from_tile_location(*tile) == chunk));
+    }
+
+    #[test]
+    fn test_chunks_in_view_are_culled_to_the_camera_and_layer() {
+        let chunk_width = (CHUNK_SIZE_IN_TILES as usize * TILE_WIDTH) as f32;
+        let chunk_height = (CHUNK_SIZE_IN_TILES as usize * TILE_HEIGHT) as f32;
+        let size = 500;
+        // A camera a little smaller than a chunk, centred on chunk (10, 10).
+        let center = Vec2::new(10.5 * chunk_width, 10.5 * chunk_height);
+        let half_size = Vec2::new(chunk_width, chunk_height) * 0.4;
+
+        let chunks = get_chunks_in_view(center, half_size, size, size);
+        let margin = CHUNK_MARGIN as usize;
+        assert_eq!(chunks.len(), (2 * margin + 1) * (2 * margin + 1));
+        assert!(chunks.contains(&ChunkLocation::new(10, 10)));
+        assert!(!chunks.contains(&ChunkLocation::new(10 + CHUNK_MARGIN + 1, 10)));
+
+        // Nothing is spawned past the edges of the layer.
+        let chunks = get_chunks_in_view(Vec2::ZERO, half_size, size, size);
+        assert!(chunks.iter().all(|chunk| chunk.i >= 0 && chunk.j >= 0));
+        assert!(chunks.contains(&ChunkLocation::new(0, 0)));
+        let chunks = get_chunks_in_view(Vec2::new(-1e6, -1e6), half_size, size, size);
+        assert!(chunks.is_empty());
+        assert!(get_chunks_in_view(center, half_size, 0, 0).is_empty());
+    }
+}
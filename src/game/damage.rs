@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use super::character::{Damage, DamageError, DamageSpec};
+use crate::assets::{FileSystem, FileSystemError};
+use crate::constants::*;
+
+static DAMAGE_REGISTRY: OnceLock<DamageRegistry> = OnceLock::new();
+
+// Structs
+
+// Traps, fires and attacks can name a damage profile by id in place of spelling the damage out, see
+// DamageSource.  Profiles themselves have to spell it out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DamageProfile {
+    name: String,
+    damage: DamageSpec,
+}
+
+impl DamageProfile {
+    pub fn new(name: &str, damage: Damage) -> Self {
+        Self {
+            name: name.to_string(),
+            damage: damage.into(),
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+// Profiles are checked once, as they load, and kept as the damage they spell out.
+#[derive(Debug, Clone)]
+pub struct DamageRegistry(HashMap<String, Damage>);
+
+impl DamageRegistry {
+    pub fn new(damages: HashMap<String, Damage>) -> Self {
+        Self(damages)
+    }
+
+    pub fn from_directory(directory_name: &str) -> Result<Self, DamageLoadError> {
+        let profiles: HashMap<String, DamageProfile> =
+            FileSystem::new_directory(directory_name).load_all_by_id()?;
+        let damages = profiles
+            .into_iter()
+            .map(|(id, profile)| match Damage::try_from(profile.damage) {
+                Ok(damage) => Ok((id, damage)),
+                Err(e) => Err(DamageLoadError::InvalidDamage(
+                    id,
+                    DamageError::Probability(e),
+                )),
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(Self::new(damages))
+    }
+
+    // Loaded up front by load_assets_system.
+    pub fn global() -> &'static Self {
+        DAMAGE_REGISTRY.get_or_init(|| {
+            Self::from_directory(DAMAGE_DIRECTORY)
+                .expect("We should be able to load the damage profiles.")
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Damage> {
+        self.0.get(id)
+    }
+}
+
+// Why a registry holding damage couldn't load, naming the spec whose damage was bad.  Shared by the
+// damage, trap and terrain registries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DamageLoadError {
+    FileSystem(FileSystemError),
+    InvalidDamage(String, DamageError),
+}
+
+impl From<FileSystemError> for DamageLoadError {
+    fn from(value: FileSystemError) -> Self {
+        Self::FileSystem(value)
+    }
+}
+
+impl fmt::Display for DamageLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FileSystem(e) => write!(f, "{:?}", e),
+            Self::InvalidDamage(id, e) => write!(f, "{}: {}", id, e),
+        }
+    }
+}
+
+// End Structs
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs;
+
+    use super::*;
+    use crate::game::character::{
+        BodyPart, BodyPartState, BodyPartStatusEffect, BodyPartTreeNode, BodyPartType,
+        DamageSource, PartialBool, ProbabilityError,
+    };
+    use crate::game::resources::RngResource;
+    use crate::game::trap::TrapRegistry;
+
+    #[test]
+    fn test_damage_registry_loads_built_in_profiles() {
+        let registry = DamageRegistry::global();
+        for id in ["slash", "pierce", "blunt", "burn", "acid"] {
+            assert!(registry.get(id).is_some(), "Missing damage profile {}", id);
+        }
+
+        // Specs can name a profile instead of spelling the damage out.
+        assert_eq!(
+            TrapRegistry::global()
+                .get(SPIKE_TRAP)
+                .expect("Spike traps are built in.")
+                .get_damage(),
+            registry.get("pierce").unwrap().clone()
+        );
+        let resolve = |json: &str| {
+            serde_json::from_str::<DamageSource>(json)
+                .unwrap()
+                .resolve(registry)
+        };
+        assert!(resolve("\"slash\"").is_ok());
+        assert_eq!(
+            resolve("\"tickle\""),
+            Err(DamageError::UnknownProfile("tickle".to_string()))
+        );
+        assert!(serde_json::from_str::<Damage>("\"slash\"").is_err());
+        assert!(
+            serde_json::from_str::<DamageProfile>(r#"{"name": "slice", "damage": "slash"}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_damage_registry_names_bad_profiles() {
+        let directory = std::env::temp_dir().join("test_damage_registry_names_bad_profiles");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("overkill.json"),
+            r#"{"name": "overkill", "damage": {"state_transitions": [
+                {"from": "Okay", "to": "Nonfunctional", "probability": 60},
+                {"from": "Okay", "to": "Destroyed", "probability": 60}
+            ]}}"#,
+        )
+        .unwrap();
+
+        let registry = DamageRegistry::from_directory(directory.to_str().unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(
+            registry.unwrap_err(),
+            DamageLoadError::InvalidDamage(
+                "overkill".to_string(),
+                DamageError::Probability(ProbabilityError::OutOfBounds)
+            )
+        );
+    }
+
+    #[test]
+    fn test_hits_land_on_parts_by_size() {
+        let mut rng = RngResource::seeded(7);
+        let body =
+            BodyPartTreeNode::new(BodyPartType::Body.into(), vec![BodyPartType::Head.into()]);
+        let head_hits = (0..1000)
+            .filter(|_| body.choose_hit_location(&mut rng) == 1)
+            .count();
+        // The head is a fifth of the body's size.
+        assert!(
+            (100..300).contains(&head_hits),
+            "The head was hit {} times",
+            head_hits
+        );
+
+        // Blunt damage never makes anything bleed, and a destroyed part stays destroyed.
+        let blunt = DamageRegistry::global().get("blunt").unwrap();
+        let mut arm: BodyPartTreeNode =
+            BodyPart::new(BodyPartType::Arm, BodyPartState::Destroyed, HashSet::new()).into();
+        for _ in 0..100 {
+            let outcome = arm.take_damage(&mut rng, blunt);
+            assert_eq!(outcome.get_body_part_type(), &BodyPartType::Arm);
+            assert_eq!(outcome.get_state(), BodyPartState::Destroyed);
+            assert!(outcome.get_new_status_effects().is_empty());
+        }
+        assert_eq!(
+            arm.has_status_effect(BodyPartStatusEffect::Bleeding),
+            PartialBool::False
+        );
+    }
+}
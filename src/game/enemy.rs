@@ -2,7 +2,10 @@ use bevy::{ecs::system::EntityCommands, prelude::*};
 use serde::{Deserialize, Serialize};
 
 use super::{
-    character::{ActionClockComponent, LocationComponent},
+    body_plan::BodyPlanRegistry,
+    character::{
+        ActionClockComponent, BloodComponent, BodyComponent, Capabilities, LocationComponent,
+    },
     map::MapLocation,
 };
 use crate::constants::*;
@@ -49,27 +52,44 @@ impl AIComponent {
 // End Components
 
 // Structs
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Enemy {
+    pub name: String,
+    // Humanoid when left out, see BodyPlanRegistry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_plan: Option<String>,
     commands: Vec<AICommand>,
 }
 
 impl Enemy {
-    pub fn new(commands: Vec<AICommand>) -> Self {
-        Self { commands }
+    pub fn new(name: String, commands: Vec<AICommand>) -> Self {
+        Self {
+            name,
+            body_plan: None,
+            commands,
+        }
+    }
+
+    pub fn get_body_plan(&self) -> Option<&str> {
+        self.body_plan.as_deref()
     }
 
     pub fn spawn(&self, entity_commands: &mut EntityCommands, location: MapLocation) -> Entity {
         entity_commands
             .insert(EnemyComponent)
+            .insert(Name::new(self.name.clone()))
             .insert(AIComponent::new(self.commands.clone()))
             .insert(ActionClockComponent(0))
             .insert(LocationComponent(location))
+            .insert(BodyComponent(
+                BodyPlanRegistry::global().build(self.body_plan.as_deref()),
+            ))
+            .insert(BloodComponent::default())
             .id()
     }
 }
 
-#[derive(Component, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AICommand {
     Wait(u8),
     Move(Direction),
@@ -77,10 +97,10 @@ pub enum AICommand {
 }
 
 impl AICommand {
-    pub fn get_ticks(&self) -> u8 {
+    pub fn get_ticks(&self, capabilities: &Capabilities) -> u8 {
         match self {
             Self::Wait(ticks) => *ticks,
-            Self::Move(_direction) => MOVEMENT_TICKS,
+            Self::Move(_direction) => capabilities.get_movement_ticks(),
             Self::Speak(_s) => AI_SPEAK_TICKS,
         }
     }
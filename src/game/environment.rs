@@ -0,0 +1,392 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+
+use super::character::{BodyComponent, LocationComponent};
+use super::events::{DamageEvent, Direction, TileChangedEvent};
+use super::map::{
+    AsciiTileAppearance, ColorCode, Map, MapLocation, SurfaceTile, Tile, TileGrid, TileLocation,
+};
+use super::player::PlayerComponent;
+use super::resources::{GameState, LoadedFont, LoadedMap, RngResource};
+use super::tileset::RenderSettings;
+use super::vision::FieldOfView;
+use crate::constants::*;
+use crate::ui::LogState;
+
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        let generalized_exploring =
+            || in_state(GameState::Exploring).or_else(in_state(GameState::NonPlayerTurns));
+        app.insert_resource(Smoke::default())
+            .add_systems(OnEnter(GameState::LoadingMap), clear_smoke_system)
+            .add_systems(OnEnter(GameState::NonPlayerTurns), environment_turn_system)
+            .add_systems(
+                Update,
+                render_smoke_system.run_if(generalized_exploring().and_then(
+                    resource_changed::<Smoke>().or_else(resource_changed::<FieldOfView>()),
+                )),
+            );
+    }
+}
+
+// Components
+
+#[derive(Component, Clone, Copy)]
+pub struct SmokeComponent;
+
+// End Components
+
+// Resources
+
+// How thick the smoke is on each tile.  Smoke drifts through anything that isn't opaque and thins
+// out over time.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub struct Smoke(HashMap<MapLocation, u8>);
+
+impl Smoke {
+    pub fn get_density(&self, location: MapLocation) -> u8 {
+        self.0.get(&location).cloned().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, location: MapLocation, density: u8) {
+        if density == 0 {
+            return;
+        }
+        let current = self.0.entry(location).or_insert(0);
+        *current = current.saturating_add(density).min(MAX_SMOKE_DENSITY);
+    }
+
+    pub fn is_opaque(&self, location: MapLocation) -> bool {
+        self.get_density(location) >= SMOKE_OPAQUE_DENSITY
+    }
+
+    pub fn get_locations(&self, map_layer: usize) -> Vec<TileLocation> {
+        self.0
+            .keys()
+            .filter(|location| location.get_map_layer() == map_layer)
+            .map(|location| location.get_tile_location())
+            .collect()
+    }
+
+    pub fn get_opaque_locations(&self, map_layer: usize) -> HashSet<TileLocation> {
+        self.get_locations(map_layer)
+            .into_iter()
+            .filter(|tile_location| self.is_opaque(MapLocation::new(map_layer, *tile_location)))
+            .collect()
+    }
+
+    // Every fire on the map gives off its smoke.
+    pub fn emit(&mut self, map: &Map) {
+        for (location, tile) in get_location_and_tile_vector(map) {
+            if let Some(fire) = tile.get_surface().get_terrain().get_fire() {
+                self.add(location, fire.get_smoke());
+            }
+        }
+    }
+
+    // Each patch of smoke thins out and then sends half of what's left to a random neighbour that
+    // isn't opaque.
+    pub fn drift(&mut self, map: &Map, rng: &mut RngResource) {
+        let mut patches = self.0.drain().collect::<Vec<_>>();
+        // Sorted so that the same seed always drifts the same way.
+        patches.sort_by_key(|(location, _density)| {
+            (location.get_map_layer(), location.get_tile_location())
+        });
+
+        let mut raw_rng = rng
+            .0
+            .write()
+            .expect("If a thread somewhere panicked, we should panic.");
+        for (location, density) in patches {
+            let density = density.saturating_sub(SMOKE_DECAY);
+            let neighbour = Direction::all()
+                .choose(&mut *raw_rng)
+                .map(|direction| {
+                    let mut neighbour = location;
+                    neighbour.translate(direction.as_tile_location());
+                    neighbour
+                })
+                .filter(|neighbour| map.get(*neighbour).map_or(false, |tile| !tile.is_opaque()));
+            match neighbour {
+                Some(neighbour) => {
+                    let drifting = (density + 1) / 2;
+                    self.add(neighbour, drifting);
+                    self.add(location, density - drifting);
+                }
+                None => self.add(location, density),
+            }
+        }
+    }
+}
+
+// End Resources
+
+// Systems
+
+fn clear_smoke_system(mut smoke: ResMut<Smoke>) {
+    *smoke = Smoke::default();
+}
+
+// The world's turn: fire spreads and burns out, smoke drifts and whoever is standing in a fire
+// gets burned.
+fn environment_turn_system(
+    mut map: ResMut<LoadedMap>,
+    mut smoke: ResMut<Smoke>,
+    mut rng: ResMut<RngResource>,
+    mut log: ResMut<LogState>,
+    character_query: Query<
+        (Entity, &LocationComponent, Option<&PlayerComponent>),
+        With<BodyComponent>,
+    >,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut tile_changed_event_writer: EventWriter<TileChangedEvent>,
+) {
+    // Fire spreads on a read of the map, and only the burned tiles are written back.
+    let burned = spread_fire(&map.0, &mut rng);
+    for (location, tile) in burned {
+        if map.0.update(tile, location).is_ok() {
+            tile_changed_event_writer.send(TileChangedEvent(location));
+        }
+    }
+
+    let mut new_smoke = smoke.clone();
+    new_smoke.drift(&map.0, &mut rng);
+    new_smoke.emit(&map.0);
+    if *smoke != new_smoke {
+        *smoke = new_smoke;
+    }
+
+    for (entity, location, maybe_player) in character_query.iter() {
+        let fire = match map.0.get(location.0) {
+            Ok(tile) => tile.get_surface().get_terrain().get_fire(),
+            Err(_) => continue,
+        };
+        if let Some(fire) = fire {
+            if maybe_player.is_some() {
+                log.log_string(fire.get_message());
+            }
+            damage_event_writer.send(DamageEvent(entity, fire.get_damage()));
+        }
+    }
+}
+
+fn render_smoke_system(
+    mut commands: Commands,
+    smoke: Res<Smoke>,
+    field_of_view: Res<FieldOfView>,
+    player_query: Query<&LocationComponent, With<PlayerComponent>>,
+    smoke_query: Query<Entity, With<SmokeComponent>>,
+    font: Res<LoadedFont>,
+    render_settings: Res<RenderSettings>,
+) {
+    for entity in smoke_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let map_layer = match player_query.get_single() {
+        Ok(player_location) => player_location.0.get_map_layer(),
+        Err(_) => return,
+    };
+    // Drawn like the smoke particles from fireplaces.
+    let appearance =
+        render_settings.get_particle_appearance(AsciiTileAppearance::new('S', ColorCode::Gray));
+    for tile_location in smoke.get_locations(map_layer) {
+        if !field_of_view.is_visible(MapLocation::new(map_layer, tile_location)) {
+            continue;
+        }
+        let entity = appearance.render(
+            &mut commands.spawn_empty(),
+            font.0.clone(),
+            GameState::Exploring,
+            TileGrid::tile_to_world_coordinates(tile_location),
+        );
+        commands.entity(entity).insert(SmokeComponent);
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+// The tiles that change this turn: flammable tiles next to a fire may catch, and fires may burn
+// out.  A fire that starts this turn doesn't spread until the next.
+pub fn spread_fire(map: &Map, rng: &mut RngResource) -> Vec<(MapLocation, Tile)> {
+    let mut changed = Vec::new();
+    let mut changed_locations = HashSet::new();
+    for (location, tile) in get_location_and_tile_vector(map) {
+        let fire = match tile.get_surface().get_terrain().get_fire() {
+            Some(fire) => fire,
+            None => continue,
+        };
+
+        for direction in Direction::all() {
+            let mut neighbour = location;
+            neighbour.translate(direction.as_tile_location());
+            if changed_locations.contains(&neighbour) {
+                continue;
+            }
+            let neighbour_tile = match map.get(neighbour) {
+                Ok(neighbour_tile) => neighbour_tile,
+                Err(_) => continue,
+            };
+            if let Some(burns_to) = neighbour_tile.get_surface().get_terrain().get_burns_to() {
+                if fire.get_spread().roll(rng) {
+                    changed.push((neighbour, with_terrain(neighbour_tile, burns_to)));
+                    changed_locations.insert(neighbour);
+                }
+            }
+        }
+
+        if let Some(burns_out_to) = fire.get_burns_out_to() {
+            if !changed_locations.contains(&location) && fire.get_burn_out().roll(rng) {
+                changed.push((location, with_terrain(tile, burns_out_to)));
+                changed_locations.insert(location);
+            }
+        }
+    }
+    changed
+}
+
+fn get_location_and_tile_vector(map: &Map) -> Vec<(MapLocation, &Tile)> {
+    map.get_layers()
+        .iter()
+        .enumerate()
+        .flat_map(|(map_layer, layer)| {
+            layer
+                .as_location_and_tile_vector()
+                .into_iter()
+                .map(move |(tile_location, tile)| {
+                    (MapLocation::new(map_layer, tile_location), tile)
+                })
+        })
+        .collect()
+}
+
+// Whatever was lying on the tile survives the fire.
+fn with_terrain(tile: &Tile, terrain: &str) -> Tile {
+    Tile::new(SurfaceTile::new(terrain), tile.get_stack().clone())
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::MapLayer;
+    use crate::game::vision::compute_obscured_field_of_view;
+
+    fn get_library() -> Map {
+        let mut map_layer = MapLayer::fill(7, 7, Tile::empty_ground());
+        (0..7).for_each(|j| {
+            map_layer
+                .update(Tile::empty(SurfaceTile::new(BOOKSHELF_TERRAIN)), 3, j)
+                .unwrap()
+        });
+        map_layer
+            .update(
+                Tile::empty(SurfaceTile::new(BURNING_BOOKSHELF_TERRAIN)),
+                3,
+                0,
+            )
+            .unwrap();
+        Map::new(vec![map_layer])
+    }
+
+    fn get_terrain_id(map: &Map, i: i32, j: i32) -> String {
+        map.get(MapLocation::new(0, TileLocation::new(i, j)))
+            .unwrap()
+            .get_surface()
+            .get_terrain_id()
+            .to_string()
+    }
+
+    #[test]
+    fn test_fire_spreads_to_bookshelves_and_burns_out() {
+        let library = get_library();
+        let mut rng = RngResource::seeded(7);
+        let changes = (0..100)
+            .flat_map(|_trial| spread_fire(&library, &mut rng))
+            .map(|(location, tile)| {
+                (
+                    location.get_tile_location(),
+                    tile.get_surface().get_terrain_id().to_string(),
+                )
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    TileLocation::new(3, 1),
+                    BURNING_BOOKSHELF_TERRAIN.to_string()
+                ),
+                (TileLocation::new(3, 0), ASH_TERRAIN.to_string()),
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        // However far the fire gets, it only ever takes bookshelves and leaves ash behind.
+        let mut map = library.clone();
+        for _turn in 0..500 {
+            for (location, tile) in spread_fire(&map, &mut rng) {
+                map.update(tile, location).unwrap();
+            }
+        }
+        assert_eq!(get_terrain_id(&map, 3, 0), ASH_TERRAIN);
+        (0..7).for_each(|j| {
+            assert!([ASH_TERRAIN, BOOKSHELF_TERRAIN].contains(&get_terrain_id(&map, 3, j).as_str()));
+            assert_eq!(get_terrain_id(&map, 2, j), GROUND_TERRAIN);
+        });
+        assert!(spread_fire(&map, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn test_fireplaces_burn_forever() {
+        let mut map: Map = MapLayer::fill(3, 3, Tile::empty_ground()).into();
+        let fireplace = MapLocation::new(0, TileLocation::new(1, 1));
+        map.update(Tile::empty(SurfaceTile::new(FIREPLACE_TERRAIN)), fireplace)
+            .unwrap();
+        let mut rng = RngResource::seeded(7);
+        (0..50).for_each(|_| assert!(spread_fire(&map, &mut rng).is_empty()));
+    }
+
+    #[test]
+    fn test_smoke_drifts_thins_out_and_blocks_sight() {
+        let map = get_library();
+        let fire = MapLocation::new(0, TileLocation::new(3, 0));
+        let mut smoke = Smoke::default();
+        smoke.emit(&map);
+        assert!(smoke.is_opaque(fire));
+
+        let mut rng = RngResource::seeded(7);
+        smoke.drift(&map, &mut rng);
+        assert!(smoke.get_density(fire) > 0);
+        // Smoke can't drift into the bookshelves next to the fire.
+        assert_eq!(
+            smoke.get_density(MapLocation::new(0, TileLocation::new(3, 1))),
+            0
+        );
+
+        smoke.add(fire, MAX_SMOKE_DENSITY);
+        assert_eq!(smoke.get_density(fire), MAX_SMOKE_DENSITY);
+        (0..MAX_SMOKE_DENSITY * 2).for_each(|_| smoke.drift(&map, &mut rng));
+        assert_eq!(smoke, Smoke::default());
+
+        let open: Map = MapLayer::fill(11, 11, Tile::empty_ground()).into();
+        let mut smoke = Smoke::default();
+        (0..11).for_each(|j| smoke.add(MapLocation::new(0, TileLocation::new(7, j)), 5));
+        let visible = compute_obscured_field_of_view(
+            open.get_layer(0).unwrap(),
+            TileLocation::new(5, 5),
+            10,
+            &smoke.get_opaque_locations(0),
+        );
+        assert!(visible.contains(&TileLocation::new(7, 5)));
+        assert!(!visible.contains(&TileLocation::new(8, 5)));
+    }
+}
@@ -1,7 +1,11 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use super::{character::Damage, map::TileLocation, resources::GameState};
+use super::{
+    character::Damage,
+    map::{MapLocation, TileLocation},
+    resources::GameState,
+};
 use crate::menu::ExploringMenuType;
 
 pub struct EventsPlugin;
@@ -73,6 +77,9 @@ impl TryMoveEvent {
     }
 }
 
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Event)]
+pub struct TryChangeLayerEvent(pub Entity, pub LayerChange);
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Event)]
 pub struct ChooseDirectionEvent(pub Direction);
 
@@ -92,9 +99,16 @@ pub struct StateChangeEvent(pub GameState);
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Event)]
 pub struct DespawnBoundEntitiesEvent(pub GameState);
 
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Event)]
+pub struct ToggleRenderModeEvent;
+
 #[derive(Debug, Clone, Event)]
 pub struct DamageEvent(pub Entity, pub Damage);
 
+// Sent after a tile in LoadedMap changes so that it can be drawn again.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Event)]
+pub struct TileChangedEvent(pub MapLocation);
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Direction {
     Up,
@@ -108,6 +122,25 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Up,
+            Self::UpRight,
+            Self::Right,
+            Self::DownRight,
+            Self::Down,
+            Self::DownLeft,
+            Self::Left,
+            Self::UpLeft,
+        ]
+    }
+
+    pub fn from_tile_location(location: TileLocation) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|direction| direction.as_tile_location() == location)
+    }
+
     pub fn as_vector(&self) -> Vec2 {
         match self {
             Self::Up => Vec2::Y,
@@ -135,6 +168,22 @@ impl Direction {
     }
 }
 
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LayerChange {
+    Ascend,
+    Descend,
+}
+
+impl LayerChange {
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::Ascend => "up",
+            Self::Descend => "down",
+        }
+        .to_string()
+    }
+}
+
 // End Events
 
 // Components
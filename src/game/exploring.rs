@@ -1,21 +1,32 @@
-use std::collections::HashSet;
-
 use bevy::prelude::*;
+use rand::Rng;
 
-use super::character::{ActionClockComponent, BodyComponent, LocationComponent};
+use super::character::{
+    ActionClockComponent, BodyComponent, Capabilities, InventoryComponent, LocationComponent,
+};
+use super::chunk::RenderedTileGrid;
 use super::enemy::{AIComponent, EnemyComponent};
-use super::events::{BoundStateComponent, DamageEvent, DespawnBoundEntitiesEvent};
-use super::resources::RngResource;
+use super::events::{
+    BoundStateComponent, DamageEvent, DespawnBoundEntitiesEvent, TileChangedEvent,
+    TryChangeLayerEvent,
+};
+use super::generation::generator::{MapGenerator, MapGeneratorType};
+use super::health::Treatment;
+use super::map_metadata::{MapMetadata, PlacementKind};
+use super::resources::{EnemySpecs, NPCSpecs, RngResource};
+use super::tileset::RenderSettings;
 use super::{
     events::TryMoveEvent,
-    map::{MapLayer, SurfaceTile, Tile},
+    map::MapTileComponent,
     npc::NPCComponent,
     particle::{ParticleComponent, ParticleEmitterComponent, ParticleTiming},
+    pathfinding::OccupiedLocations,
     player::PlayerComponent,
     resources::{GameState, LoadedFont, LoadedMap},
 };
+use crate::assets::{FileSystem, FileSystemError};
 use crate::constants::*;
-use crate::game::map::{AsciiTileAppearance, TileAppearance, TileGrid};
+use crate::game::map::{AsciiTileAppearance, Map, MapLocation, ObjectTile, TileGrid};
 use crate::ui::LogState;
 
 pub struct ExploringPlugin;
@@ -52,6 +63,10 @@ impl Plugin for ExploringPlugin {
             .add_systems(
                 Update,
                 despawn_bound_entities.run_if(on_event::<DespawnBoundEntitiesEvent>()),
+            )
+            .add_systems(
+                Update,
+                redraw_changed_tiles_system.run_if(on_event::<TileChangedEvent>()),
             );
     }
 }
@@ -82,39 +97,92 @@ pub struct NonPlayerTurnLength(pub u8);
 
 // Systems
 
-fn load_map_system(mut commands: Commands) {
-    let mut map_layer = MapLayer::fill(
-        DEFAULT_MAP_WIDTH_IN_TILES,
-        DEFAULT_MAP_HEIGHT_IN_TILES,
-        Tile::empty_ground(),
-    );
-    map_layer.update_edges(&Tile::wall());
-    map_layer
-        .update(Tile::empty(SurfaceTile::Fireplace), 5, 5)
-        .expect("5, 5 exists because we're setting it up that way.");
+// Loads the starting map if there is one and otherwise generates a dungeon with everyone scattered
+// through it.
+fn load_map_system(
+    mut commands: Commands,
+    mut rng: ResMut<RngResource>,
+    mut log: ResMut<LogState>,
+    npc_specs: Res<NPCSpecs>,
+    enemy_specs: Res<EnemySpecs>,
+) {
+    let map = match FileSystem::<Map>::new_directory(MAP_DIRECTORY).load(STARTING_MAP_FILENAME) {
+        Ok(map) => {
+            log.log_string(&format!("Loaded {}.", map.get_metadata().get_name()));
+            map
+        }
+        Err(FileSystemError::NoSuchFile | FileSystemError::CouldntReadDirectory) => {
+            let seed: u64 = rng
+                .0
+                .write()
+                .expect("If a thread somewhere panicked, we should panic.")
+                .gen();
+            let mut map = MapGeneratorType::Dungeon
+                .build(DEFAULT_MAP_WIDTH_IN_TILES, DEFAULT_MAP_HEIGHT_IN_TILES, &[])
+                .and_then(|generator| generator.generate_map(seed, DEFAULT_MAP_LAYERS))
+                .expect("The default dungeon always generates.");
+            let specs = npc_specs
+                .0
+                .as_vec()
+                .into_iter()
+                .map(|(name, _npc)| (PlacementKind::NPC, name))
+                .chain(
+                    enemy_specs
+                        .0
+                        .as_vec()
+                        .into_iter()
+                        .map(|(name, _enemy)| (PlacementKind::Enemy, name)),
+                )
+                .collect();
+            map.set_metadata(MapMetadata::scattered(
+                &map,
+                seed,
+                "Generated dungeon",
+                specs,
+            ));
+            log.log_string(&format!("Generated map from seed {}.", seed));
+            map
+        }
+        // A starting map that's there but broken is a mistake worth hearing about, not a reason to
+        // quietly play somewhere else.
+        Err(error) => panic!("Couldn't load {}: {:?}", STARTING_MAP_FILENAME, error),
+    };
 
-    commands.insert_resource(LoadedMap(map_layer.into()));
-    commands.insert_resource(NextState(Some(GameState::Exploring)));
+    commands.insert_resource(LoadedMap(map));
+    commands.insert_resource(NextState(Some(GameState::InitializingWorld)));
     commands.insert_resource(ShouldSpawnMap(true));
 }
 
 fn movement_system(
     mut commands: Commands,
     mut movement_event_reader: EventReader<TryMoveEvent>,
-    mut query: Query<(Entity, &mut LocationComponent, Option<&PlayerComponent>)>,
+    mut change_layer_event_reader: EventReader<TryChangeLayerEvent>,
+    mut tile_changed_event_writer: EventWriter<TileChangedEvent>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut query: Query<(
+        Entity,
+        &mut LocationComponent,
+        Option<&PlayerComponent>,
+        Option<&mut InventoryComponent>,
+        Option<&BodyComponent>,
+    )>,
     mut log: ResMut<LogState>,
-    map: Res<LoadedMap>,
+    mut should_spawn: ResMut<ShouldSpawnMap>,
+    mut map: ResMut<LoadedMap>,
 ) {
     let non_traversable_entity_locations = query
         .iter()
-        .map(|(_, location, _)| location.0)
-        .collect::<HashSet<_>>();
+        .map(|(_, location, _, _, _)| location.0)
+        .collect::<OccupiedLocations>();
     for try_move_event in movement_event_reader.iter() {
-        for (entity, mut location, _maybe_is_player) in query.iter_mut() {
+        for (entity, mut location, maybe_is_player, maybe_inventory, maybe_body) in query.iter_mut()
+        {
             let TryMoveEvent(entity_to_move, direction) = try_move_event;
             if entity == *entity_to_move {
+                let capabilities =
+                    maybe_body.map_or_else(Capabilities::default, |body| body.0.get_capabilities());
                 let final_location = location.translated(direction.as_tile_location());
-                if non_traversable_entity_locations.contains(&final_location.0) {
+                if non_traversable_entity_locations.contains(final_location.0) {
                     log.log_string("Trying to walk into another entity.");
                 } else {
                     match map.0.is_traversable(final_location.0) {
@@ -124,7 +192,37 @@ fn movement_system(
                         Ok(is_traversable) => {
                             if is_traversable {
                                 *location = final_location;
-                                end_turn(&mut commands, MOVEMENT_TICKS);
+                                spring_traps(
+                                    &mut map.0,
+                                    entity,
+                                    location.0,
+                                    maybe_is_player.is_some(),
+                                    &mut log,
+                                    &mut damage_event_writer,
+                                    &mut tile_changed_event_writer,
+                                );
+                                if maybe_is_player.is_some() {
+                                    describe_objects_at(&map.0, location.0, &mut log);
+                                }
+                                if let Some(mut inventory) =
+                                    maybe_inventory.filter(|_| capabilities.can_manipulate())
+                                {
+                                    pick_up_objects(
+                                        &mut map.0,
+                                        location.0,
+                                        &mut inventory,
+                                        &mut log,
+                                        &mut tile_changed_event_writer,
+                                    );
+                                }
+                                let movement_cost = map
+                                    .0
+                                    .get(location.0)
+                                    .map_or(1, |tile| tile.get_movement_cost());
+                                end_turn(
+                                    &mut commands,
+                                    capabilities.get_movement_ticks_onto(movement_cost),
+                                );
                             } else {
                                 log.log_string("Trying to traverse non-traversable terrain.");
                             }
@@ -134,6 +232,52 @@ fn movement_system(
             }
         }
     }
+
+    for try_change_layer_event in change_layer_event_reader.iter() {
+        for (entity, mut location, maybe_is_player, _, maybe_body) in query.iter_mut() {
+            let TryChangeLayerEvent(entity_to_move, layer_change) = try_change_layer_event;
+            if entity == *entity_to_move {
+                let capabilities =
+                    maybe_body.map_or_else(Capabilities::default, |body| body.0.get_capabilities());
+                match map.0.get_destination(location.0, *layer_change) {
+                    Err(_e) => {
+                        log.log_string("Trying to change layers from off the map.");
+                    }
+                    Ok(None) => {
+                        log.log_string(&format!(
+                            "There's no way {} from here.",
+                            layer_change.to_string()
+                        ));
+                    }
+                    Ok(Some(destination)) => {
+                        if non_traversable_entity_locations.contains(destination) {
+                            log.log_string("Something is blocking the way.");
+                        } else {
+                            match map.0.is_traversable(destination) {
+                                Ok(true) => {
+                                    *location = LocationComponent(destination);
+                                    if maybe_is_player.is_some() {
+                                        should_spawn.0 = true;
+                                    }
+                                    let movement_cost = map
+                                        .0
+                                        .get(destination)
+                                        .map_or(1, |tile| tile.get_movement_cost());
+                                    end_turn(
+                                        &mut commands,
+                                        capabilities.get_movement_ticks_onto(movement_cost),
+                                    );
+                                }
+                                _ => {
+                                    log.log_string("The way is blocked.");
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
 
 fn update_positions(mut query: Query<(&LocationComponent, &mut Transform)>) {
@@ -161,14 +305,27 @@ fn spawn_map_system(
         )>,
     >,
     enemy_query: Query<(Entity, &LocationComponent), With<EnemyComponent>>,
+    previous_map_query: Query<
+        Entity,
+        Or<(
+            With<MapTileComponent>,
+            With<ParticleEmitterComponent>,
+            With<ParticleComponent>,
+        )>,
+    >,
     map: Res<LoadedMap>,
     font: Res<LoadedFont>,
+    render_settings: Res<RenderSettings>,
     mut should_spawn: ResMut<ShouldSpawnMap>,
 ) {
     if !should_spawn.0 {
         return;
     }
 
+    for entity in previous_map_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
     let (player_entity, player_location, _, _, _) = character_query
         .iter()
         .find(|(entity, _location, maybe_player_component, _, _)| maybe_player_component.is_some())
@@ -179,8 +336,11 @@ fn spawn_map_system(
         .get_layer(player_location.0.get_map_layer())
         .expect("Player's layer must exist.");
 
-    let tile_grid = TileGrid::from_map_layer(map_layer.clone());
-    tile_grid.render(&mut commands, font.0.clone(), GameState::Exploring);
+    // Tile entities are spawned chunk by chunk around the camera, see chunk.rs.
+    commands.insert_resource(RenderedTileGrid::new(
+        TileGrid::from_map_layer(map_layer, &render_settings),
+        GameState::Exploring,
+    ));
 
     map_layer
         .as_location_and_tile_vector()
@@ -190,15 +350,17 @@ fn spawn_map_system(
                 particle_spec.render(
                     &mut commands,
                     font.0.clone(),
+                    &render_settings,
                     GameState::Exploring,
                     location,
                 );
             };
         });
 
-    let player_tile = TileAppearance::Ascii(AsciiTileAppearance::from('@'));
-    let npc_tile = TileAppearance::Ascii('&'.into());
-    let enemy_tile = TileAppearance::Ascii('s'.into());
+    let player_tile =
+        render_settings.get_entity_appearance("player", AsciiTileAppearance::from('@'));
+    let npc_tile = render_settings.get_entity_appearance("npc", '&'.into());
+    let enemy_tile = render_settings.get_entity_appearance("enemy", 's'.into());
 
     for (entity, location, maybe_player, maybe_npc, maybe_enemy) in character_query.iter() {
         let maybe_player_tile = maybe_player.map(|_| player_tile.clone());
@@ -227,16 +389,67 @@ fn spawn_map_system(
     should_spawn.0 = false;
 }
 
+fn redraw_changed_tiles_system(
+    mut commands: Commands,
+    mut tile_changed_event_reader: EventReader<TileChangedEvent>,
+    tile_query: Query<(Entity, &MapTileComponent)>,
+    player_query: Query<&LocationComponent, With<PlayerComponent>>,
+    map: Res<LoadedMap>,
+    font: Res<LoadedFont>,
+    render_settings: Res<RenderSettings>,
+    mut rendered_grid: ResMut<RenderedTileGrid>,
+) {
+    let map_layer = match player_query.get_single() {
+        Ok(player_location) => player_location.0.get_map_layer(),
+        Err(_) => return,
+    };
+
+    for TileChangedEvent(location) in tile_changed_event_reader.iter() {
+        // Other layers are drawn from scratch when the player reaches them.
+        if location.get_map_layer() != map_layer {
+            continue;
+        }
+        let tile_location = location.get_tile_location();
+        let tile = match map.0.get(*location) {
+            Ok(tile) => tile,
+            Err(_) => continue,
+        };
+        let appearance = render_settings.get_tile_appearance(tile);
+        if rendered_grid
+            .update(tile_location, appearance.clone())
+            .is_err()
+        {
+            continue;
+        }
+        // Tiles in chunks that aren't spawned yet pick up the change when they are.
+        if let Some((entity, _)) = tile_query
+            .iter()
+            .find(|(_entity, MapTileComponent(drawn_location))| *drawn_location == tile_location)
+        {
+            appearance.render(
+                &mut commands.entity(entity),
+                font.0.clone(),
+                GameState::Exploring,
+                TileGrid::tile_to_world_coordinates(tile_location),
+            );
+        }
+    }
+}
+
 fn handle_damage_system(
-    mut character_query: Query<(Entity, &mut BodyComponent)>,
+    mut character_query: Query<(Entity, &mut BodyComponent, Option<&PlayerComponent>)>,
     mut damage_event_reader: EventReader<DamageEvent>,
     mut rng: ResMut<RngResource>,
+    mut log: ResMut<LogState>,
 ) {
     for damage_event in damage_event_reader.iter() {
         let DamageEvent(damaged_entity, damage) = damage_event;
-        for (entity, mut body_component) in character_query.iter_mut() {
+        for (entity, mut body_component, maybe_player) in character_query.iter_mut() {
             if *damaged_entity == entity {
-                body_component.0.take_damage(&mut *rng, damage.clone());
+                let outcome = body_component.0.take_damage(&mut *rng, damage);
+                if maybe_player.is_some() {
+                    log.log_string(&outcome.to_string());
+                }
             }
         }
     }
@@ -247,6 +460,7 @@ fn emit_particles_system(
     mut emitter_query: Query<(&mut ParticleEmitterComponent, &Transform)>,
     time: Res<Time>,
     font: Res<LoadedFont>,
+    render_settings: Res<RenderSettings>,
     current_state: Res<State<GameState>>,
 ) {
     for (mut emitter, transform) in emitter_query.iter_mut() {
@@ -256,6 +470,7 @@ fn emit_particles_system(
             emitter.emit(
                 &mut commands,
                 font.0.clone(),
+                &render_settings,
                 *current_state.get(),
                 transform.translation.truncate(),
             );
@@ -345,20 +560,28 @@ fn determine_turn_order_system(
     ));
 }
 
-fn process_non_player_turn(
+pub fn process_non_player_turn(
     mut commands: Commands,
     mut non_player_turns: ResMut<NonPlayerTurnOrder>,
-    mut non_player_query: Query<(Entity, &mut ActionClockComponent, &mut AIComponent)>,
+    mut non_player_query: Query<(
+        Entity,
+        &mut ActionClockComponent,
+        &mut AIComponent,
+        Option<&BodyComponent>,
+    )>,
     mut log: ResMut<LogState>,
     non_player_turn_length: Res<NonPlayerTurnLength>,
 ) {
     match non_player_turns.0.pop() {
         Some(non_player_entity) => {
-            for (acting_entity, mut action_clock, mut ai) in non_player_query.iter_mut() {
+            for (acting_entity, mut action_clock, mut ai, maybe_body) in non_player_query.iter_mut()
+            {
                 if acting_entity == non_player_entity {
                     if action_clock.tick_and_is_finished(non_player_turn_length.0) {
                         log.log_string(&format!("{:?} takes its turn", non_player_entity));
-                        action_clock.reset(ai.next().get_ticks());
+                        let capabilities = maybe_body
+                            .map_or_else(Capabilities::default, |body| body.0.get_capabilities());
+                        action_clock.reset(ai.next().get_ticks(&capabilities));
                     } else {
                         log.log_string(&format!(
                             "{:?} didn't get to take its turn",
@@ -397,7 +620,68 @@ struct PlayerSprite;
 // End Components
 
 // Helper Functions
-fn end_turn(mut commands: &mut Commands, n_ticks: u8) {
+fn describe_objects_at(map: &Map, location: MapLocation, log: &mut LogState) {
+    if let Ok(tile) = map.get(location) {
+        if !tile.get_visible_stack().is_empty() {
+            log.log_string(&format!("You see: {}", tile.get_description()));
+        }
+    }
+}
+
+fn spring_traps(
+    map: &mut Map,
+    entity: Entity,
+    location: MapLocation,
+    is_player: bool,
+    log: &mut LogState,
+    damage_event_writer: &mut EventWriter<DamageEvent>,
+    tile_changed_event_writer: &mut EventWriter<TileChangedEvent>,
+) {
+    let sprung = map.spring_traps(location).unwrap_or_default();
+    if sprung.is_empty() {
+        return;
+    }
+    for trap_spec in sprung {
+        if is_player {
+            log.log_string(trap_spec.get_message());
+        } else {
+            log.log_string(&format!("Something sets off a {}.", trap_spec.get_name()));
+        }
+        damage_event_writer.send(DamageEvent(entity, trap_spec.get_damage()));
+    }
+    tile_changed_event_writer.send(TileChangedEvent(location));
+}
+
+// Keys and treatments are picked up by walking over them.
+fn pick_up_objects(
+    map: &mut Map,
+    location: MapLocation,
+    inventory: &mut InventoryComponent,
+    log: &mut LogState,
+    tile_changed_event_writer: &mut EventWriter<TileChangedEvent>,
+) {
+    let mut picked_up = false;
+    let objects = std::iter::once(ObjectTile::Key)
+        .chain(Treatment::all().into_iter().map(ObjectTile::Treatment));
+    for object in objects {
+        let count = map.take_objects(location, object.clone()).unwrap_or(0);
+        if count == 0 {
+            continue;
+        }
+        inventory.add(object.clone(), count);
+        if count == 1 {
+            log.log_string(&format!("You pick up a {}.", object.to_string()));
+        } else {
+            log.log_string(&format!("You pick up {} {}s.", count, object.to_string()));
+        }
+        picked_up = true;
+    }
+    if picked_up {
+        tile_changed_event_writer.send(TileChangedEvent(location));
+    }
+}
+
+pub fn end_turn(mut commands: &mut Commands, n_ticks: u8) {
     commands.insert_resource(NonPlayerTurnLength(n_ticks));
     commands.insert_resource(NextState(Some(GameState::NonPlayerTurns)));
 }
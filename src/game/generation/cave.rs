@@ -0,0 +1,203 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::generator::{keep_largest_region, GenerationError, MapGenerator};
+use crate::constants::*;
+use crate::game::map::{MapLayer, Tile};
+
+// Structs
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CaveParameters {
+    pub width: usize,
+    pub height: usize,
+    pub initial_wall_percent: u8,
+    pub smoothing_iterations: usize,
+}
+
+impl Default for CaveParameters {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_MAP_WIDTH_IN_TILES,
+            DEFAULT_MAP_HEIGHT_IN_TILES,
+            DEFAULT_CAVE_WALL_PERCENT,
+            DEFAULT_CAVE_SMOOTHING_ITERATIONS,
+        )
+    }
+}
+
+impl CaveParameters {
+    pub fn new(
+        width: usize,
+        height: usize,
+        initial_wall_percent: u8,
+        smoothing_iterations: usize,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            initial_wall_percent,
+            smoothing_iterations,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GenerationError> {
+        if self.initial_wall_percent > 100 {
+            return Err(GenerationError::InvalidParameters);
+        }
+        // The outer edge is always wall, so leave room for at least one open tile.
+        if self.width < 3 || self.height < 3 {
+            return Err(GenerationError::InvalidParameters);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaveGenerator {
+    parameters: CaveParameters,
+}
+
+impl MapGenerator for CaveGenerator {
+    fn generate_layer(&self, seed: u64) -> Result<MapLayer, GenerationError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let parameters = &self.parameters;
+
+        let mut walls = (0..parameters.width)
+            .map(|i| {
+                (0..parameters.height)
+                    .map(|j| {
+                        self.is_edge(i, j)
+                            || rng.gen_range(0..100) < parameters.initial_wall_percent
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..parameters.smoothing_iterations {
+            walls = self.smooth(&walls);
+        }
+
+        let mut map_layer = MapLayer::new(
+            walls
+                .into_iter()
+                .map(|column| {
+                    column
+                        .into_iter()
+                        .map(|is_wall| {
+                            if is_wall {
+                                Tile::wall()
+                            } else {
+                                Tile::empty_ground()
+                            }
+                        })
+                        .collect()
+                })
+                .collect(),
+        );
+
+        // Smoothing leaves isolated pockets behind, so only the main cavern is kept.
+        if keep_largest_region(&mut map_layer) == 0 {
+            return Err(GenerationError::NoTraversableTiles);
+        }
+        Ok(map_layer)
+    }
+}
+
+impl CaveGenerator {
+    pub fn new(parameters: CaveParameters) -> Result<Self, GenerationError> {
+        parameters.validate()?;
+        Ok(Self { parameters })
+    }
+
+    // A tile becomes wall when most of its 3x3 neighbourhood is wall, with the outside counting
+    // as wall.
+    fn smooth(&self, walls: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
+        (0..self.parameters.width)
+            .map(|i| {
+                (0..self.parameters.height)
+                    .map(|j| {
+                        self.is_edge(i, j)
+                            || Self::count_walls_around(walls, i, j)
+                                >= CAVE_WALL_NEIGHBOURHOOD_THRESHOLD
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn count_walls_around(walls: &Vec<Vec<bool>>, i: usize, j: usize) -> usize {
+        let (i, j) = (i as i32, j as i32);
+        (-1..=1)
+            .map(|di| (-1..=1).map(move |dj| (i + di, j + dj)))
+            .flatten()
+            .filter(|(i, j)| {
+                if *i < 0 || *j < 0 {
+                    return true;
+                }
+                walls
+                    .get(*i as usize)
+                    .and_then(|column| column.get(*j as usize))
+                    .copied()
+                    .unwrap_or(true)
+            })
+            .count()
+    }
+
+    fn is_edge(&self, i: usize, j: usize) -> bool {
+        i == 0 || j == 0 || i == self.parameters.width - 1 || j == self.parameters.height - 1
+    }
+}
+
+// End Structs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cave_generator_produces_walled_caverns() {
+        let generator = CaveGenerator::new(CaveParameters::default()).unwrap();
+        for seed in 0..10 {
+            let map_layer = generator.generate_layer(seed).unwrap();
+            assert_eq!(map_layer, generator.generate_layer(seed).unwrap());
+            assert_eq!(map_layer.width(), DEFAULT_MAP_WIDTH_IN_TILES);
+            assert_eq!(map_layer.height(), DEFAULT_MAP_HEIGHT_IN_TILES);
+
+            let n_traversable = map_layer
+                .as_location_and_tile_vector()
+                .into_iter()
+                .filter(|(_location, tile)| tile.is_traversable())
+                .count();
+            assert!(n_traversable > 0);
+            // Every open tile is already in the single remaining region.
+            assert_eq!(keep_largest_region(&mut map_layer.clone()), n_traversable);
+
+            (0..map_layer.height()).for_each(|j| {
+                assert!(!map_layer.get(0, j).unwrap().is_traversable());
+                assert!(!map_layer
+                    .get(map_layer.width() - 1, j)
+                    .unwrap()
+                    .is_traversable());
+            });
+        }
+    }
+
+    #[test]
+    fn test_cave_parameters_are_validated() {
+        assert_eq!(
+            CaveGenerator::new(CaveParameters::new(10, 10, 101, 3)),
+            Err(GenerationError::InvalidParameters)
+        );
+        assert_eq!(
+            CaveGenerator::new(CaveParameters::new(2, 10, 45, 3)),
+            Err(GenerationError::InvalidParameters)
+        );
+        assert_eq!(
+            CaveGenerator::new(CaveParameters::new(10, 10, 100, 3))
+                .unwrap()
+                .generate_layer(0),
+            Err(GenerationError::NoTraversableTiles)
+        );
+    }
+}
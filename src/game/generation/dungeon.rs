@@ -0,0 +1,307 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::generator::{GenerationError, MapGenerator};
+use crate::constants::*;
+use crate::game::map::{MapLayer, Tile, TileLocation};
+
+// Structs
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DungeonParameters {
+    pub width: usize,
+    pub height: usize,
+    pub room_count: usize,
+    pub min_room_size: usize,
+    pub max_room_size: usize,
+}
+
+impl Default for DungeonParameters {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_MAP_WIDTH_IN_TILES,
+            DEFAULT_MAP_HEIGHT_IN_TILES,
+            DEFAULT_ROOM_COUNT,
+            DEFAULT_MIN_ROOM_SIZE,
+            DEFAULT_MAX_ROOM_SIZE,
+        )
+    }
+}
+
+impl DungeonParameters {
+    pub fn new(
+        width: usize,
+        height: usize,
+        room_count: usize,
+        min_room_size: usize,
+        max_room_size: usize,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            room_count,
+            min_room_size,
+            max_room_size,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GenerationError> {
+        if self.room_count == 0 || self.min_room_size == 0 {
+            return Err(GenerationError::InvalidParameters);
+        }
+        if self.min_room_size > self.max_room_size {
+            return Err(GenerationError::InvalidParameters);
+        }
+        // Rooms are surrounded by at least one wall on every side.
+        if self.width < self.min_room_size + 2 || self.height < self.min_room_size + 2 {
+            return Err(GenerationError::InvalidParameters);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DungeonGenerator {
+    parameters: DungeonParameters,
+}
+
+impl MapGenerator for DungeonGenerator {
+    fn generate_layer(&self, seed: u64) -> Result<MapLayer, GenerationError> {
+        Ok(self.carve_rooms_and_corridors(seed))
+    }
+}
+
+impl DungeonGenerator {
+    pub fn new(parameters: DungeonParameters) -> Result<Self, GenerationError> {
+        parameters.validate()?;
+        Ok(Self { parameters })
+    }
+
+    fn carve_rooms_and_corridors(&self, seed: u64) -> MapLayer {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let parameters = &self.parameters;
+        let mut map_layer = MapLayer::fill(parameters.width, parameters.height, Tile::wall());
+
+        let mut rooms: Vec<Room> = Vec::new();
+        for _ in 0..(parameters.room_count * ROOM_PLACEMENT_ATTEMPTS_PER_ROOM) {
+            if rooms.len() >= parameters.room_count {
+                break;
+            }
+            let room = self.random_room(&mut rng);
+            if rooms.iter().all(|other| !room.intersects(other)) {
+                rooms.push(room);
+            }
+        }
+
+        if rooms.is_empty() {
+            // The parameters guarantee that the smallest room fits, so always carve at least one.
+            rooms.push(Room::new(
+                TileLocation::new(1, 1),
+                parameters.min_room_size as i32,
+                parameters.min_room_size as i32,
+            ));
+        }
+
+        for room in rooms.iter() {
+            for location in room.get_tiles() {
+                map_layer
+                    .update_at_location(Tile::empty_ground(), location)
+                    .expect("Rooms are placed within the layer.");
+            }
+        }
+
+        for pair in rooms.windows(2) {
+            let horizontal_first = rng.gen_bool(0.5);
+            carve_corridor(
+                &mut map_layer,
+                pair[0].get_center(),
+                pair[1].get_center(),
+                horizontal_first,
+            );
+        }
+
+        map_layer
+    }
+
+    fn random_room(&self, rng: &mut StdRng) -> Room {
+        let parameters = &self.parameters;
+        let max_width = parameters.max_room_size.min(parameters.width - 2);
+        let max_height = parameters.max_room_size.min(parameters.height - 2);
+        let width = rng.gen_range(parameters.min_room_size..=max_width);
+        let height = rng.gen_range(parameters.min_room_size..=max_height);
+        let i = rng.gen_range(1..=(parameters.width - 1 - width));
+        let j = rng.gen_range(1..=(parameters.height - 1 - height));
+        Room::new(
+            TileLocation::new(i as i32, j as i32),
+            width as i32,
+            height as i32,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Room {
+    corner: TileLocation,
+    width: i32,
+    height: i32,
+}
+
+impl Room {
+    pub fn new(corner: TileLocation, width: i32, height: i32) -> Self {
+        Self {
+            corner,
+            width,
+            height,
+        }
+    }
+
+    pub fn get_center(&self) -> TileLocation {
+        TileLocation::new(
+            self.corner.i + self.width / 2,
+            self.corner.j + self.height / 2,
+        )
+    }
+
+    pub fn get_tiles(&self) -> Vec<TileLocation> {
+        (self.corner.i..(self.corner.i + self.width))
+            .map(|i| {
+                (self.corner.j..(self.corner.j + self.height)).map(move |j| TileLocation::new(i, j))
+            })
+            .flatten()
+            .collect()
+    }
+
+    // Rooms that touch would merge into one, so a wall's width of margin is required.
+    pub fn intersects(&self, other: &Room) -> bool {
+        self.corner.i <= other.corner.i + other.width
+            && other.corner.i <= self.corner.i + self.width
+            && self.corner.j <= other.corner.j + other.height
+            && other.corner.j <= self.corner.j + self.height
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+fn carve_corridor(
+    map_layer: &mut MapLayer,
+    from: TileLocation,
+    to: TileLocation,
+    horizontal_first: bool,
+) {
+    let corner = if horizontal_first {
+        TileLocation::new(to.i, from.j)
+    } else {
+        TileLocation::new(from.i, to.j)
+    };
+    carve_straight_line(map_layer, from, corner);
+    carve_straight_line(map_layer, corner, to);
+}
+
+fn carve_straight_line(map_layer: &mut MapLayer, from: TileLocation, to: TileLocation) {
+    let (i_min, i_max) = (from.i.min(to.i), from.i.max(to.i));
+    let (j_min, j_max) = (from.j.min(to.j), from.j.max(to.j));
+    for i in i_min..=i_max {
+        for j in j_min..=j_max {
+            let location = TileLocation::new(i, j);
+            let is_wall = map_layer.get_from_location(location).map_or(false, |tile| {
+                tile.get_surface().get_terrain_id() == WALL_TERRAIN
+            });
+            if is_wall {
+                map_layer
+                    .update_at_location(Tile::empty_ground(), location)
+                    .expect("Corridors connect locations within the layer.");
+            }
+        }
+    }
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::game::events::Direction;
+
+    fn get_reachable(map_layer: &MapLayer, start: TileLocation) -> HashSet<TileLocation> {
+        let mut reachable = HashSet::new();
+        let mut to_visit = vec![start];
+        while let Some(location) = to_visit.pop() {
+            let is_traversable = map_layer
+                .get_from_location(location)
+                .map_or(false, |tile| tile.is_traversable());
+            if is_traversable && reachable.insert(location) {
+                Direction::all()
+                    .into_iter()
+                    .for_each(|direction| to_visit.push(location + direction.as_tile_location()));
+            }
+        }
+        reachable
+    }
+
+    fn get_traversable(map_layer: &MapLayer) -> HashSet<TileLocation> {
+        map_layer
+            .as_location_and_tile_vector()
+            .into_iter()
+            .filter(|(_location, tile)| tile.is_traversable())
+            .map(|(location, _tile)| location)
+            .collect()
+    }
+
+    #[test]
+    fn test_dungeon_generator_is_reproducible() {
+        let generator = DungeonGenerator::new(DungeonParameters::new(40, 30, 8, 3, 8)).unwrap();
+        assert_eq!(
+            generator.generate_layer(42).unwrap(),
+            generator.generate_layer(42).unwrap()
+        );
+        assert_ne!(
+            generator.generate_layer(42).unwrap(),
+            generator.generate_layer(43).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dungeon_generator_produces_connected_floors() {
+        let generator = DungeonGenerator::new(DungeonParameters::default()).unwrap();
+        for seed in 0..20 {
+            let map_layer = generator.generate_layer(seed).unwrap();
+            assert_eq!(map_layer.width(), DEFAULT_MAP_WIDTH_IN_TILES);
+            assert_eq!(map_layer.height(), DEFAULT_MAP_HEIGHT_IN_TILES);
+
+            let traversable = get_traversable(&map_layer);
+            let start = *traversable.iter().next().unwrap();
+            assert_eq!(get_reachable(&map_layer, start), traversable);
+
+            // The outer edge is always wall.
+            (0..map_layer.width()).for_each(|i| {
+                assert!(!map_layer.get(i, 0).unwrap().is_traversable());
+                assert!(!map_layer
+                    .get(i, map_layer.height() - 1)
+                    .unwrap()
+                    .is_traversable());
+            });
+        }
+    }
+
+    #[test]
+    fn test_dungeon_parameters_are_validated() {
+        assert_eq!(
+            DungeonGenerator::new(DungeonParameters::new(10, 10, 0, 3, 5)),
+            Err(GenerationError::InvalidParameters)
+        );
+        assert_eq!(
+            DungeonGenerator::new(DungeonParameters::new(10, 10, 3, 6, 5)),
+            Err(GenerationError::InvalidParameters)
+        );
+        assert_eq!(
+            DungeonGenerator::new(DungeonParameters::new(4, 10, 3, 3, 5)),
+            Err(GenerationError::InvalidParameters)
+        );
+        assert!(DungeonGenerator::new(DungeonParameters::new(5, 5, 3, 3, 5)).is_ok());
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use super::cave::{CaveGenerator, CaveParameters};
+use super::dungeon::{DungeonGenerator, DungeonParameters};
+use super::wave_function_collapse::{
+    WaveFunctionCollapseGenerator, WaveFunctionCollapseParameters,
+};
+use crate::constants::*;
+use crate::game::events::Direction;
+use crate::game::map::{Map, MapLayer, MapLocation, SurfaceTile, Tile, TileLocation};
+
+// Traits
+
+pub trait MapGenerator {
+    fn generate_layer(&self, seed: u64) -> Result<MapLayer, GenerationError>;
+
+    // Each layer is generated from its own seed and stairs link it to the layer below.
+    fn generate_map(&self, seed: u64, n_layers: usize) -> Result<Map, GenerationError> {
+        let layers = (0..n_layers)
+            .map(|i| self.generate_layer(seed.wrapping_add(i as u64)))
+            .collect::<Result<Vec<_>, _>>()?;
+        link_layers_with_stairs(layers, seed)
+    }
+}
+
+// End Traits
+
+// Structs
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MapGeneratorType {
+    Empty,
+    Dungeon,
+    Cave,
+    WaveFunctionCollapse,
+}
+
+impl MapGeneratorType {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Empty,
+            Self::Dungeon,
+            Self::Cave,
+            Self::WaveFunctionCollapse,
+        ]
+    }
+
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::Empty => "Empty",
+            Self::Dungeon => "Dungeon",
+            Self::Cave => "Cave",
+            Self::WaveFunctionCollapse => "Wave Function Collapse",
+        }
+        .to_string()
+    }
+
+    pub fn from_string(s: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|generator_type| generator_type.to_string() == s)
+    }
+
+    // Samples are only used by generators that learn from existing maps.
+    pub fn build(
+        &self,
+        width: usize,
+        height: usize,
+        samples: &[MapLayer],
+    ) -> Result<Box<dyn MapGenerator>, GenerationError> {
+        Ok(match self {
+            Self::Empty => Box::new(EmptyGenerator::new(width, height)?),
+            Self::Dungeon => Box::new(DungeonGenerator::new(DungeonParameters {
+                width,
+                height,
+                ..Default::default()
+            })?),
+            Self::Cave => Box::new(CaveGenerator::new(CaveParameters {
+                width,
+                height,
+                ..Default::default()
+            })?),
+            Self::WaveFunctionCollapse => Box::new(WaveFunctionCollapseGenerator::new(
+                WaveFunctionCollapseParameters {
+                    width,
+                    height,
+                    ..Default::default()
+                },
+                samples,
+            )?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmptyGenerator {
+    width: usize,
+    height: usize,
+}
+
+impl EmptyGenerator {
+    pub fn new(width: usize, height: usize) -> Result<Self, GenerationError> {
+        if width == 0 || height == 0 {
+            return Err(GenerationError::InvalidParameters);
+        }
+        Ok(Self { width, height })
+    }
+}
+
+impl MapGenerator for EmptyGenerator {
+    fn generate_layer(&self, _seed: u64) -> Result<MapLayer, GenerationError> {
+        Ok(MapLayer::fill(
+            self.width,
+            self.height,
+            Tile::empty_ground(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GenerationError {
+    InvalidParameters,
+    NoSamples,
+    Contradiction,
+    NoTraversableTiles,
+}
+
+// End Structs
+
+// Helper Functions
+
+// Walls off every traversable region except the largest, returning how many tiles remain open.
+pub fn keep_largest_region(map_layer: &mut MapLayer) -> usize {
+    let mut unvisited = map_layer
+        .as_location_and_tile_vector()
+        .into_iter()
+        .filter(|(_location, tile)| tile.is_traversable())
+        .map(|(location, _tile)| location)
+        .collect::<Vec<_>>();
+    unvisited.sort();
+
+    let mut visited = HashSet::new();
+    let mut regions: Vec<Vec<TileLocation>> = Vec::new();
+    for start in unvisited {
+        if visited.contains(&start) {
+            continue;
+        }
+        let mut region = Vec::new();
+        let mut to_visit = vec![start];
+        while let Some(location) = to_visit.pop() {
+            let is_traversable = map_layer
+                .get_from_location(location)
+                .map_or(false, |tile| tile.is_traversable());
+            if is_traversable && visited.insert(location) {
+                region.push(location);
+                Direction::all()
+                    .into_iter()
+                    .for_each(|direction| to_visit.push(location + direction.as_tile_location()));
+            }
+        }
+        regions.push(region);
+    }
+
+    let largest = regions
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, region)| (region.len(), std::cmp::Reverse(*index)))
+        .map(|(index, _region)| index);
+    let mut n_open = 0;
+    for (index, region) in regions.into_iter().enumerate() {
+        if Some(index) == largest {
+            n_open = region.len();
+            continue;
+        }
+        for location in region {
+            map_layer
+                .update_at_location(Tile::wall(), location)
+                .expect("Regions only contain locations within the layer.");
+        }
+    }
+    n_open
+}
+
+fn link_layers_with_stairs(mut layers: Vec<MapLayer>, seed: u64) -> Result<Map, GenerationError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for i in 1..layers.len() {
+        let upper_location = choose_open_location(&layers[i - 1], &mut rng)?;
+        let lower_location = choose_open_location(&layers[i], &mut rng)?;
+
+        layers[i - 1]
+            .update_at_location(
+                Tile::empty(SurfaceTile::linked(
+                    STAIRS_DOWN_TERRAIN,
+                    MapLocation::new(i, lower_location),
+                )),
+                upper_location,
+            )
+            .expect("Open locations are within the layer.");
+        layers[i]
+            .update_at_location(
+                Tile::empty(SurfaceTile::linked(
+                    STAIRS_UP_TERRAIN,
+                    MapLocation::new(i - 1, upper_location),
+                )),
+                lower_location,
+            )
+            .expect("Open locations are within the layer.");
+    }
+    Ok(Map::new(layers))
+}
+
+// Only plain ground is chosen so that stairs never replace other stairs or features.
+fn choose_open_location(
+    map_layer: &MapLayer,
+    rng: &mut StdRng,
+) -> Result<TileLocation, GenerationError> {
+    let open_locations = map_layer
+        .as_location_and_tile_vector()
+        .into_iter()
+        .filter(|(_location, tile)| **tile == Tile::empty_ground())
+        .map(|(location, _tile)| location)
+        .collect::<Vec<_>>();
+    open_locations
+        .choose(rng)
+        .copied()
+        .ok_or(GenerationError::NoTraversableTiles)
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::events::LayerChange;
+
+    #[test]
+    fn test_keep_largest_region_walls_off_smaller_regions() {
+        let mut map_layer = MapLayer::fill(7, 7, Tile::empty_ground());
+        (0..7).for_each(|j| map_layer.update(Tile::wall(), 2, j).unwrap());
+
+        assert_eq!(keep_largest_region(&mut map_layer), 28);
+        assert!(!map_layer.get(0, 0).unwrap().is_traversable());
+        assert!(!map_layer.get(1, 6).unwrap().is_traversable());
+        assert!(map_layer.get(3, 0).unwrap().is_traversable());
+        assert!(map_layer.get(6, 6).unwrap().is_traversable());
+
+        let mut walls = MapLayer::fill(3, 3, Tile::wall());
+        assert_eq!(keep_largest_region(&mut walls), 0);
+    }
+
+    #[test]
+    fn test_generators_are_interchangeable() {
+        let sample = MapLayer::fill(10, 10, Tile::empty_ground());
+        for generator_type in MapGeneratorType::all() {
+            assert_eq!(
+                MapGeneratorType::from_string(&generator_type.to_string()),
+                Some(generator_type)
+            );
+
+            let generator = generator_type.build(30, 20, &[sample.clone()]).unwrap();
+            let map_layer = generator.generate_layer(3).unwrap();
+            assert_eq!(map_layer.width(), 30);
+            assert_eq!(map_layer.height(), 20);
+            assert_eq!(map_layer, generator.generate_layer(3).unwrap());
+
+            let map = generator.generate_map(3, 2).unwrap();
+            let stairs_down = map
+                .get_layer(0)
+                .unwrap()
+                .as_location_and_tile_vector()
+                .into_iter()
+                .map(|(location, _tile)| MapLocation::new(0, location))
+                .find(|location| {
+                    map.get_destination(*location, LayerChange::Descend)
+                        .unwrap()
+                        .is_some()
+                })
+                .unwrap();
+            let destination = map
+                .get_destination(stairs_down, LayerChange::Descend)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                map.get_destination(destination, LayerChange::Ascend)
+                    .unwrap(),
+                Some(stairs_down)
+            );
+        }
+
+        assert_eq!(
+            MapGeneratorType::WaveFunctionCollapse
+                .build(30, 20, &[])
+                .err(),
+            Some(GenerationError::NoSamples)
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod cave;
+pub mod dungeon;
+pub mod generator;
+pub mod wave_function_collapse;
@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::generator::{keep_largest_region, GenerationError, MapGenerator};
+use crate::constants::*;
+use crate::game::map::{MapLayer, SurfaceTile, Tile};
+
+// Neighbouring cells, ordered so that the opposite of offset d is d ^ 1.
+const OFFSETS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+// Structs
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WaveFunctionCollapseParameters {
+    pub width: usize,
+    pub height: usize,
+    pub pattern_size: usize,
+}
+
+impl Default for WaveFunctionCollapseParameters {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_MAP_WIDTH_IN_TILES,
+            DEFAULT_MAP_HEIGHT_IN_TILES,
+            DEFAULT_PATTERN_SIZE,
+        )
+    }
+}
+
+impl WaveFunctionCollapseParameters {
+    pub fn new(width: usize, height: usize, pattern_size: usize) -> Self {
+        Self {
+            width,
+            height,
+            pattern_size,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GenerationError> {
+        if self.pattern_size == 0 {
+            return Err(GenerationError::InvalidParameters);
+        }
+        if self.width < self.pattern_size || self.height < self.pattern_size {
+            return Err(GenerationError::InvalidParameters);
+        }
+        Ok(())
+    }
+}
+
+// Overlapping model: every pattern_size x pattern_size window of the samples is a pattern, and
+// two patterns may sit next to each other wherever they agree on the tiles they overlap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveFunctionCollapseGenerator {
+    parameters: WaveFunctionCollapseParameters,
+    patterns: Vec<Pattern>,
+    weights: Vec<f64>,
+    // compatible[d][p] holds every pattern that may be placed at OFFSETS[d] from pattern p.
+    compatible: [Vec<Vec<usize>>; 4],
+}
+
+impl MapGenerator for WaveFunctionCollapseGenerator {
+    fn generate_layer(&self, seed: u64) -> Result<MapLayer, GenerationError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut map_layer = self.collapse(&mut rng)?;
+        if keep_largest_region(&mut map_layer) == 0 {
+            return Err(GenerationError::NoTraversableTiles);
+        }
+        Ok(map_layer)
+    }
+}
+
+impl WaveFunctionCollapseGenerator {
+    pub fn new(
+        parameters: WaveFunctionCollapseParameters,
+        samples: &[MapLayer],
+    ) -> Result<Self, GenerationError> {
+        parameters.validate()?;
+
+        let mut pattern_indices: HashMap<Pattern, usize> = HashMap::new();
+        let mut patterns = Vec::new();
+        let mut weights = Vec::new();
+        for sample in samples {
+            for pattern in Pattern::all_from_sample(sample, parameters.pattern_size) {
+                match pattern_indices.get(&pattern) {
+                    Some(index) => weights[*index] += 1.,
+                    None => {
+                        pattern_indices.insert(pattern.clone(), patterns.len());
+                        patterns.push(pattern);
+                        weights.push(1.);
+                    }
+                }
+            }
+        }
+        if patterns.is_empty() {
+            return Err(GenerationError::NoSamples);
+        }
+
+        let compatible = OFFSETS.map(|(di, dj)| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    (0..patterns.len())
+                        .filter(|other| pattern.agrees(&patterns[*other], di, dj))
+                        .collect()
+                })
+                .collect()
+        });
+
+        Ok(Self {
+            parameters,
+            patterns,
+            weights,
+            compatible,
+        })
+    }
+
+    fn collapse(&self, rng: &mut StdRng) -> Result<MapLayer, GenerationError> {
+        for _ in 0..WAVE_FUNCTION_COLLAPSE_ATTEMPTS {
+            let mut wave = Wave::new(self);
+            if wave.run(rng) {
+                return Ok(self.to_map_layer(&wave));
+            }
+        }
+        Err(GenerationError::Contradiction)
+    }
+
+    // Cells cover every position where a whole pattern fits, and tiles past the last cell are
+    // read from the far side of the last pattern.
+    fn to_map_layer(&self, wave: &Wave) -> MapLayer {
+        let n = self.parameters.pattern_size;
+        MapLayer::new(
+            (0..self.parameters.width)
+                .map(|i| {
+                    (0..self.parameters.height)
+                        .map(|j| {
+                            let (cell_i, cell_j) = (i.min(wave.width - 1), j.min(wave.height - 1));
+                            let pattern = wave
+                                .get_collapsed(cell_i, cell_j)
+                                .expect("A successful run collapses every cell.");
+                            self.patterns[pattern].get(i - cell_i, j - cell_j, n)
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+}
+
+// End Structs
+
+// Helper Structs
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Pattern(Vec<Tile>);
+
+impl Pattern {
+    fn all_from_sample(sample: &MapLayer, size: usize) -> Vec<Self> {
+        if sample.width() < size || sample.height() < size {
+            return Vec::new();
+        }
+        (0..=(sample.width() - size))
+            .map(|i| (0..=(sample.height() - size)).map(move |j| (i, j)))
+            .flatten()
+            .map(|(i, j)| {
+                Self(
+                    (0..size)
+                        .map(|di| (0..size).map(move |dj| (i + di, j + dj)))
+                        .flatten()
+                        .map(|(i, j)| {
+                            normalize_tile(
+                                sample
+                                    .get(i, j)
+                                    .expect("Windows are taken from within the sample."),
+                            )
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn get(&self, i: usize, j: usize, size: usize) -> Tile {
+        self.0[i * size + j].clone()
+    }
+
+    // Whether other, shifted by (di, dj), matches self wherever the two overlap.
+    fn agrees(&self, other: &Self, di: i32, dj: i32) -> bool {
+        let size = (self.0.len() as f64).sqrt() as i32;
+        (di.max(0)..(size + di.min(0)))
+            .map(|i| (dj.max(0)..(size + dj.min(0))).map(move |j| (i, j)))
+            .flatten()
+            .all(|(i, j)| {
+                self.0[(i * size + j) as usize] == other.0[((i - di) * size + (j - dj)) as usize]
+            })
+    }
+}
+
+struct Wave<'a> {
+    generator: &'a WaveFunctionCollapseGenerator,
+    width: usize,
+    height: usize,
+    possible: Vec<Vec<bool>>,
+    // supports[cell][p][d] counts the patterns still possible at cell - OFFSETS[d] that allow p.
+    supports: Vec<Vec<[usize; 4]>>,
+    counts: Vec<usize>,
+    sum_of_weights: Vec<f64>,
+    sum_of_weight_log_weights: Vec<f64>,
+    to_propagate: Vec<(usize, usize)>,
+    has_contradiction: bool,
+}
+
+impl<'a> Wave<'a> {
+    fn new(generator: &'a WaveFunctionCollapseGenerator) -> Self {
+        let parameters = &generator.parameters;
+        let width = parameters.width - parameters.pattern_size + 1;
+        let height = parameters.height - parameters.pattern_size + 1;
+        let n_cells = width * height;
+        let n_patterns = generator.patterns.len();
+
+        let initial_supports = (0..n_patterns)
+            .map(|p| [0, 1, 2, 3].map(|d| generator.compatible[d ^ 1][p].len()))
+            .collect::<Vec<_>>();
+        let sum_of_weights = generator.weights.iter().sum::<f64>();
+        let sum_of_weight_log_weights = generator.weights.iter().map(|w| w * w.ln()).sum::<f64>();
+
+        Self {
+            generator,
+            width,
+            height,
+            possible: vec![vec![true; n_patterns]; n_cells],
+            supports: vec![initial_supports; n_cells],
+            counts: vec![n_patterns; n_cells],
+            sum_of_weights: vec![sum_of_weights; n_cells],
+            sum_of_weight_log_weights: vec![sum_of_weight_log_weights; n_cells],
+            to_propagate: Vec::new(),
+            has_contradiction: false,
+        }
+    }
+
+    fn run(&mut self, rng: &mut StdRng) -> bool {
+        // Patterns that nothing can sit beside are only allowed along that side of the map.
+        for cell in 0..self.possible.len() {
+            for d in 0..OFFSETS.len() {
+                if self.get_neighbour(cell, d ^ 1).is_none() {
+                    continue;
+                }
+                for p in 0..self.generator.patterns.len() {
+                    if self.supports[cell][p][d] == 0 && self.possible[cell][p] {
+                        self.ban(cell, p);
+                    }
+                }
+            }
+        }
+        self.propagate();
+
+        while !self.has_contradiction {
+            match self.get_lowest_entropy_cell(rng) {
+                None => return true,
+                Some(cell) => {
+                    self.observe(cell, rng);
+                    self.propagate();
+                }
+            }
+        }
+        false
+    }
+
+    fn get_lowest_entropy_cell(&self, rng: &mut StdRng) -> Option<usize> {
+        (0..self.possible.len())
+            .filter(|cell| self.counts[*cell] > 1)
+            .map(|cell| {
+                let entropy = self.sum_of_weights[cell].ln()
+                    - self.sum_of_weight_log_weights[cell] / self.sum_of_weights[cell];
+                // A little noise breaks ties without favouring any corner of the map.
+                (cell, entropy + rng.gen::<f64>() * 1e-6)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(cell, _entropy)| cell)
+    }
+
+    fn observe(&mut self, cell: usize, rng: &mut StdRng) {
+        let mut remaining = rng.gen::<f64>() * self.sum_of_weights[cell];
+        let mut chosen = None;
+        for p in 0..self.generator.patterns.len() {
+            if !self.possible[cell][p] {
+                continue;
+            }
+            chosen = Some(p);
+            remaining -= self.generator.weights[p];
+            if remaining <= 0. {
+                break;
+            }
+        }
+        let chosen = chosen.expect("Only cells with several possible patterns are observed.");
+
+        for p in 0..self.generator.patterns.len() {
+            if p != chosen && self.possible[cell][p] {
+                self.ban(cell, p);
+            }
+        }
+    }
+
+    fn ban(&mut self, cell: usize, p: usize) {
+        self.possible[cell][p] = false;
+        let weight = self.generator.weights[p];
+        self.counts[cell] -= 1;
+        self.sum_of_weights[cell] -= weight;
+        self.sum_of_weight_log_weights[cell] -= weight * weight.ln();
+        if self.counts[cell] == 0 {
+            self.has_contradiction = true;
+        }
+        self.to_propagate.push((cell, p));
+    }
+
+    fn propagate(&mut self) {
+        let generator = self.generator;
+        while let Some((cell, p)) = self.to_propagate.pop() {
+            for d in 0..OFFSETS.len() {
+                let neighbour = match self.get_neighbour(cell, d) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+                for q in generator.compatible[d][p].iter() {
+                    let support = &mut self.supports[neighbour][*q][d];
+                    if *support == 0 {
+                        continue;
+                    }
+                    *support -= 1;
+                    if *support == 0 && self.possible[neighbour][*q] {
+                        self.ban(neighbour, *q);
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_neighbour(&self, cell: usize, d: usize) -> Option<usize> {
+        let (di, dj) = OFFSETS[d];
+        let i = (cell / self.height) as i32 + di;
+        let j = (cell % self.height) as i32 + dj;
+        if i < 0 || j < 0 || i >= self.width as i32 || j >= self.height as i32 {
+            return None;
+        }
+        Some(i as usize * self.height + j as usize)
+    }
+
+    fn get_collapsed(&self, i: usize, j: usize) -> Option<usize> {
+        let cell = i * self.height + j;
+        if self.counts[cell] != 1 {
+            return None;
+        }
+        self.possible[cell]
+            .iter()
+            .position(|is_possible| *is_possible)
+    }
+}
+
+// End Helper Structs
+
+// Helper Functions
+
+// Links to other layers only make sense where they were placed, so they're learned as ground.
+fn normalize_tile(tile: &Tile) -> Tile {
+    if tile.get_surface().get_linked_destination().is_some() {
+        Tile::new(SurfaceTile::ground(), tile.get_stack().clone())
+    } else {
+        tile.clone()
+    }
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::game::map::{MapLocation, TileLocation};
+
+    fn get_pillar_sample() -> MapLayer {
+        let mut sample = MapLayer::fill(12, 12, Tile::empty_ground());
+        for i in (0..12).step_by(3) {
+            for j in (0..12).step_by(3) {
+                sample.update(Tile::wall(), i, j).unwrap();
+            }
+        }
+        sample
+    }
+
+    #[test]
+    fn test_wave_function_collapse_only_uses_learned_patterns() {
+        let sample = get_pillar_sample();
+        let parameters = WaveFunctionCollapseParameters::new(20, 15, 3);
+        let generator = WaveFunctionCollapseGenerator::new(parameters, &[sample.clone()]).unwrap();
+        let learned = Pattern::all_from_sample(&sample, 3)
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let map_layer = generator.collapse(&mut StdRng::seed_from_u64(5)).unwrap();
+        assert_eq!(map_layer.width(), 20);
+        assert_eq!(map_layer.height(), 15);
+        assert_eq!(
+            map_layer,
+            generator.collapse(&mut StdRng::seed_from_u64(5)).unwrap()
+        );
+        assert!(Pattern::all_from_sample(&map_layer, 3)
+            .iter()
+            .all(|pattern| learned.contains(pattern)));
+    }
+
+    #[test]
+    fn test_wave_function_collapse_learns_ground_instead_of_stairs() {
+        let mut sample = MapLayer::fill(5, 5, Tile::empty_ground());
+        sample
+            .update(
+                Tile::empty(SurfaceTile::linked(
+                    STAIRS_DOWN_TERRAIN,
+                    MapLocation::new(1, TileLocation::zero()),
+                )),
+                2,
+                2,
+            )
+            .unwrap();
+        let generator = WaveFunctionCollapseGenerator::new(
+            WaveFunctionCollapseParameters::new(8, 8, 2),
+            &[sample],
+        )
+        .unwrap();
+
+        assert_eq!(
+            generator.generate_layer(0).unwrap(),
+            MapLayer::fill(8, 8, Tile::empty_ground())
+        );
+    }
+
+    #[test]
+    fn test_wave_function_collapse_requires_samples() {
+        let parameters = WaveFunctionCollapseParameters::new(10, 10, 3);
+        assert_eq!(
+            WaveFunctionCollapseGenerator::new(parameters.clone(), &[]),
+            Err(GenerationError::NoSamples)
+        );
+        assert_eq!(
+            WaveFunctionCollapseGenerator::new(
+                parameters,
+                &[MapLayer::fill(2, 2, Tile::empty_ground())]
+            ),
+            Err(GenerationError::NoSamples)
+        );
+        assert_eq!(
+            WaveFunctionCollapseGenerator::new(
+                WaveFunctionCollapseParameters::new(10, 10, 0),
+                &[get_pillar_sample()]
+            ),
+            Err(GenerationError::InvalidParameters)
+        );
+    }
+}
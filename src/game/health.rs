@@ -0,0 +1,292 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::character::{
+    BloodComponent, BodyComponent, BodyPartTreeNode, BodyPartType, InventoryComponent,
+    LocationComponent,
+};
+use super::exploring::process_non_player_turn;
+use super::map::{AsciiTileAppearance, ColorCode, ObjectTile};
+use super::player::PlayerComponent;
+use super::resources::{GameState, RngResource};
+use super::vision::FieldOfView;
+use crate::constants::*;
+use crate::ui::LogState;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        let generalized_exploring =
+            || in_state(GameState::Exploring).or_else(in_state(GameState::NonPlayerTurns));
+        app.add_systems(
+            OnEnter(GameState::NonPlayerTurns),
+            progress_status_effects_system,
+        )
+        // After the turn order so that dying isn't undone by going back to exploring.
+        .add_systems(
+            Update,
+            bleed_out_system
+                .after(process_non_player_turn)
+                .run_if(generalized_exploring()),
+        );
+    }
+}
+
+// Systems
+
+fn progress_status_effects_system(
+    mut character_query: Query<(
+        &mut BodyComponent,
+        &mut BloodComponent,
+        Option<&PlayerComponent>,
+    )>,
+    mut rng: ResMut<RngResource>,
+    mut log: ResMut<LogState>,
+) {
+    for (mut body, mut blood, maybe_player) in character_query.iter_mut() {
+        // Progressed on a copy so that bodies are only touched when something changed.
+        let mut progressed = body.0.clone();
+        let progress = progressed.progress_status_effects(&mut rng);
+        if !progress.changes.is_empty() {
+            body.0 = progressed;
+        }
+        if progress.blood_loss > 0 {
+            blood.drain(progress.blood_loss);
+        } else if !blood.is_full() {
+            blood.regenerate(BLOOD_REGENERATION);
+        }
+
+        if maybe_player.is_some() {
+            for change in progress.changes {
+                log.log_string(&change.to_string());
+            }
+        }
+    }
+}
+
+fn bleed_out_system(
+    mut commands: Commands,
+    character_query: Query<
+        (
+            Entity,
+            &BloodComponent,
+            &LocationComponent,
+            Option<&Name>,
+            Option<&PlayerComponent>,
+        ),
+        Changed<BloodComponent>,
+    >,
+    field_of_view: Res<FieldOfView>,
+    mut log: ResMut<LogState>,
+) {
+    for (entity, blood, location, maybe_name, maybe_player) in character_query.iter() {
+        if !blood.is_empty() {
+            continue;
+        }
+        if maybe_player.is_some() {
+            log.log_string("You bleed to death.");
+            commands.insert_resource(NextState(Some(GameState::GameOver)));
+        } else {
+            // Deaths out of sight go unmentioned.
+            if field_of_view.is_visible(location.0) {
+                log.log_string(&format!(
+                    "{} bleeds to death.",
+                    maybe_name.map_or("Someone", |name| name.as_str())
+                ));
+            }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// End Systems
+
+// Structs
+
+// Treatments are carried as objects and used up on a single body part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Treatment {
+    Bandage,
+    Medicine,
+    Splint,
+}
+
+impl Treatment {
+    pub fn all() -> Vec<Self> {
+        vec![Self::Bandage, Self::Medicine, Self::Splint]
+    }
+
+    pub fn from_kind(kind: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|treatment| treatment.get_kind() == kind)
+    }
+
+    pub fn get_kind(&self) -> &str {
+        match self {
+            Self::Bandage => "bandage",
+            Self::Medicine => "medicine",
+            Self::Splint => "splint",
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::Bandage => "Bandage",
+            Self::Medicine => "Medicine",
+            Self::Splint => "Splint",
+        }
+        .to_string()
+    }
+
+    pub fn get_appearance(&self) -> AsciiTileAppearance {
+        match self {
+            Self::Bandage => AsciiTileAppearance::new('+', ColorCode::AntiqueWhite),
+            Self::Medicine => AsciiTileAppearance::new('!', ColorCode::Blue),
+            Self::Splint => AsciiTileAppearance::new('/', ColorCode::Orange),
+        }
+    }
+
+    pub fn get_message(&self, body_part_type: &BodyPartType) -> String {
+        let part = body_part_type.to_string().to_lowercase();
+        match self {
+            Self::Bandage => format!("You bandage your {}.", part),
+            Self::Medicine => format!("You treat the infection in your {}.", part),
+            Self::Splint => format!("You splint your {}.", part),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreatmentError {
+    NoneLeft(Treatment),
+    NoSuchPart,
+    NotNeeded(Treatment, BodyPartType),
+}
+
+impl fmt::Display for TreatmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoneLeft(treatment) => {
+                write!(f, "You don't have any {}.", treatment.get_kind())
+            }
+            Self::NoSuchPart => write!(f, "There's no such body part."),
+            Self::NotNeeded(treatment, body_part_type) => write!(
+                f,
+                "{} won't help your {}.",
+                treatment.to_string(),
+                body_part_type.to_string().to_lowercase()
+            ),
+        }
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+// Treats the part at part_index, in get_menu_text order, and uses up the treatment if it helped.
+pub fn treat(
+    body: &mut BodyPartTreeNode,
+    inventory: &mut InventoryComponent,
+    part_index: usize,
+    treatment: Treatment,
+) -> Result<String, TreatmentError> {
+    let object = ObjectTile::Treatment(treatment);
+    if inventory.count(&object) == 0 {
+        return Err(TreatmentError::NoneLeft(treatment));
+    }
+    let body_part = body
+        .get_part_mut(part_index)
+        .ok_or(TreatmentError::NoSuchPart)?;
+    let body_part_type = body_part.get_body_part_type().clone();
+    if !body_part.treat(treatment) {
+        return Err(TreatmentError::NotNeeded(treatment, body_part_type));
+    }
+    inventory.take(&object);
+    Ok(treatment.get_message(&body_part_type))
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::game::character::{BodyPart, BodyPartState, BodyPartStatusEffect, PartialBool};
+
+    #[test]
+    fn test_treatments_are_used_up_only_when_they_help() {
+        let mut rng = RngResource::seeded(7);
+        // Parts are numbered 0: Body, 1: Arm, 2: Leg.
+        let mut body = BodyPartTreeNode::new(
+            BodyPartType::Body.into(),
+            vec![
+                BodyPart::new_with_status_effects(
+                    BodyPartType::Arm,
+                    vec![BodyPartStatusEffect::Bleeding].into_iter().collect(),
+                )
+                .into(),
+                BodyPart::new(
+                    BodyPartType::Leg,
+                    BodyPartState::Nonfunctional,
+                    HashSet::new(),
+                )
+                .into(),
+            ],
+        );
+        let mut inventory = InventoryComponent::default();
+
+        assert_eq!(
+            treat(&mut body, &mut inventory, 1, Treatment::Bandage),
+            Err(TreatmentError::NoneLeft(Treatment::Bandage))
+        );
+        inventory.add(ObjectTile::Treatment(Treatment::Bandage), 1);
+        inventory.add(ObjectTile::Treatment(Treatment::Splint), 1);
+        assert_eq!(
+            treat(&mut body, &mut inventory, 2, Treatment::Bandage),
+            Err(TreatmentError::NotNeeded(
+                Treatment::Bandage,
+                BodyPartType::Leg
+            ))
+        );
+        assert_eq!(
+            treat(&mut body, &mut inventory, 3, Treatment::Bandage),
+            Err(TreatmentError::NoSuchPart)
+        );
+        assert!(treat(&mut body, &mut inventory, 1, Treatment::Bandage).is_ok());
+        assert_eq!(
+            inventory.count(&ObjectTile::Treatment(Treatment::Bandage)),
+            0
+        );
+        assert_eq!(
+            body.get_part_mut(1)
+                .unwrap()
+                .has_status_effect(BodyPartStatusEffect::Bleeding),
+            PartialBool::False
+        );
+
+        // Splints only go on broken limbs, and come off once the limb has healed.
+        assert!(treat(&mut body, &mut inventory, 0, Treatment::Splint).is_err());
+        assert!(treat(&mut body, &mut inventory, 2, Treatment::Splint).is_ok());
+        assert_eq!(
+            body.get_part_mut(2)
+                .unwrap()
+                .has_status_effect(BodyPartStatusEffect::Splinted),
+            PartialBool::True
+        );
+        for _ in 0..1000 {
+            body.progress_status_effects(&mut rng);
+        }
+        let leg = body.get_part_mut(2).unwrap();
+        assert_eq!(leg.get_state(), BodyPartState::Okay);
+        assert_eq!(
+            leg.has_status_effect(BodyPartStatusEffect::Splinted),
+            PartialBool::False
+        );
+    }
+}
@@ -1,12 +1,17 @@
 use bevy::prelude::*;
 
-use super::character::LocationComponent;
+use super::character::{BodyComponent, InventoryComponent, LocationComponent};
+use super::exploring::end_turn;
+use super::map::{MapError, ObjectTile};
+use super::pathfinding::OccupiedLocations;
 use super::{
     dialog::Dialog,
-    events::{ChooseDirectionEvent, ProgressPromptEvent, UpdateUIEvent},
+    events::{ChooseDirectionEvent, ProgressPromptEvent, TileChangedEvent, UpdateUIEvent},
     player::PlayerComponent,
-    resources::GameState,
+    resources::{GameState, LoadedMap},
 };
+use crate::constants::*;
+use crate::ui::LogState;
 
 pub struct InteractingPlugin;
 
@@ -72,13 +77,21 @@ fn handle_direction_choice_system(
     mut state: ResMut<InteractingState>,
     mut reader: EventReader<ChooseDirectionEvent>,
     mut update_ui_event_writer: EventWriter<UpdateUIEvent>,
-    player_query: Query<&LocationComponent, With<PlayerComponent>>,
+    mut tile_changed_event_writer: EventWriter<TileChangedEvent>,
+    mut map: ResMut<LoadedMap>,
+    mut log: ResMut<LogState>,
+    player_query: Query<
+        (&LocationComponent, &InventoryComponent, &BodyComponent),
+        With<PlayerComponent>,
+    >,
     interactable_query: Query<
         (&LocationComponent, &InteractableComponent),
         Without<PlayerComponent>,
     >,
+    character_query: Query<&LocationComponent, Without<PlayerComponent>>,
 ) {
-    let player_location = player_query.single();
+    let (player_location, inventory, body) = player_query.single();
+    let capabilities = body.0.get_capabilities();
     match *state {
         InteractingState::ChoosingDirection => {
             for direction_event in reader.iter() {
@@ -88,8 +101,44 @@ fn handle_direction_choice_system(
                     .iter()
                     .find(|(location, _interactable)| **location == interact_location)
                 {
+                    None if !capabilities.can_manipulate() => {
+                        log.log_string("You need a working hand for that.");
+                        commands.insert_resource(NextState(Some(GameState::Exploring)));
+                    }
                     None => {
-                        info!("Nothing to interact with.");
+                        let occupied = character_query
+                            .iter()
+                            .map(|location| location.0)
+                            .collect::<OccupiedLocations>();
+                        match map.0.toggle_door(
+                            interact_location.0,
+                            &occupied,
+                            inventory.contains(ObjectTile::Key),
+                        ) {
+                            Ok(message) => {
+                                log.log_string(&message);
+                                tile_changed_event_writer
+                                    .send(TileChangedEvent(interact_location.0));
+                                end_turn(&mut commands, DOOR_TICKS);
+                            }
+                            Err(MapError::DoorLocked) => {
+                                log.log_string("It's locked.");
+                                commands.insert_resource(NextState(Some(GameState::Exploring)));
+                            }
+                            Err(MapError::DoorBlocked) => {
+                                log.log_string("Something is in the way.");
+                                commands.insert_resource(NextState(Some(GameState::Exploring)));
+                            }
+                            Err(_) => {
+                                info!("Nothing to interact with.");
+                                commands.insert_resource(NextState(Some(GameState::Exploring)));
+                            }
+                        }
+                    }
+                    Some((_location, InteractableComponent(Interactable::Dialog(_))))
+                        if !capabilities.can_hear() =>
+                    {
+                        log.log_string("You can't hear a word they say.");
                         commands.insert_resource(NextState(Some(GameState::Exploring)));
                     }
                     Some((_location, interaction)) => {
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::character::LocationComponent;
+use super::chunk::RenderedTileGrid;
+use super::map::{ColorCode, MapLayer, MapTileComponent, TileLocation};
+use super::player::PlayerComponent;
+use super::resources::{GameState, LoadedMap};
+use super::vision::compute_field_of_view;
+use crate::constants::*;
+
+pub struct LightPlugin;
+
+impl Plugin for LightPlugin {
+    fn build(&self, app: &mut App) {
+        let generalized_exploring =
+            || in_state(GameState::Exploring).or_else(in_state(GameState::NonPlayerTurns));
+        app.insert_resource(LightMap::default())
+            .add_systems(
+                Update,
+                update_light_map_system.run_if(generalized_exploring()),
+            )
+            .add_systems(
+                Update,
+                render_light_system.after(update_light_map_system).run_if(
+                    generalized_exploring().and_then(resource_exists::<RenderedTileGrid>()),
+                ),
+            );
+    }
+}
+
+// Components
+
+// Carried light, e.g. the player's lamp.  Fixed lights come from terrain instead.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct LightSourceComponent(pub LightSpec);
+
+// End Components
+
+// Resources
+
+// How much light of each color reaches every tile of the player's layer.  Tiles that no light
+// reaches are missing and only get the ambient light.
+#[derive(Debug, Default, Clone, PartialEq, Resource)]
+pub struct LightMap {
+    map_layer: usize,
+    light: HashMap<TileLocation, [f32; 3]>,
+}
+
+impl LightMap {
+    pub fn compute(
+        map_layer: &MapLayer,
+        map_layer_index: usize,
+        sources: &Vec<(TileLocation, LightSpec)>,
+    ) -> Self {
+        let mut light: HashMap<TileLocation, [f32; 3]> = HashMap::new();
+        for (origin, spec) in sources {
+            let color = spec.color.to_color();
+            let tint = [color.r(), color.g(), color.b()];
+            // Light travels like sight, so walls cast shadows.
+            for location in compute_field_of_view(map_layer, *origin, spec.radius) {
+                let level = spec.get_level_at(origin.get_distance(location));
+                let channels = light.entry(location).or_insert([0.; 3]);
+                for (channel, tint_channel) in channels.iter_mut().zip(tint) {
+                    *channel = (*channel + level * tint_channel).min(1.);
+                }
+            }
+        }
+        Self {
+            map_layer: map_layer_index,
+            light,
+        }
+    }
+
+    pub fn get_map_layer(&self) -> usize {
+        self.map_layer
+    }
+
+    // The brightest channel of the light at the location, from 0 in the dark to 1.
+    pub fn get_level(&self, location: TileLocation) -> f32 {
+        self.light
+            .get(&location)
+            .map_or(0., |channels| channels.iter().cloned().fold(0., f32::max))
+    }
+
+    pub fn is_lit(&self, location: TileLocation) -> bool {
+        self.get_level(location) >= MIN_VISIBLE_LIGHT_LEVEL
+    }
+
+    // The color as it looks under this tile's light, keeping its alpha.
+    pub fn light_color(&self, color: Color, location: TileLocation) -> Color {
+        let channels = self.light.get(&location).cloned().unwrap_or([0.; 3]);
+        let [r, g, b] =
+            channels.map(|channel| AMBIENT_LIGHT_LEVEL + (1. - AMBIENT_LIGHT_LEVEL) * channel);
+        Color::rgba(color.r() * r, color.g() * g, color.b() * b, color.a())
+    }
+}
+
+// End Resources
+
+// Systems
+
+pub fn update_light_map_system(
+    mut light_map: ResMut<LightMap>,
+    map: Res<LoadedMap>,
+    player_query: Query<&LocationComponent, With<PlayerComponent>>,
+    source_query: Query<(&LocationComponent, &LightSourceComponent)>,
+    moved_source_query: Query<(), (With<LightSourceComponent>, Changed<LocationComponent>)>,
+) {
+    let map_layer_index = match player_query.get_single() {
+        Ok(player_location) => player_location.0.get_map_layer(),
+        Err(_) => return,
+    };
+    // Light only changes when the map does or a carried light moves.
+    if !map.is_changed() && moved_source_query.is_empty() && !light_map.is_added() {
+        return;
+    }
+    let map_layer = match map.0.get_layer(map_layer_index) {
+        Ok(map_layer) => map_layer,
+        Err(_) => return,
+    };
+
+    let mut sources = map_layer
+        .as_location_and_tile_vector()
+        .into_iter()
+        .filter_map(|(location, tile)| {
+            tile.get_surface()
+                .get_terrain()
+                .get_light()
+                .map(|spec| (location, spec.clone()))
+        })
+        .collect::<Vec<_>>();
+    sources.extend(
+        source_query
+            .iter()
+            .filter(|(location, _light)| location.0.get_map_layer() == map_layer_index)
+            .map(|(location, light)| (location.0.get_tile_location(), light.0.clone())),
+    );
+
+    let new_light_map = LightMap::compute(map_layer, map_layer_index, &sources);
+    if *light_map != new_light_map {
+        *light_map = new_light_map;
+    }
+}
+
+fn render_light_system(
+    light_map: Res<LightMap>,
+    rendered_grid: Res<RenderedTileGrid>,
+    mut tile_query: Query<(
+        &MapTileComponent,
+        Option<&mut Text>,
+        Option<&mut TextureAtlasSprite>,
+    )>,
+) {
+    for (MapTileComponent(location), maybe_text, maybe_sprite) in tile_query.iter_mut() {
+        let base_color = match rendered_grid.get(*location) {
+            Some(appearance) => appearance.get_color(),
+            None => continue,
+        };
+        // Only write when something changed so that text isn't laid out again every frame.
+        if let Some(mut text) = maybe_text {
+            for i in 0..text.sections.len() {
+                let color = text.sections[i].style.color;
+                let lit = light_map.light_color(base_color.with_a(color.a()), *location);
+                if color != lit {
+                    text.sections[i].style.color = lit;
+                }
+            }
+        }
+        if let Some(mut sprite) = maybe_sprite {
+            let lit = light_map.light_color(base_color.with_a(sprite.color.a()), *location);
+            if sprite.color != lit {
+                sprite.color = lit;
+            }
+        }
+    }
+}
+
+// End Systems
+
+// Structs
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LightSpec {
+    radius: u32,
+    color: ColorCode,
+}
+
+impl LightSpec {
+    pub fn new(radius: u32, color: ColorCode) -> Self {
+        Self { radius, color }
+    }
+
+    pub fn lamp() -> Self {
+        Self::new(LAMP_LIGHT_RADIUS, ColorCode::AntiqueWhite)
+    }
+
+    pub fn get_radius(&self) -> u32 {
+        self.radius
+    }
+
+    // Full strength at the source, fading to nothing just past the radius.
+    pub fn get_level_at(&self, distance: f32) -> f32 {
+        (1. - distance / (self.radius as f32 + 1.)).max(0.)
+    }
+}
+
+// End Structs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::Tile;
+
+    fn white_light(radius: u32) -> LightSpec {
+        LightSpec::new(radius, ColorCode::AntiqueWhite)
+    }
+
+    #[test]
+    fn test_light_fades_with_distance() {
+        let map_layer = MapLayer::fill(11, 11, Tile::empty_ground());
+        let origin = TileLocation::new(5, 5);
+        let light_map = LightMap::compute(&map_layer, 0, &vec![(origin, white_light(3))]);
+
+        let near = light_map.get_level(TileLocation::new(6, 5));
+        let far = light_map.get_level(TileLocation::new(8, 5));
+        assert!(light_map.get_level(origin) > near);
+        assert!(near > far);
+        assert!(far > 0.);
+        assert_eq!(light_map.get_level(TileLocation::new(9, 5)), 0.);
+        assert!(!light_map.is_lit(TileLocation::new(0, 0)));
+
+        let dark = light_map.light_color(Color::WHITE, TileLocation::new(0, 0));
+        assert_eq!(dark.r(), AMBIENT_LIGHT_LEVEL);
+        assert_eq!(dark.a(), 1.);
+    }
+
+    #[test]
+    fn test_light_is_blocked_by_walls_and_adds_up() {
+        let mut map_layer = MapLayer::fill(11, 11, Tile::empty_ground());
+        (0..11).for_each(|j| map_layer.update(Tile::wall(), 7, j).unwrap());
+        let origin = TileLocation::new(5, 5);
+        let light_map = LightMap::compute(&map_layer, 0, &vec![(origin, white_light(5))]);
+        assert!(light_map.is_lit(TileLocation::new(7, 5)));
+        assert!(!light_map.is_lit(TileLocation::new(8, 5)));
+
+        let single = light_map.get_level(TileLocation::new(4, 5));
+        let light_map = LightMap::compute(
+            &map_layer,
+            0,
+            &vec![(origin, white_light(5)), (origin, white_light(5))],
+        );
+        assert!(light_map.get_level(TileLocation::new(4, 5)) > single);
+        assert!(light_map.get_level(origin) <= 1.);
+    }
+}
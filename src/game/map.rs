@@ -1,33 +1,63 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::ops::{Add, AddAssign};
 
-use bevy::utils::Duration;
 use bevy::{ecs::system::EntityCommands, prelude::*};
 use serde::{Deserialize, Serialize};
 
-use super::events::BoundStateComponent;
+use super::events::Direction;
+use super::events::{BoundStateComponent, LayerChange};
+use super::health::Treatment;
+use super::map_file::{CompactMap, MapFile, SurfaceTileFile};
+use super::map_metadata::MapMetadata;
+use super::particle::ParticleSpec;
+use super::pathfinding::{find_path, OccupiedLocations};
 use super::resources::GameState;
-use super::{
-    events::Direction,
-    particle::{
-        ParticleAppearance, ParticleDirection, ParticleDuration, ParticleLocation,
-        ParticleMovement, ParticleSpec, ParticleTiming,
-    },
-};
+use super::terrain::{TerrainRegistry, TerrainSpec};
+use super::tileset::{RenderSettings, SpriteTileAppearance};
+use super::trap::{Trap, TrapSpec};
 use crate::constants::*;
 
+// Components
+#[derive(Component, Clone, Copy)]
+pub struct MapTileComponent(pub TileLocation);
+
+// End Components
+
+// Saved in the compact palette format, see map_file.rs.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
-pub struct Map(Vec<MapLayer>);
+#[serde(into = "CompactMap", try_from = "MapFile")]
+pub struct Map {
+    layers: Vec<MapLayer>,
+    metadata: MapMetadata,
+}
 
 impl Map {
     pub fn new(layers: Vec<MapLayer>) -> Self {
-        Self(layers)
+        Self::with_metadata(layers, MapMetadata::default())
+    }
+
+    pub fn with_metadata(layers: Vec<MapLayer>, metadata: MapMetadata) -> Self {
+        Self { layers, metadata }
+    }
+
+    pub fn get_layers(&self) -> &Vec<MapLayer> {
+        &self.layers
     }
 
     pub fn get_layer(&self, i: usize) -> Result<&MapLayer, MapError> {
-        if i >= self.0.len() {
+        if i >= self.layers.len() {
             return Err(MapError::OutOfBounds);
         }
-        return Ok(&self.0[i]);
+        return Ok(&self.layers[i]);
+    }
+
+    pub fn get_metadata(&self) -> &MapMetadata {
+        &self.metadata
+    }
+
+    pub fn set_metadata(&mut self, metadata: MapMetadata) {
+        self.metadata = metadata;
     }
 
     pub fn get(&self, location: MapLocation) -> Result<&Tile, MapError> {
@@ -40,8 +70,258 @@ impl Map {
             .and_then(|layer| layer.is_traversable(location.get_tile_location()))
     }
 
+    pub fn get_destination(
+        &self,
+        location: MapLocation,
+        layer_change: LayerChange,
+    ) -> Result<Option<MapLocation>, MapError> {
+        Ok(self
+            .get(location)?
+            .get_surface()
+            .get_destination(layer_change))
+    }
+
+    pub fn find_path(
+        &self,
+        start: MapLocation,
+        goal: MapLocation,
+        occupied: &OccupiedLocations,
+    ) -> Result<Vec<Direction>, MapError> {
+        if start.get_map_layer() != goal.get_map_layer() {
+            return Err(MapError::DifferentLayers);
+        }
+        self.get_layer(start.get_map_layer())?.find_path(
+            start.get_tile_location(),
+            goal.get_tile_location(),
+            &occupied.on_layer(start.get_map_layer()),
+        )
+    }
+
+    pub fn find_nearest_open_location(
+        &self,
+        location: MapLocation,
+        occupied: &OccupiedLocations,
+    ) -> Option<MapLocation> {
+        let map_layer = location.get_map_layer();
+        self.get_layer(map_layer)
+            .ok()?
+            .as_location_and_tile_vector()
+            .into_iter()
+            .map(|(tile_location, tile)| (MapLocation::new(map_layer, tile_location), tile))
+            .filter(|(open_location, tile)| {
+                tile.is_traversable() && !occupied.contains(*open_location)
+            })
+            .min_by_key(|(open_location, _tile)| {
+                open_location
+                    .get_tile_location()
+                    .get_chebyshev_distance(location.get_tile_location())
+            })
+            .map(|(open_location, _tile)| open_location)
+    }
+
+    // Opens or closes the door at the location and returns the door's message.  Doors can't be
+    // closed on whoever is standing in them.
+    pub fn toggle_door(
+        &mut self,
+        location: MapLocation,
+        occupied: &OccupiedLocations,
+        has_key: bool,
+    ) -> Result<String, MapError> {
+        let tile = self.get(location)?;
+        let door = tile
+            .get_surface()
+            .get_terrain()
+            .get_door()
+            .ok_or(MapError::NoDoor)?;
+        if door.is_locked() && !has_key {
+            return Err(MapError::DoorLocked);
+        }
+
+        let toggled = Tile::new(
+            SurfaceTile::new(door.get_toggles_to()),
+            tile.get_stack().clone(),
+        );
+        if !toggled.is_traversable() && occupied.contains(location) {
+            return Err(MapError::DoorBlocked);
+        }
+        let message = door.get_message().to_string();
+        self.update(toggled, location)?;
+        Ok(message)
+    }
+
+    pub fn spring_traps(
+        &mut self,
+        location: MapLocation,
+    ) -> Result<Vec<&'static TrapSpec>, MapError> {
+        let mut tile = self.get(location)?.clone();
+        let sprung = tile.spring_traps();
+        if !sprung.is_empty() {
+            self.update(tile, location)?;
+        }
+        Ok(sprung)
+    }
+
+    // Removes every copy of the object from the tile and returns how many there were.
+    pub fn take_objects(
+        &mut self,
+        location: MapLocation,
+        object: ObjectTile,
+    ) -> Result<usize, MapError> {
+        let mut tile = self.get(location)?.clone();
+        let count = tile.take_objects(object);
+        if count > 0 {
+            self.update(tile, location)?;
+        }
+        Ok(count)
+    }
+
+    // Puts the object on top of the tile's stack.
+    pub fn place_object(
+        &mut self,
+        location: MapLocation,
+        object: ObjectTile,
+    ) -> Result<(), MapError> {
+        let mut tile = self.get(location)?.clone();
+        tile.place_object(object);
+        self.update(tile, location)
+    }
+
     pub fn update(&mut self, tile: Tile, location: MapLocation) -> Result<(), MapError> {
-        self.0[location.get_map_layer()].update_at_location(tile, location.get_tile_location())
+        self.get_layer_mut(location.get_map_layer())?
+            .update_at_location(tile, location.get_tile_location())
+    }
+
+    // Resizing, cropping and shifting a layer move what's on it, so spawns, placements and links
+    // into the layer move along and are dropped if they fall off the edge.
+    pub fn resize_layer(
+        &mut self,
+        map_layer: usize,
+        width: usize,
+        height: usize,
+        fill: &Tile,
+    ) -> Result<(), MapError> {
+        self.get_layer_mut(map_layer)?.resize(width, height, fill);
+        self.relocate(|location| {
+            Self::keep_if_in(location, map_layer, width, height, TileLocation::zero())
+        });
+        Ok(())
+    }
+
+    pub fn crop_layer(
+        &mut self,
+        map_layer: usize,
+        origin: TileLocation,
+        width: usize,
+        height: usize,
+    ) -> Result<(), MapError> {
+        self.get_layer_mut(map_layer)?.crop(origin, width, height)?;
+        self.relocate(|location| Self::keep_if_in(location, map_layer, width, height, origin));
+        Ok(())
+    }
+
+    pub fn shift_layer(
+        &mut self,
+        map_layer: usize,
+        offset: TileLocation,
+        fill: &Tile,
+    ) -> Result<(), MapError> {
+        let layer = self.get_layer_mut(map_layer)?;
+        layer.shift(offset, fill);
+        let (width, height) = (layer.width(), layer.height());
+        let origin = TileLocation::new(-offset.i, -offset.j);
+        self.relocate(|location| Self::keep_if_in(location, map_layer, width, height, origin));
+        Ok(())
+    }
+
+    // Inserting, removing and moving layers renumber the layers after them, and everything that
+    // refers to a layer by number is renumbered to match.
+    pub fn insert_layer(&mut self, map_layer: usize, layer: MapLayer) -> Result<(), MapError> {
+        if map_layer > self.layers.len() {
+            return Err(MapError::OutOfBounds);
+        }
+        self.layers.insert(map_layer, layer);
+        self.relocate(|location| {
+            let index = location.get_map_layer();
+            let index = if index >= map_layer { index + 1 } else { index };
+            Some(MapLocation::new(index, location.get_tile_location()))
+        });
+        Ok(())
+    }
+
+    // Spawns and placements on the removed layer are dropped, and links into it are cut.
+    pub fn remove_layer(&mut self, map_layer: usize) -> Result<MapLayer, MapError> {
+        self.get_layer(map_layer)?;
+        let removed = self.layers.remove(map_layer);
+        self.relocate(|location| {
+            let index = location.get_map_layer();
+            match index.cmp(&map_layer) {
+                Ordering::Less => Some(location),
+                Ordering::Equal => None,
+                Ordering::Greater => {
+                    Some(MapLocation::new(index - 1, location.get_tile_location()))
+                }
+            }
+        });
+        Ok(removed)
+    }
+
+    // Moves the layer at from so that it ends up at to, like removing it and inserting it again.
+    pub fn move_layer(&mut self, from: usize, to: usize) -> Result<(), MapError> {
+        self.get_layer(from)?;
+        self.get_layer(to)?;
+        let layer = self.layers.remove(from);
+        self.layers.insert(to, layer);
+        self.relocate(|location| {
+            let index = location.get_map_layer();
+            let index = if index == from {
+                to
+            } else if from < index && index <= to {
+                index - 1
+            } else if to <= index && index < from {
+                index + 1
+            } else {
+                index
+            };
+            Some(MapLocation::new(index, location.get_tile_location()))
+        });
+        Ok(())
+    }
+
+    fn get_layer_mut(&mut self, i: usize) -> Result<&mut MapLayer, MapError> {
+        self.layers.get_mut(i).ok_or(MapError::OutOfBounds)
+    }
+
+    // Moves every location stored in the map, i.e. spawns, placements and stair and portal
+    // destinations.  Locations that map to None are dropped.
+    fn relocate(&mut self, relocate: impl Fn(MapLocation) -> Option<MapLocation>) {
+        self.metadata.relocate(&relocate);
+        self.layers
+            .iter_mut()
+            .flat_map(|layer| layer.0.iter_mut().flatten())
+            .for_each(|tile| {
+                tile.surface.destination = tile.surface.destination.and_then(&relocate);
+            });
+    }
+
+    // Where a location on the map layer ends up when the width x height rectangle starting at
+    // origin becomes the whole layer.  Locations on other layers stay where they are.
+    fn keep_if_in(
+        location: MapLocation,
+        map_layer: usize,
+        width: usize,
+        height: usize,
+        origin: TileLocation,
+    ) -> Option<MapLocation> {
+        if location.get_map_layer() != map_layer {
+            return Some(location);
+        }
+        let tile_location = location.get_tile_location();
+        let moved = TileLocation::new(tile_location.i - origin.i, tile_location.j - origin.j);
+        let is_inside = moved.i >= 0
+            && moved.j >= 0
+            && (moved.i as usize) < width
+            && (moved.j as usize) < height;
+        is_inside.then(|| MapLocation::new(map_layer, moved))
     }
 }
 
@@ -63,6 +343,12 @@ impl MapLayer {
         Self(tiles)
     }
 
+    // Layers read straight from a file skip the check in new.
+    pub fn is_rectangular(&self) -> bool {
+        let height = self.height();
+        self.0.iter().all(|column| column.len() == height)
+    }
+
     pub fn get_tiles_cloned(&self) -> Vec<Vec<Tile>> {
         self.0.clone()
     }
@@ -150,6 +436,47 @@ impl MapLayer {
         })
     }
 
+    // Tiles keep their locations; new tiles are filled in and tiles past the new size are lost.
+    pub fn resize(&mut self, width: usize, height: usize, fill: &Tile) {
+        self.0.resize(width, vec![fill.clone(); height]);
+        self.0
+            .iter_mut()
+            .for_each(|column| column.resize(height, fill.clone()));
+    }
+
+    // Keeps the width x height rectangle starting at origin, which becomes the new (0, 0).
+    pub fn crop(
+        &mut self,
+        origin: TileLocation,
+        width: usize,
+        height: usize,
+    ) -> Result<(), MapError> {
+        if origin.i < 0
+            || origin.j < 0
+            || origin.i as usize + width > self.width()
+            || origin.j as usize + height > self.height()
+        {
+            return Err(MapError::OutOfBounds);
+        }
+        let (i, j) = (origin.i as usize, origin.j as usize);
+        self.0 = self.0[i..i + width]
+            .iter()
+            .map(|column| column[j..j + height].to_vec())
+            .collect();
+        Ok(())
+    }
+
+    // Moves every tile by the offset.  Tiles moved off the layer are lost and the tiles they leave
+    // behind are filled in.
+    pub fn shift(&mut self, offset: TileLocation, fill: &Tile) {
+        let mut shifted = Self::fill(self.width(), self.height(), fill.clone());
+        for (location, tile) in self.as_location_and_tile_vector() {
+            // Anything shifted out of bounds is dropped.
+            let _ = shifted.update_at_location(tile.clone(), location + offset);
+        }
+        *self = shifted;
+    }
+
     fn is_in_bounds(&self, i: usize, j: usize) -> bool {
         !self.is_out_of_bounds(i, j)
     }
@@ -161,11 +488,29 @@ impl MapLayer {
     fn is_traversable(&self, tile_location: TileLocation) -> Result<bool, MapError> {
         Ok(self.get_from_location(tile_location)?.is_traversable())
     }
+
+    pub fn find_path(
+        &self,
+        start: TileLocation,
+        goal: TileLocation,
+        occupied: &HashSet<TileLocation>,
+    ) -> Result<Vec<Direction>, MapError> {
+        find_path(self, start, goal, occupied)
+    }
+
+    pub fn is_opaque(&self, tile_location: TileLocation) -> Result<bool, MapError> {
+        Ok(self.get_from_location(tile_location)?.is_opaque())
+    }
 }
 
-#[derive(Debug, Clone, Copy, Hash, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum MapError {
     OutOfBounds,
+    DifferentLayers,
+    NoPathExists,
+    NoDoor,
+    DoorLocked,
+    DoorBlocked,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -184,70 +529,212 @@ impl Tile {
     }
 
     pub fn empty_ground() -> Self {
-        Self::empty(SurfaceTile::Ground)
+        Self::empty(SurfaceTile::ground())
     }
 
     pub fn wall() -> Self {
-        Self::empty(SurfaceTile::Wall)
+        Self::empty(SurfaceTile::wall())
     }
 
     pub fn object(object: ObjectTile) -> Self {
-        Self::new(SurfaceTile::Ground, vec![object])
+        Self::new(SurfaceTile::ground(), vec![object])
     }
 
-    pub fn get_surface(&self) -> SurfaceTile {
-        self.surface
+    pub fn get_surface(&self) -> &SurfaceTile {
+        &self.surface
     }
 
     pub fn get_stack(&self) -> &Vec<ObjectTile> {
         &self.stack
     }
 
-    pub fn get_top_of_stack(&self) -> Option<ObjectTile> {
-        self.stack.last().cloned()
+    // Hidden objects are neither drawn nor described.
+    pub fn get_visible_stack(&self) -> Vec<&ObjectTile> {
+        self.stack
+            .iter()
+            .filter(|object| !object.is_hidden())
+            .collect()
+    }
+
+    pub fn get_top_visible_object(&self) -> Option<ObjectTile> {
+        self.get_visible_stack()
+            .last()
+            .map(|object| (*object).clone())
+    }
+
+    // Sets off every trap on the tile and returns their specs.  Traps that reveal themselves are
+    // revealed and one-shot traps are removed.
+    pub fn spring_traps(&mut self) -> Vec<&'static TrapSpec> {
+        let mut sprung = Vec::new();
+        self.stack.retain_mut(|object| {
+            let trap = match object {
+                ObjectTile::Trap(trap) => trap,
+                _ => return true,
+            };
+            let spec = match trap.get_spec() {
+                Some(spec) => spec,
+                None => return true,
+            };
+            sprung.push(spec);
+            if spec.reveals_on_trigger() {
+                trap.reveal();
+            }
+            !spec.is_one_shot()
+        });
+        sprung
+    }
+
+    pub fn place_object(&mut self, object: ObjectTile) {
+        self.stack.push(object);
+    }
+
+    pub fn take_objects(&mut self, object: ObjectTile) -> usize {
+        let count = self.stack.len();
+        self.stack.retain(|stacked| *stacked != object);
+        count - self.stack.len()
     }
 
     pub fn is_traversable(&self) -> bool {
-        match self.get_surface() {
-            SurfaceTile::Ground => true,
-            SurfaceTile::Fireplace => true,
-            SurfaceTile::Wall => false,
+        self.surface.get_terrain().is_traversable()
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.surface.get_terrain().is_opaque()
+    }
+
+    pub fn get_movement_cost(&self) -> u32 {
+        self.surface.get_terrain().get_movement_cost()
+    }
+
+    // Everything on the tile from the top of the stack down, e.g. "Trap and item on ground."
+    pub fn get_description(&self) -> String {
+        let objects = self
+            .get_visible_stack()
+            .into_iter()
+            .rev()
+            .map(|object| object.to_string())
+            .collect::<Vec<_>>();
+        let description = match objects.as_slice() {
+            [] => self.surface.to_string(),
+            [object] => format!("{} on {}", object, self.surface.to_string()),
+            [rest @ .., last] => format!(
+                "{} and {} on {}",
+                rest.join(", "),
+                last,
+                self.surface.to_string()
+            ),
+        };
+        let mut characters = description.chars();
+        match characters.next() {
+            Some(first) => format!("{}{}.", first.to_uppercase(), characters.as_str()),
+            None => description,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
-pub enum SurfaceTile {
-    Ground,
-    Wall,
-    Fireplace,
+// The terrain is an id into the TerrainRegistry.  Stairs and portals also carry where they lead.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(into = "SurfaceTileFile", from = "SurfaceTileFile")]
+pub struct SurfaceTile {
+    terrain: String,
+    destination: Option<MapLocation>,
 }
 
 impl SurfaceTile {
-    pub fn get_particle_spec(&self) -> Option<ParticleSpec> {
-        match self {
-            Self::Fireplace => Some(ParticleSpec::new(
-                ParticleTiming::Every(ParticleDuration::Exact(Duration::from_millis(500))),
-                ParticleLocation::Exact(Direction::Up.as_tile_location()),
-                ParticleMovement::new(
-                    ParticleTiming::Every(ParticleDuration::Exact(Duration::from_millis(500))),
-                    ParticleDirection::Weighted(vec![
-                        (Direction::Up, 2),
-                        (Direction::UpLeft, 1),
-                        (Direction::UpRight, 1),
-                    ]),
-                ),
-                ParticleAppearance::Constant(AsciiTileAppearance::new('S', ColorCode::Gray)),
-            )),
-            _ => None,
+    pub fn new(terrain: &str) -> Self {
+        Self {
+            terrain: terrain.to_string(),
+            destination: None,
+        }
+    }
+
+    pub fn linked(terrain: &str, destination: MapLocation) -> Self {
+        Self {
+            terrain: terrain.to_string(),
+            destination: Some(destination),
         }
     }
+
+    pub fn ground() -> Self {
+        Self::new(GROUND_TERRAIN)
+    }
+
+    pub fn wall() -> Self {
+        Self::new(WALL_TERRAIN)
+    }
+
+    pub fn get_terrain_id(&self) -> &str {
+        &self.terrain
+    }
+
+    pub fn get_terrain(&self) -> &'static TerrainSpec {
+        TerrainRegistry::global().get(&self.terrain)
+    }
+
+    // Where the surface leads regardless of direction, see get_destination.
+    pub fn get_linked_destination(&self) -> Option<MapLocation> {
+        self.destination
+    }
+
+    pub fn to_string(&self) -> String {
+        self.get_terrain().get_name().to_string()
+    }
+
+    pub fn get_appearance(&self) -> AsciiTileAppearance {
+        self.get_terrain().get_appearance()
+    }
+
+    pub fn get_destination(&self, layer_change: LayerChange) -> Option<MapLocation> {
+        self.destination
+            .filter(|_destination| self.get_terrain().links(layer_change))
+    }
+
+    pub fn get_particle_spec(&self) -> Option<ParticleSpec> {
+        self.get_terrain().get_particle_spec()
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum ObjectTile {
     Item,
-    Trap,
+    Trap(Trap),
+    Key,
+    Treatment(Treatment),
+}
+
+impl ObjectTile {
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::Trap(trap) => trap.to_string(),
+            _ => self.get_kind().to_string(),
+        }
+    }
+
+    // The same for every object of a variant, e.g. "trap" for every trap spec.
+    pub fn get_kind(&self) -> &str {
+        match self {
+            Self::Item => "item",
+            Self::Trap(_) => "trap",
+            Self::Key => "key",
+            Self::Treatment(treatment) => treatment.get_kind(),
+        }
+    }
+
+    pub fn get_appearance(&self) -> AsciiTileAppearance {
+        match self {
+            Self::Item => AsciiTileAppearance::new('*', ColorCode::Yellow),
+            Self::Trap(_) => AsciiTileAppearance::new('^', ColorCode::Red),
+            Self::Key => AsciiTileAppearance::new('k', ColorCode::Yellow),
+            Self::Treatment(treatment) => treatment.get_appearance(),
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        match self {
+            Self::Trap(trap) => !trap.is_revealed(),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -283,7 +770,9 @@ impl MapLocation {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
 pub struct TileLocation {
     pub i: i32,
     pub j: i32,
@@ -320,6 +809,16 @@ impl TileLocation {
         Self::new(0, 0)
     }
 
+    pub fn get_distance(&self, other: TileLocation) -> f32 {
+        (((self.i - other.i).pow(2) + (self.j - other.j).pow(2)) as f32).sqrt()
+    }
+
+    pub fn get_chebyshev_distance(&self, other: TileLocation) -> u32 {
+        (self.i - other.i)
+            .unsigned_abs()
+            .max((self.j - other.j).unsigned_abs())
+    }
+
     pub fn step(&mut self, direction: Direction) {
         let new_self = self.clone() + direction.as_tile_location();
         self.i = new_self.i;
@@ -337,20 +836,29 @@ impl TileGrid {
         Self { grid }
     }
 
-    pub fn from_map_layer(layer: MapLayer) -> Self {
+    pub fn from_map_layer(layer: &MapLayer, render_settings: &RenderSettings) -> Self {
         Self::new(
-            layer
-                .get_tiles_cloned()
-                .into_iter()
-                .map(|row| {
-                    row.into_iter()
-                        .map(|tile| TileAppearance::from_tile(&tile))
+            (0..layer.width())
+                .map(|i| {
+                    (0..layer.height())
+                        .map(|j| {
+                            let tile = layer.get(i, j).expect("We're iterating within the layer.");
+                            render_settings.get_tile_appearance(tile)
+                        })
                         .collect()
                 })
                 .collect(),
         )
     }
 
+    pub fn width(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.first().map_or(0, |column| column.len())
+    }
+
     pub fn get(&self, i: usize, j: usize) -> Option<TileAppearance> {
         if self.is_in_bounds(i, j) {
             Some(self.grid[i][j].clone())
@@ -368,17 +876,6 @@ impl TileGrid {
         }
     }
 
-    pub fn render(&self, commands: &mut Commands, font: Handle<Font>, bound_state: GameState) {
-        for (location, tile) in self.enumerated().iter() {
-            let _tile_entity = tile.render(
-                &mut (commands.spawn_empty()),
-                font.clone(),
-                bound_state,
-                Self::tile_to_world_coordinates(*location),
-            );
-        }
-    }
-
     pub fn tile_to_world_coordinates(location: TileLocation) -> Vec2 {
         Vec2::new(
             (location.i * (TILE_WIDTH as i32)) as f32,
@@ -386,18 +883,11 @@ impl TileGrid {
         )
     }
 
-    fn enumerated(&self) -> Vec<(TileLocation, TileAppearance)> {
-        self.grid
-            .iter()
-            .enumerate()
-            .map(|(i, row)| {
-                row.iter()
-                    .enumerate()
-                    .map(|(j, tile)| (TileLocation::new(i as i32, j as i32), tile.clone()))
-                    .collect::<Vec<_>>()
-            })
-            .flatten()
-            .collect()
+    pub fn world_to_tile_coordinates(coordinates: Vec2) -> TileLocation {
+        TileLocation::new(
+            (coordinates.x / (TILE_WIDTH as f32)).round() as i32,
+            (coordinates.y / (TILE_HEIGHT as f32)).round() as i32,
+        )
     }
 
     fn is_in_bounds(&self, i: usize, j: usize) -> bool {
@@ -414,22 +904,23 @@ impl TileGrid {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TileAppearance {
     Ascii(AsciiTileAppearance),
-    Sprite(Handle<TextureAtlas>),
+    Sprite(SpriteTileAppearance),
 }
 
 impl TileAppearance {
+    // Sprites are drawn in their own colors, so they're tinted from white.
+    pub fn get_color(&self) -> Color {
+        match self {
+            TileAppearance::Ascii(appearance) => appearance.color_code.to_color(),
+            TileAppearance::Sprite(_appearance) => Color::WHITE,
+        }
+    }
+
+    // Only the top of the stack is drawn, since it covers everything beneath it.
     pub fn from_tile(tile: &Tile) -> Self {
-        match tile.get_top_of_stack() {
-            Some(object_tile) => {
-                panic!("Not implemented yet.");
-            }
-            None => match tile.get_surface() {
-                SurfaceTile::Ground => TileAppearance::Ascii(MIDDLE_DOT.into()),
-                SurfaceTile::Wall => TileAppearance::Ascii('#'.into()),
-                SurfaceTile::Fireplace => {
-                    TileAppearance::Ascii(AsciiTileAppearance::new('X', ColorCode::Red))
-                }
-            },
+        match tile.get_top_visible_object() {
+            Some(object_tile) => TileAppearance::Ascii(object_tile.get_appearance()),
+            None => TileAppearance::Ascii(tile.get_surface().get_appearance()),
         }
     }
 
@@ -440,8 +931,10 @@ impl TileAppearance {
         bound_state: GameState,
         location: Vec2,
     ) -> Entity {
+        // Entities are re-rendered in place when the render mode changes, so drop the other kind.
         match self {
             TileAppearance::Ascii(appearance) => entity_commands
+                .remove::<TextureAtlasSprite>()
                 .insert(Text2dBundle {
                     text: Text::from_section(
                         appearance.character,
@@ -456,7 +949,20 @@ impl TileAppearance {
                 })
                 .insert(BoundStateComponent(bound_state))
                 .id(),
-            TileAppearance::Sprite(_) => panic!("Not implemented yet."),
+            TileAppearance::Sprite(appearance) => entity_commands
+                .remove::<Text>()
+                .insert(SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        index: appearance.get_index(),
+                        custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+                        ..Default::default()
+                    },
+                    texture_atlas: appearance.get_atlas(),
+                    transform: Transform::from_translation(location.extend(0.)),
+                    ..Default::default()
+                })
+                .insert(BoundStateComponent(bound_state))
+                .id(),
         }
     }
 }
@@ -484,6 +990,10 @@ impl AsciiTileAppearance {
     pub fn character(character: char) -> Self {
         Self::new(character, ColorCode::AntiqueWhite)
     }
+
+    pub fn get_character(&self) -> char {
+        self.character
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -491,6 +1001,9 @@ pub enum ColorCode {
     AntiqueWhite,
     Red,
     Gray,
+    Blue,
+    Yellow,
+    Orange,
 }
 
 impl ColorCode {
@@ -499,6 +1012,9 @@ impl ColorCode {
             Self::AntiqueWhite => Color::ANTIQUE_WHITE,
             Self::Red => Color::RED,
             Self::Gray => Color::GRAY,
+            Self::Blue => Color::BLUE,
+            Self::Yellow => Color::YELLOW,
+            Self::Orange => Color::ORANGE,
         }
     }
 }
@@ -545,6 +1061,195 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_map_stairs_lead_to_destination() {
+        let lower = MapLocation::new(1, TileLocation::new(2, 2));
+        let upper = MapLocation::new(0, TileLocation::new(3, 3));
+
+        let mut map = Map::new(vec![
+            MapLayer::fill(5, 5, Tile::empty_ground()),
+            MapLayer::fill(5, 5, Tile::empty_ground()),
+        ]);
+        map.update(
+            Tile::empty(SurfaceTile::linked(STAIRS_DOWN_TERRAIN, lower)),
+            upper,
+        )
+        .unwrap();
+        map.update(
+            Tile::empty(SurfaceTile::linked(STAIRS_UP_TERRAIN, upper)),
+            lower,
+        )
+        .unwrap();
+
+        assert!(map.is_traversable(upper).unwrap());
+        assert_eq!(
+            map.get_destination(upper, LayerChange::Descend).unwrap(),
+            Some(lower)
+        );
+        assert_eq!(
+            map.get_destination(upper, LayerChange::Ascend).unwrap(),
+            None
+        );
+        assert_eq!(
+            map.get_destination(lower, LayerChange::Ascend).unwrap(),
+            Some(upper)
+        );
+        assert_eq!(
+            map.get_destination(lower, LayerChange::Descend).unwrap(),
+            None
+        );
+        assert_eq!(
+            map.get_destination(
+                MapLocation::new(0, TileLocation::zero()),
+                LayerChange::Descend
+            )
+            .unwrap(),
+            None
+        );
+
+        let portal = MapLocation::new(0, TileLocation::new(1, 1));
+        map.update(
+            Tile::empty(SurfaceTile::linked(PORTAL_TERRAIN, lower)),
+            portal,
+        )
+        .unwrap();
+        assert_eq!(
+            map.get_destination(portal, LayerChange::Ascend).unwrap(),
+            Some(lower)
+        );
+        assert_eq!(
+            map.get_destination(portal, LayerChange::Descend).unwrap(),
+            Some(lower)
+        );
+    }
+
+    #[test]
+    fn test_map_layer_edits_keep_links_pointing_at_the_same_tiles() {
+        let lower = MapLocation::new(1, TileLocation::new(2, 2));
+        let upper = MapLocation::new(0, TileLocation::new(3, 3));
+        let mut map = Map::new(vec![
+            MapLayer::fill(5, 5, Tile::empty_ground()),
+            MapLayer::fill(5, 5, Tile::empty_ground()),
+        ]);
+        map.update(
+            Tile::empty(SurfaceTile::linked(STAIRS_DOWN_TERRAIN, lower)),
+            upper,
+        )
+        .unwrap();
+
+        map.shift_layer(1, TileLocation::new(-1, 1), &Tile::wall())
+            .unwrap();
+        let lower = MapLocation::new(1, TileLocation::new(1, 3));
+        assert_eq!(
+            map.get_destination(upper, LayerChange::Descend).unwrap(),
+            Some(lower)
+        );
+
+        map.insert_layer(1, MapLayer::fill(2, 2, Tile::wall()))
+            .unwrap();
+        let lower = MapLocation::new(2, TileLocation::new(1, 3));
+        assert_eq!(
+            map.get_destination(upper, LayerChange::Descend).unwrap(),
+            Some(lower)
+        );
+
+        map.crop_layer(2, TileLocation::new(0, 0), 3, 3).unwrap();
+        assert_eq!(
+            map.get_destination(upper, LayerChange::Descend).unwrap(),
+            None
+        );
+        assert!(map.crop_layer(2, TileLocation::new(1, 1), 3, 3).is_err());
+        assert!(map.move_layer(0, 3).is_err());
+        assert!(map.remove_layer(3).is_err());
+    }
+
+    #[test]
+    fn test_map_toggles_doors() {
+        let door = MapLocation::new(0, TileLocation::new(1, 1));
+        let locked = MapLocation::new(0, TileLocation::new(2, 1));
+        let mut map = Map::new(vec![MapLayer::fill(4, 3, Tile::empty_ground())]);
+        map.update(Tile::empty(SurfaceTile::new(DOOR_CLOSED_TERRAIN)), door)
+            .unwrap();
+        map.update(Tile::empty(SurfaceTile::new(DOOR_LOCKED_TERRAIN)), locked)
+            .unwrap();
+        let nobody = OccupiedLocations::empty();
+
+        assert!(!map.is_traversable(door).unwrap());
+        assert_eq!(
+            map.toggle_door(door, &nobody, false).unwrap(),
+            "You open the door."
+        );
+        assert!(map.is_traversable(door).unwrap());
+        assert!(!map.get(door).unwrap().is_opaque());
+
+        let occupied = vec![door].into_iter().collect::<OccupiedLocations>();
+        assert!(matches!(
+            map.toggle_door(door, &occupied, false),
+            Err(MapError::DoorBlocked)
+        ));
+        assert!(map.toggle_door(door, &nobody, false).is_ok());
+        assert!(map.get(door).unwrap().is_opaque());
+
+        assert!(matches!(
+            map.toggle_door(locked, &nobody, false),
+            Err(MapError::DoorLocked)
+        ));
+        assert!(map.toggle_door(locked, &nobody, true).is_ok());
+        assert!(map.is_traversable(locked).unwrap());
+        assert!(matches!(
+            map.toggle_door(MapLocation::new(0, TileLocation::zero()), &nobody, true),
+            Err(MapError::NoDoor)
+        ));
+    }
+
+    #[test]
+    fn test_map_takes_objects() {
+        let location = MapLocation::new(0, TileLocation::new(1, 1));
+        let mut map = Map::new(vec![MapLayer::fill(3, 3, Tile::empty_ground())]);
+        map.update(
+            Tile::new(
+                SurfaceTile::ground(),
+                vec![ObjectTile::Key, ObjectTile::Item, ObjectTile::Key],
+            ),
+            location,
+        )
+        .unwrap();
+
+        assert_eq!(map.take_objects(location, ObjectTile::Key).unwrap(), 2);
+        assert_eq!(map.get(location).unwrap(), &Tile::object(ObjectTile::Item));
+        assert_eq!(map.take_objects(location, ObjectTile::Key).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_map_finds_nearest_open_location() {
+        let mut map_layer = MapLayer::fill(5, 5, Tile::wall());
+        map_layer.update(Tile::empty_ground(), 3, 3).unwrap();
+        map_layer.update(Tile::empty_ground(), 4, 4).unwrap();
+        let map: Map = map_layer.into();
+
+        let mut occupied = OccupiedLocations::empty();
+        assert_eq!(
+            map.find_nearest_open_location(TileLocation::new(1, 1).into(), &occupied),
+            Some(TileLocation::new(3, 3).into())
+        );
+
+        occupied.insert(TileLocation::new(3, 3).into());
+        assert_eq!(
+            map.find_nearest_open_location(TileLocation::new(1, 1).into(), &occupied),
+            Some(TileLocation::new(4, 4).into())
+        );
+
+        occupied.insert(TileLocation::new(4, 4).into());
+        assert_eq!(
+            map.find_nearest_open_location(TileLocation::new(1, 1).into(), &occupied),
+            None
+        );
+        assert_eq!(
+            map.find_nearest_open_location(MapLocation::new(1, TileLocation::zero()), &occupied),
+            None
+        );
+    }
+
     #[test]
     fn test_map_layer_width_and_height() {
         let grid = MapLayer::fill(4, 9, Tile::wall());
@@ -578,4 +1283,88 @@ mod tests {
         assert_eq!(v[0], (TileLocation::zero(), &Tile::wall()));
         assert_eq!(v.last(), Some(&(TileLocation::new(3, 8), &Tile::wall())));
     }
+
+    #[test]
+    fn test_tile_appearance_shows_top_of_stack() {
+        let tile = Tile::new(
+            SurfaceTile::ground(),
+            vec![ObjectTile::Item, ObjectTile::Trap(Trap::new(SPIKE_TRAP))],
+        );
+        assert_eq!(
+            TileAppearance::from_tile(&tile),
+            TileAppearance::Ascii(AsciiTileAppearance::new('^', ColorCode::Red))
+        );
+        let hidden = Tile::new(
+            SurfaceTile::ground(),
+            vec![ObjectTile::Item, ObjectTile::Trap(Trap::new("dart_trap"))],
+        );
+        assert_eq!(
+            TileAppearance::from_tile(&hidden),
+            TileAppearance::Ascii(AsciiTileAppearance::new('*', ColorCode::Yellow))
+        );
+        assert_eq!(
+            TileAppearance::from_tile(&Tile::object(ObjectTile::Item)),
+            TileAppearance::Ascii(AsciiTileAppearance::new('*', ColorCode::Yellow))
+        );
+        assert_eq!(
+            TileAppearance::from_tile(&Tile::wall()),
+            TileAppearance::Ascii('#'.into())
+        );
+    }
+
+    #[test]
+    fn test_tile_description_lists_stack_from_the_top() {
+        assert_eq!(Tile::empty_ground().get_description(), "Ground.");
+        assert_eq!(
+            Tile::object(ObjectTile::Trap(Trap::new(SPIKE_TRAP))).get_description(),
+            "Spike trap on ground."
+        );
+        assert_eq!(
+            Tile::new(
+                SurfaceTile::new(FIREPLACE_TERRAIN),
+                vec![
+                    ObjectTile::Item,
+                    ObjectTile::Trap(Trap::new(SPIKE_TRAP)),
+                    ObjectTile::Item,
+                    ObjectTile::Trap(Trap::new("dart_trap")),
+                ]
+            )
+            .get_description(),
+            "Item, spike trap and item on fireplace."
+        );
+    }
+
+    #[test]
+    fn test_tile_springs_traps() {
+        let mut tile = Tile::new(
+            SurfaceTile::ground(),
+            vec![
+                ObjectTile::Trap(Trap::new(SPIKE_TRAP)),
+                ObjectTile::Trap(Trap::new("dart_trap")),
+                ObjectTile::Trap(Trap::new("falling_rock_trap")),
+            ],
+        );
+        assert_eq!(tile.get_visible_stack().len(), 1);
+
+        let sprung = tile.spring_traps();
+        assert_eq!(
+            sprung
+                .iter()
+                .map(|spec| spec.get_name())
+                .collect::<Vec<_>>(),
+            vec!["spike trap", "dart trap", "falling rock trap"]
+        );
+        // The dart trap reveals itself and the falling rock trap is used up.
+        let mut revealed_dart_trap = Trap::new("dart_trap");
+        revealed_dart_trap.reveal();
+        assert_eq!(
+            tile.get_stack(),
+            &vec![
+                ObjectTile::Trap(Trap::new(SPIKE_TRAP)),
+                ObjectTile::Trap(revealed_dart_trap),
+            ]
+        );
+        assert_eq!(tile.get_visible_stack().len(), 2);
+        assert!(Tile::empty_ground().spring_traps().is_empty());
+    }
 }
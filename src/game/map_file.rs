@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::map::{Map, MapLayer, MapLocation, SurfaceTile, Tile};
+use super::map_metadata::MapMetadata;
+use super::terrain::TerrainRegistry;
+use crate::constants::*;
+
+// Structs
+
+// On-disk form of a Map.  Each distinct tile is stored once in the palette and every column of a
+// layer is a run-length encoded string of palette indices, e.g. "12x0 3x1 35x0".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactMap {
+    version: u32,
+    palette: Vec<Tile>,
+    layers: Vec<CompactMapLayer>,
+    // Maps saved before metadata existed have none.
+    #[serde(default, skip_serializing_if = "MapMetadata::is_empty")]
+    metadata: MapMetadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactMapLayer {
+    width: usize,
+    height: usize,
+    columns: Vec<String>,
+}
+
+// Maps saved before the compact format are plain nested arrays of tiles.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum MapFile {
+    Compact(CompactMap),
+    Verbose(Vec<MapLayer>),
+}
+
+// On-disk form of a SurfaceTile.  Plain terrain is just its id, e.g. "ground", and linked terrain
+// also records its destination.  Maps saved before the terrain registry used the old enum.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SurfaceTileFile {
+    Legacy(LegacySurfaceTile),
+    Terrain(String),
+    Linked {
+        terrain: String,
+        destination: MapLocation,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LegacySurfaceTile {
+    Ground,
+    Wall,
+    Fireplace,
+    StairsUp(MapLocation),
+    StairsDown(MapLocation),
+    Portal(MapLocation),
+}
+
+impl From<SurfaceTile> for SurfaceTileFile {
+    fn from(surface: SurfaceTile) -> Self {
+        match surface.get_linked_destination() {
+            Some(destination) => Self::Linked {
+                terrain: surface.get_terrain_id().to_string(),
+                destination,
+            },
+            None => Self::Terrain(surface.get_terrain_id().to_string()),
+        }
+    }
+}
+
+impl From<SurfaceTileFile> for SurfaceTile {
+    fn from(surface_file: SurfaceTileFile) -> Self {
+        match surface_file {
+            SurfaceTileFile::Terrain(terrain) => Self::new(&terrain),
+            SurfaceTileFile::Linked {
+                terrain,
+                destination,
+            } => Self::linked(&terrain, destination),
+            SurfaceTileFile::Legacy(legacy) => match legacy {
+                LegacySurfaceTile::Ground => Self::new(GROUND_TERRAIN),
+                LegacySurfaceTile::Wall => Self::new(WALL_TERRAIN),
+                LegacySurfaceTile::Fireplace => Self::new(FIREPLACE_TERRAIN),
+                LegacySurfaceTile::StairsUp(destination) => {
+                    Self::linked(STAIRS_UP_TERRAIN, destination)
+                }
+                LegacySurfaceTile::StairsDown(destination) => {
+                    Self::linked(STAIRS_DOWN_TERRAIN, destination)
+                }
+                LegacySurfaceTile::Portal(destination) => Self::linked(PORTAL_TERRAIN, destination),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapFileError {
+    UnsupportedVersion(u32),
+    InvalidRun(String),
+    InvalidPaletteIndex(usize),
+    WrongWidth(usize),
+    WrongHeight(usize),
+    UnknownTerrain(String),
+    RaggedLayer(usize),
+}
+
+impl fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported map file version: {}", version)
+            }
+            Self::InvalidRun(run) => write!(f, "Invalid run in map file: {}", run),
+            Self::InvalidPaletteIndex(index) => {
+                write!(f, "Palette index out of range in map file: {}", index)
+            }
+            Self::WrongWidth(width) => {
+                write!(f, "Layer has the wrong number of columns: {}", width)
+            }
+            Self::WrongHeight(height) => {
+                write!(f, "Column has the wrong number of tiles: {}", height)
+            }
+            Self::UnknownTerrain(terrain) => write!(f, "Unknown terrain in map file: {}", terrain),
+            Self::RaggedLayer(map_layer) => {
+                write!(f, "Layer {} has columns of different heights", map_layer)
+            }
+        }
+    }
+}
+
+impl From<Map> for CompactMap {
+    fn from(map: Map) -> Self {
+        let mut palette = Vec::new();
+        let mut palette_indices: HashMap<Tile, usize> = HashMap::new();
+        let layers = map
+            .get_layers()
+            .iter()
+            .map(|map_layer| CompactMapLayer {
+                width: map_layer.width(),
+                height: map_layer.height(),
+                columns: map_layer
+                    .get_tiles_cloned()
+                    .into_iter()
+                    .map(|column| {
+                        let indices = column
+                            .into_iter()
+                            .map(|tile| {
+                                *palette_indices.entry(tile.clone()).or_insert_with(|| {
+                                    palette.push(tile);
+                                    palette.len() - 1
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        encode_runs(&indices)
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            version: MAP_FILE_VERSION,
+            palette,
+            layers,
+            metadata: map.get_metadata().clone(),
+        }
+    }
+}
+
+impl TryFrom<MapFile> for Map {
+    type Error = MapFileError;
+
+    fn try_from(map_file: MapFile) -> Result<Self, Self::Error> {
+        match map_file {
+            MapFile::Verbose(layers) => {
+                // Verbose layers are read straight into MapLayer, so they skip its checks.
+                if let Some(map_layer) = layers.iter().position(|layer| !layer.is_rectangular()) {
+                    return Err(MapFileError::RaggedLayer(map_layer));
+                }
+                for layer in layers.iter() {
+                    check_terrain(layer.get_tiles_cloned().iter().flatten())?;
+                }
+                Ok(Map::new(layers))
+            }
+            MapFile::Compact(compact_map) => {
+                if compact_map.version != MAP_FILE_VERSION {
+                    return Err(MapFileError::UnsupportedVersion(compact_map.version));
+                }
+                check_terrain(compact_map.palette.iter())?;
+                let layers = compact_map
+                    .layers
+                    .iter()
+                    .map(|layer| decode_layer(layer, &compact_map.palette))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Map::with_metadata(layers, compact_map.metadata))
+            }
+        }
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+fn check_terrain<'a>(mut tiles: impl Iterator<Item = &'a Tile>) -> Result<(), MapFileError> {
+    let registry = TerrainRegistry::global();
+    match tiles.find(|tile| !registry.contains(tile.get_surface().get_terrain_id())) {
+        Some(tile) => Err(MapFileError::UnknownTerrain(
+            tile.get_surface().get_terrain_id().to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn encode_runs(indices: &Vec<usize>) -> String {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for index in indices {
+        match runs.last_mut() {
+            Some((count, last_index)) if last_index == index => *count += 1,
+            _ => runs.push((1, *index)),
+        }
+    }
+    runs.into_iter()
+        .map(|(count, index)| format!("{}x{}", count, index))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Stops as soon as the runs add up to more than the height, so a huge run count can't be used to
+// make us allocate without limit.
+fn decode_runs(column: &str, height: usize) -> Result<Vec<usize>, MapFileError> {
+    let mut indices = Vec::new();
+    for run in column.split_whitespace() {
+        let invalid_run = || MapFileError::InvalidRun(run.to_string());
+        let (count, index) = run.split_once('x').ok_or_else(invalid_run)?;
+        let count = count.parse::<usize>().map_err(|_| invalid_run())?;
+        let index = index.parse::<usize>().map_err(|_| invalid_run())?;
+        let total = indices.len().saturating_add(count);
+        if total > height {
+            return Err(MapFileError::WrongHeight(total));
+        }
+        indices.extend(std::iter::repeat(index).take(count));
+    }
+    Ok(indices)
+}
+
+fn decode_layer(layer: &CompactMapLayer, palette: &Vec<Tile>) -> Result<MapLayer, MapFileError> {
+    if layer.columns.len() != layer.width {
+        return Err(MapFileError::WrongWidth(layer.columns.len()));
+    }
+    let tiles = layer
+        .columns
+        .iter()
+        .map(|column| {
+            let indices = decode_runs(column, layer.height)?;
+            if indices.len() != layer.height {
+                return Err(MapFileError::WrongHeight(indices.len()));
+            }
+            indices
+                .into_iter()
+                .map(|index| {
+                    palette
+                        .get(index)
+                        .cloned()
+                        .ok_or(MapFileError::InvalidPaletteIndex(index))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(MapLayer::new(tiles))
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::TileLocation;
+    use crate::game::map_metadata::{Placement, PlacementKind};
+
+    fn get_example_map() -> Map {
+        let mut upper = MapLayer::fill(6, 4, Tile::empty_ground());
+        upper.update_edges(&Tile::wall());
+        upper
+            .update(
+                Tile::empty(SurfaceTile::linked(
+                    STAIRS_DOWN_TERRAIN,
+                    MapLocation::new(1, TileLocation::new(1, 1)),
+                )),
+                2,
+                2,
+            )
+            .unwrap();
+        Map::new(vec![upper, MapLayer::fill(3, 3, Tile::wall())])
+    }
+
+    #[test]
+    fn test_map_file_round_trips_compactly() {
+        let map = get_example_map();
+        let compact = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<Map>(&compact).unwrap(), map);
+
+        let verbose = serde_json::to_string(map.get_layers()).unwrap();
+        assert!(compact.len() < verbose.len());
+
+        let compact_map: CompactMap = map.into();
+        assert_eq!(compact_map.palette.len(), 3);
+        assert_eq!(compact_map.layers[0].columns[0], "4x0");
+        assert_eq!(compact_map.layers[0].columns[2], "1x0 1x1 1x2 1x0");
+        assert_eq!(compact_map.layers[1].columns, vec!["3x0"; 3]);
+    }
+
+    #[test]
+    fn test_map_file_round_trips_metadata() {
+        let mut metadata = MapMetadata::new("Example");
+        metadata.add_player_spawn(MapLocation::new(0, TileLocation::new(1, 1)));
+        metadata.add_placement(Placement::new(
+            PlacementKind::Enemy,
+            "Example enemy",
+            MapLocation::new(0, TileLocation::new(3, 2)),
+        ));
+        let mut map = get_example_map();
+        map.set_metadata(metadata);
+
+        let compact = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<Map>(&compact).unwrap(), map);
+
+        let without_metadata = serde_json::to_string(&get_example_map()).unwrap();
+        assert!(!without_metadata.contains("metadata"));
+        assert!(serde_json::from_str::<Map>(&without_metadata)
+            .unwrap()
+            .get_metadata()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_map_file_loads_verbose_maps() {
+        let map = get_example_map();
+        let verbose = serde_json::to_string(map.get_layers()).unwrap();
+        assert_eq!(serde_json::from_str::<Map>(&verbose).unwrap(), map);
+
+        let old_format = r#"[[[{"surface":"Ground","stack":[]},{"surface":"Wall","stack":[]}]]]"#;
+        let map = serde_json::from_str::<Map>(old_format).unwrap();
+        assert_eq!(
+            map.get_layer(0).unwrap(),
+            &MapLayer::new(vec![vec![Tile::empty_ground(), Tile::wall()]])
+        );
+
+        let old_stairs =
+            r#"{"surface":{"StairsUp":{"map_layer":0,"tile_location":{"i":2,"j":3}}},"stack":[]}"#;
+        assert_eq!(
+            serde_json::from_str::<Tile>(old_stairs).unwrap(),
+            Tile::empty(SurfaceTile::linked(
+                STAIRS_UP_TERRAIN,
+                MapLocation::new(0, TileLocation::new(2, 3))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_map_file_rejects_unknown_terrain() {
+        let unknown = r#"{"version":1,"palette":[{"surface":"lava","stack":[]}],"layers":[{"width":1,"height":1,"columns":["1x0"]}]}"#;
+        let map_file = serde_json::from_str::<MapFile>(unknown).unwrap();
+        assert_eq!(
+            Map::try_from(map_file),
+            Err(MapFileError::UnknownTerrain("lava".to_string()))
+        );
+
+        let verbose_unknown = r#"[[[{"surface":"lava","stack":[]}]]]"#;
+        let map_file = serde_json::from_str::<MapFile>(verbose_unknown).unwrap();
+        assert_eq!(
+            Map::try_from(map_file),
+            Err(MapFileError::UnknownTerrain("lava".to_string()))
+        );
+
+        let ragged = r#"[[[{"surface":"ground","stack":[]}],[]]]"#;
+        let map_file = serde_json::from_str::<MapFile>(ragged).unwrap();
+        assert_eq!(Map::try_from(map_file), Err(MapFileError::RaggedLayer(0)));
+    }
+
+    #[test]
+    fn test_map_file_rejects_malformed_layers() {
+        let palette = vec![Tile::empty_ground()];
+        let layer = |columns: Vec<&str>, height| CompactMapLayer {
+            width: 2,
+            height,
+            columns: columns
+                .into_iter()
+                .map(|column| column.to_string())
+                .collect(),
+        };
+
+        assert!(decode_layer(&layer(vec!["2x0", "1x0 1x0"], 2), &palette).is_ok());
+        assert_eq!(
+            decode_layer(&layer(vec!["2x0", "2x1"], 2), &palette),
+            Err(MapFileError::InvalidPaletteIndex(1))
+        );
+        assert_eq!(
+            decode_layer(&layer(vec!["2x0", "3x0"], 2), &palette),
+            Err(MapFileError::WrongHeight(3))
+        );
+        assert_eq!(
+            decode_layer(&layer(vec!["2x0", "1x0 99999999999x0"], 2), &palette),
+            Err(MapFileError::WrongHeight(100000000000))
+        );
+        assert_eq!(
+            decode_layer(&layer(vec!["2x0"], 2), &palette),
+            Err(MapFileError::WrongWidth(1))
+        );
+        assert_eq!(
+            decode_layer(&layer(vec!["2x0", "two"], 2), &palette),
+            Err(MapFileError::InvalidRun("two".to_string()))
+        );
+    }
+}
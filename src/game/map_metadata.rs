@@ -0,0 +1,229 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use super::health::Treatment;
+use super::map::{Map, MapLocation, ObjectTile};
+use super::trap::{Trap, TrapRegistry};
+
+// Structs
+
+// Everything besides tiles that a level needs: where the player starts and who and what is placed
+// where, with NPCs, enemies and items referred to by spec name.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MapMetadata {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    player_spawns: Vec<MapLocation>,
+    #[serde(default)]
+    placements: Vec<Placement>,
+}
+
+impl MapMetadata {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // Spawns the player and places each spec on its own random open tile of the top layer.
+    pub fn scattered(
+        map: &Map,
+        seed: u64,
+        name: &str,
+        specs: Vec<(PlacementKind, String)>,
+    ) -> Self {
+        let mut metadata = Self::new(name);
+        let mut open_locations = match map.get_layer(0) {
+            Ok(map_layer) => map_layer
+                .as_location_and_tile_vector()
+                .into_iter()
+                .filter(|(_location, tile)| tile.is_traversable())
+                .map(|(location, _tile)| MapLocation::new(0, location))
+                .collect::<Vec<_>>(),
+            Err(_) => Vec::new(),
+        };
+        open_locations.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        let mut open_locations = open_locations.into_iter();
+        if let Some(spawn) = open_locations.next() {
+            metadata.add_player_spawn(spawn);
+        }
+        for ((kind, spec), location) in specs.into_iter().zip(open_locations) {
+            metadata.add_placement(Placement::new(kind, &spec, location));
+        }
+        metadata
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_player_spawns(&self) -> &Vec<MapLocation> {
+        &self.player_spawns
+    }
+
+    pub fn get_placements(&self) -> &Vec<Placement> {
+        &self.placements
+    }
+
+    pub fn get_placements_of(&self, kind: PlacementKind) -> Vec<&Placement> {
+        self.placements
+            .iter()
+            .filter(|placement| placement.get_kind() == kind)
+            .collect()
+    }
+
+    pub fn add_player_spawn(&mut self, location: MapLocation) {
+        self.player_spawns.push(location);
+    }
+
+    pub fn add_placement(&mut self, placement: Placement) {
+        self.placements.push(placement);
+    }
+
+    // Moves every spawn and placement, dropping those that map to None.
+    pub fn relocate(&mut self, relocate: &impl Fn(MapLocation) -> Option<MapLocation>) {
+        self.player_spawns = self
+            .player_spawns
+            .iter()
+            .filter_map(|location| relocate(*location))
+            .collect();
+        self.placements = self
+            .placements
+            .drain(..)
+            .filter_map(|placement| {
+                relocate(placement.location).map(|location| Placement {
+                    location,
+                    ..placement
+                })
+            })
+            .collect();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Placement {
+    kind: PlacementKind,
+    spec: String,
+    location: MapLocation,
+}
+
+impl Placement {
+    pub fn new(kind: PlacementKind, spec: &str, location: MapLocation) -> Self {
+        Self {
+            kind,
+            spec: spec.to_string(),
+            location,
+        }
+    }
+
+    pub fn get_kind(&self) -> PlacementKind {
+        self.kind
+    }
+
+    pub fn get_spec(&self) -> &str {
+        &self.spec
+    }
+
+    pub fn get_location(&self) -> MapLocation {
+        self.location
+    }
+
+    // Items are "item", "key", a treatment such as "bandage" or the id of a trap spec.
+    pub fn get_object(&self) -> Option<ObjectTile> {
+        match (self.kind, self.spec.as_str()) {
+            (PlacementKind::Item, "item") => Some(ObjectTile::Item),
+            (PlacementKind::Item, "key") => Some(ObjectTile::Key),
+            (PlacementKind::Item, spec) => Treatment::from_kind(spec)
+                .map(ObjectTile::Treatment)
+                .or_else(|| {
+                    TrapRegistry::global()
+                        .get(spec)
+                        .map(|_trap_spec| ObjectTile::Trap(Trap::new(spec)))
+                }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PlacementKind {
+    NPC,
+    Enemy,
+    Item,
+}
+
+// End Structs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::*;
+    use crate::game::map::{MapLayer, Tile, TileLocation};
+
+    #[test]
+    fn test_map_metadata_scatters_onto_open_tiles() {
+        let mut map_layer = MapLayer::fill(6, 6, Tile::empty_ground());
+        map_layer.update_edges(&Tile::wall());
+        let map = Map::new(vec![map_layer]);
+        let specs = vec![
+            (PlacementKind::NPC, "Jep".to_string()),
+            (PlacementKind::Enemy, "Example enemy".to_string()),
+            (PlacementKind::Enemy, "Example enemy".to_string()),
+        ];
+
+        let metadata = MapMetadata::scattered(&map, 3, "Test", specs.clone());
+        assert_eq!(metadata.get_name(), "Test");
+        assert_eq!(metadata.get_player_spawns().len(), 1);
+        assert_eq!(metadata.get_placements_of(PlacementKind::Enemy).len(), 2);
+
+        let mut locations = metadata.get_player_spawns().clone();
+        locations.extend(
+            metadata
+                .get_placements()
+                .iter()
+                .map(|placement| placement.get_location()),
+        );
+        assert!(locations
+            .iter()
+            .all(|location| map.is_traversable(*location).unwrap()));
+        locations.sort_by_key(|location| location.get_tile_location());
+        locations.dedup();
+        assert_eq!(locations.len(), 4);
+
+        assert_eq!(MapMetadata::scattered(&map, 3, "Test", specs), metadata);
+    }
+
+    #[test]
+    fn test_placement_resolves_items() {
+        let location = MapLocation::new(0, TileLocation::zero());
+        assert_eq!(
+            Placement::new(PlacementKind::Item, "key", location).get_object(),
+            Some(ObjectTile::Key)
+        );
+        assert_eq!(
+            Placement::new(PlacementKind::Item, SPIKE_TRAP, location).get_object(),
+            Some(ObjectTile::Trap(Trap::new(SPIKE_TRAP)))
+        );
+        assert_eq!(
+            Placement::new(PlacementKind::Item, "splint", location).get_object(),
+            Some(ObjectTile::Treatment(Treatment::Splint))
+        );
+        assert_eq!(
+            Placement::new(PlacementKind::Item, "sword", location).get_object(),
+            None
+        );
+        assert_eq!(
+            Placement::new(PlacementKind::NPC, "key", location).get_object(),
+            None
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::events::Direction;
+use super::map::{Map, MapLayer, MapLocation, Tile};
+use super::map_metadata::PlacementKind;
+
+// Structs
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapDiagnostic {
+    RaggedLayer(usize),
+    NoPlayerSpawn,
+    SpawnOutOfBounds(MapLocation),
+    SpawnBlocked(MapLocation),
+    PlacementOutOfBounds(String, MapLocation),
+    PlacementBlocked(String, MapLocation),
+    // A link whose destination is off the map or inside something solid.
+    BrokenLink(MapLocation, MapLocation),
+    // The first tile of a region nobody can walk to from a spawn point, and the region's size.
+    UnreachableRegion(MapLocation, usize),
+}
+
+impl MapDiagnostic {
+    pub fn get_severity(&self) -> Severity {
+        match self {
+            Self::NoPlayerSpawn | Self::UnreachableRegion(..) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn get_location(&self) -> Option<MapLocation> {
+        match self {
+            Self::RaggedLayer(_) | Self::NoPlayerSpawn => None,
+            Self::SpawnOutOfBounds(location)
+            | Self::SpawnBlocked(location)
+            | Self::PlacementOutOfBounds(_, location)
+            | Self::PlacementBlocked(_, location)
+            | Self::BrokenLink(location, _)
+            | Self::UnreachableRegion(location, _) => Some(*location),
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        let at = |location: &MapLocation| {
+            let tile_location = location.get_tile_location();
+            format!(
+                "layer {} ({}, {})",
+                location.get_map_layer(),
+                tile_location.x(),
+                tile_location.y()
+            )
+        };
+        match self {
+            Self::RaggedLayer(layer) => format!("Layer {} isn't rectangular.", layer),
+            Self::NoPlayerSpawn => "The map has no player spawn point.".to_string(),
+            Self::SpawnOutOfBounds(location) => {
+                format!("The player spawn at {} is off the map.", at(location))
+            }
+            Self::SpawnBlocked(location) => {
+                format!("The player spawn at {} isn't traversable.", at(location))
+            }
+            Self::PlacementOutOfBounds(spec, location) => {
+                format!("{} at {} is off the map.", spec, at(location))
+            }
+            Self::PlacementBlocked(spec, location) => {
+                format!("{} at {} isn't on a traversable tile.", spec, at(location))
+            }
+            Self::BrokenLink(location, destination) => format!(
+                "The link at {} leads to {}, which can't be stood on.",
+                at(location),
+                at(destination)
+            ),
+            Self::UnreachableRegion(location, size) => format!(
+                "{} tiles around {} can't be reached from a spawn point.",
+                size,
+                at(location)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MapValidationReport(Vec<MapDiagnostic>);
+
+impl MapValidationReport {
+    pub fn get_diagnostics(&self) -> &Vec<MapDiagnostic> {
+        &self.0
+    }
+
+    pub fn get_errors(&self) -> Vec<&MapDiagnostic> {
+        self.with_severity(Severity::Error)
+    }
+
+    pub fn get_warnings(&self) -> Vec<&MapDiagnostic> {
+        self.with_severity(Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.get_errors().is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // A one line summary followed by the worst problem, e.g. for a toast message.
+    pub fn to_string(&self) -> String {
+        let summary = format!(
+            "{} errors and {} warnings.",
+            self.get_errors().len(),
+            self.get_warnings().len()
+        );
+        let worst = self
+            .get_errors()
+            .into_iter()
+            .chain(self.get_warnings())
+            .next();
+        match worst {
+            Some(diagnostic) => format!("{} {}", summary, diagnostic.to_string()),
+            None => "The map is valid.".to_string(),
+        }
+    }
+
+    fn with_severity(&self, severity: Severity) -> Vec<&MapDiagnostic> {
+        self.0
+            .iter()
+            .filter(|diagnostic| diagnostic.get_severity() == severity)
+            .collect()
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+pub fn validate_map(map: &Map) -> MapValidationReport {
+    let mut diagnostics = Vec::new();
+    let ragged_layers = map
+        .get_layers()
+        .iter()
+        .enumerate()
+        .filter(|(_index, map_layer)| !map_layer.is_rectangular())
+        .map(|(index, _map_layer)| index)
+        .collect::<Vec<_>>();
+    // Everything else indexes into the layers, which isn't safe until they're all rectangular.
+    if !ragged_layers.is_empty() {
+        diagnostics.extend(ragged_layers.into_iter().map(MapDiagnostic::RaggedLayer));
+        return MapValidationReport(diagnostics);
+    }
+
+    let metadata = map.get_metadata();
+    if metadata.get_player_spawns().is_empty() {
+        diagnostics.push(MapDiagnostic::NoPlayerSpawn);
+    }
+    for spawn in metadata.get_player_spawns() {
+        match map.get(*spawn) {
+            Err(_) => diagnostics.push(MapDiagnostic::SpawnOutOfBounds(*spawn)),
+            Ok(tile) if !tile.is_traversable() => {
+                diagnostics.push(MapDiagnostic::SpawnBlocked(*spawn))
+            }
+            Ok(_) => {}
+        }
+    }
+
+    // Items can sit on anything, but characters have to be able to stand where they're placed.
+    for placement in metadata.get_placements() {
+        let (spec, location) = (placement.get_spec().to_string(), placement.get_location());
+        match map.get(location) {
+            Err(_) => diagnostics.push(MapDiagnostic::PlacementOutOfBounds(spec, location)),
+            Ok(tile) if !tile.is_traversable() && placement.get_kind() != PlacementKind::Item => {
+                diagnostics.push(MapDiagnostic::PlacementBlocked(spec, location))
+            }
+            Ok(_) => {}
+        }
+    }
+
+    for (index, map_layer) in map.get_layers().iter().enumerate() {
+        for (tile_location, tile) in map_layer.as_location_and_tile_vector() {
+            if let Some(destination) = tile.get_surface().get_linked_destination() {
+                if !map.get(destination).map_or(false, is_passable) {
+                    diagnostics.push(MapDiagnostic::BrokenLink(
+                        MapLocation::new(index, tile_location),
+                        destination,
+                    ));
+                }
+            }
+        }
+    }
+
+    if !metadata.get_player_spawns().is_empty() {
+        diagnostics.extend(find_unreachable_regions(map, metadata.get_player_spawns()));
+    }
+    MapValidationReport(diagnostics)
+}
+
+// Doors can be opened, so they don't cut a map in two.
+fn is_passable(tile: &Tile) -> bool {
+    tile.is_traversable() || tile.get_surface().get_terrain().get_door().is_some()
+}
+
+fn flood_fill(
+    map: &Map,
+    starts: Vec<MapLocation>,
+    visited: &mut HashSet<MapLocation>,
+) -> Vec<MapLocation> {
+    let mut region = Vec::new();
+    let mut frontier = starts
+        .into_iter()
+        .filter(|start| map.get(*start).map_or(false, is_passable) && visited.insert(*start))
+        .collect::<VecDeque<_>>();
+    while let Some(location) = frontier.pop_front() {
+        region.push(location);
+        let tile = map
+            .get(location)
+            .expect("Only tiles on the map are visited.");
+        let mut neighbours = Direction::all()
+            .into_iter()
+            .map(|direction| {
+                let mut neighbour = location;
+                neighbour.translate(direction.as_tile_location());
+                neighbour
+            })
+            .collect::<Vec<_>>();
+        neighbours.extend(tile.get_surface().get_linked_destination());
+        for neighbour in neighbours {
+            if map.get(neighbour).map_or(false, is_passable) && visited.insert(neighbour) {
+                frontier.push_back(neighbour);
+            }
+        }
+    }
+    region
+}
+
+fn find_unreachable_regions(map: &Map, spawns: &Vec<MapLocation>) -> Vec<MapDiagnostic> {
+    let mut visited = HashSet::new();
+    flood_fill(map, spawns.clone(), &mut visited);
+
+    let mut diagnostics = Vec::new();
+    for (index, map_layer) in map.get_layers().iter().enumerate() {
+        for location in get_passable_locations(map_layer, index) {
+            if !visited.contains(&location) {
+                let region = flood_fill(map, vec![location], &mut visited);
+                diagnostics.push(MapDiagnostic::UnreachableRegion(location, region.len()));
+            }
+        }
+    }
+    diagnostics
+}
+
+fn get_passable_locations(map_layer: &MapLayer, index: usize) -> Vec<MapLocation> {
+    map_layer
+        .as_location_and_tile_vector()
+        .into_iter()
+        .filter(|(_tile_location, tile)| is_passable(tile))
+        .map(|(tile_location, _tile)| MapLocation::new(index, tile_location))
+        .collect()
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::FileSystem;
+    use crate::constants::*;
+    use crate::game::map::{SurfaceTile, TileLocation};
+    use crate::game::map_metadata::{MapMetadata, Placement};
+
+    fn at(map_layer: usize, i: i32, j: i32) -> MapLocation {
+        MapLocation::new(map_layer, TileLocation::new(i, j))
+    }
+
+    // Two walled rooms on layer 0 joined by a door, with stairs down to a small second layer.
+    fn get_example_map() -> Map {
+        let mut upper = MapLayer::fill(9, 5, Tile::empty_ground());
+        upper.update_edges(&Tile::wall());
+        (0..5).for_each(|j| upper.update(Tile::wall(), 4, j).unwrap());
+        upper
+            .update(Tile::empty(SurfaceTile::new(DOOR_CLOSED_TERRAIN)), 4, 2)
+            .unwrap();
+        upper
+            .update(
+                Tile::empty(SurfaceTile::linked(STAIRS_DOWN_TERRAIN, at(1, 1, 1))),
+                6,
+                2,
+            )
+            .unwrap();
+        let mut lower = MapLayer::fill(3, 3, Tile::empty_ground());
+        lower.update_edges(&Tile::wall());
+
+        let mut metadata = MapMetadata::new("Example");
+        metadata.add_player_spawn(at(0, 1, 1));
+        metadata.add_placement(Placement::new(PlacementKind::NPC, "Jep", at(0, 2, 2)));
+        Map::with_metadata(vec![upper, lower], metadata)
+    }
+
+    #[test]
+    fn test_validate_map_accepts_connected_maps() {
+        let report = validate_map(&get_example_map());
+        assert!(report.is_empty(), "{:?}", report);
+        assert_eq!(report.to_string(), "The map is valid.");
+    }
+
+    #[test]
+    fn test_shipped_maps_have_no_errors() {
+        let maps = FileSystem::<Map>::new_directory(MAP_DIRECTORY)
+            .load_all()
+            .expect("The shipped maps should load.");
+        for (name, map) in maps {
+            let report = validate_map(&map);
+            assert!(!report.has_errors(), "{}: {}", name, report.to_string());
+        }
+    }
+
+    #[test]
+    fn test_validate_map_reports_bad_placements_and_links() {
+        let mut map = get_example_map();
+        let mut metadata = map.get_metadata().clone();
+        metadata.add_player_spawn(at(0, 0, 0));
+        metadata.add_placement(Placement::new(PlacementKind::Enemy, "Rat", at(0, 20, 2)));
+        metadata.add_placement(Placement::new(PlacementKind::Item, "key", at(0, 0, 1)));
+        map.set_metadata(metadata);
+        map.update(
+            Tile::empty(SurfaceTile::linked(PORTAL_TERRAIN, at(1, 0, 0))),
+            at(0, 7, 3),
+        )
+        .unwrap();
+
+        let report = validate_map(&map);
+        assert_eq!(
+            report.get_errors(),
+            vec![
+                &MapDiagnostic::SpawnBlocked(at(0, 0, 0)),
+                &MapDiagnostic::PlacementOutOfBounds("Rat".to_string(), at(0, 20, 2)),
+                &MapDiagnostic::BrokenLink(at(0, 7, 3), at(1, 0, 0)),
+            ]
+        );
+        assert!(report.get_warnings().is_empty());
+        assert!(report.to_string().starts_with("3 errors and 0 warnings."));
+    }
+
+    #[test]
+    fn test_validate_map_reports_unreachable_regions() {
+        let mut map = get_example_map();
+        map.update(Tile::wall(), at(0, 4, 2)).unwrap();
+        let report = validate_map(&map);
+        // The far room and the layer its stairs lead to are one region.
+        assert_eq!(
+            report.get_warnings(),
+            vec![&MapDiagnostic::UnreachableRegion(at(0, 5, 1), 10)]
+        );
+        assert!(!report.has_errors());
+
+        map.set_metadata(MapMetadata::default());
+        assert_eq!(
+            validate_map(&map).get_diagnostics(),
+            &vec![MapDiagnostic::NoPlayerSpawn]
+        );
+
+        let ragged: MapLayer =
+            serde_json::from_str(r#"[[{"surface":"ground","stack":[]}],[]]"#).unwrap();
+        assert_eq!(
+            validate_map(&Map::new(vec![ragged])).get_diagnostics(),
+            &vec![MapDiagnostic::RaggedLayer(0)]
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use egui::{Align2, Color32, Rect, Sense, Stroke, Ui};
+
+use super::character::LocationComponent;
+use super::chunk::RenderedTileGrid;
+use super::enemy::EnemyComponent;
+use super::map::{MapLayer, MapLocation, TileGrid, TileLocation};
+use super::npc::NPCComponent;
+use super::player::PlayerComponent;
+use super::resources::{GameState, LoadedMap};
+use super::vision::FieldOfView;
+use crate::constants::*;
+use crate::ui::get_underlined_text;
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        let generalized_exploring =
+            || in_state(GameState::Exploring).or_else(in_state(GameState::NonPlayerTurns));
+        app.add_systems(
+            Update,
+            render_minimap_system.run_if(generalized_exploring()),
+        )
+        .add_systems(
+            Update,
+            render_overview_system.run_if(in_state(GameState::ViewingMap)),
+        );
+    }
+}
+
+// Systems
+
+fn render_minimap_system(
+    mut contexts: EguiContexts,
+    map: Res<LoadedMap>,
+    field_of_view: Res<FieldOfView>,
+    player_query: Query<&LocationComponent, With<PlayerComponent>>,
+    character_query: Query<
+        (&LocationComponent, Option<&NPCComponent>),
+        Or<(With<NPCComponent>, With<EnemyComponent>)>,
+    >,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    window_query: Query<&Window>,
+) {
+    let minimap = match get_minimap(&map, &field_of_view, &player_query, &character_query) {
+        Some(minimap) => minimap,
+        None => return,
+    };
+    let cell_size = MINIMAP_CELL_SIZE
+        .min(MINIMAP_MAX_SIZE / minimap.width().max(minimap.height()).max(1) as f32);
+    let view = get_view(&camera_query, &window_query);
+
+    egui::Window::new("minimap")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(Align2::RIGHT_TOP, MINIMAP_OFFSET)
+        .show(contexts.ctx_mut(), |ui| {
+            paint_minimap(ui, &minimap, cell_size, view);
+        });
+}
+
+fn render_overview_system(
+    mut contexts: EguiContexts,
+    map: Res<LoadedMap>,
+    field_of_view: Res<FieldOfView>,
+    player_query: Query<&LocationComponent, With<PlayerComponent>>,
+    character_query: Query<
+        (&LocationComponent, Option<&NPCComponent>),
+        Or<(With<NPCComponent>, With<EnemyComponent>)>,
+    >,
+) {
+    let minimap = match get_minimap(&map, &field_of_view, &player_query, &character_query) {
+        Some(minimap) => minimap,
+        None => return,
+    };
+
+    egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+        let map_layer = match player_query.get_single() {
+            Ok(player_location) => player_location.0.get_map_layer(),
+            Err(_) => 0,
+        };
+        ui.label(get_underlined_text(format!(
+            "{} (layer {})",
+            map.0.get_metadata().get_name(),
+            map_layer
+        )));
+        let available = ui.available_size();
+        let cell_size = (available.x / minimap.width().max(1) as f32)
+            .min(available.y / minimap.height().max(1) as f32);
+        ui.centered_and_justified(|ui| {
+            paint_minimap(ui, &minimap, cell_size, None);
+        });
+    });
+}
+
+// End Systems
+
+// Structs
+
+// One cell per tile of the player's layer.  Only explored tiles are shown, and characters only
+// while they can be seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Minimap(Vec<Vec<MinimapCell>>);
+
+impl Minimap {
+    pub fn new(map_layer: &MapLayer, map_layer_index: usize, field_of_view: &FieldOfView) -> Self {
+        let mut cells = vec![vec![MinimapCell::Unexplored; map_layer.height()]; map_layer.width()];
+        for (tile_location, tile) in map_layer.as_location_and_tile_vector() {
+            let location = MapLocation::new(map_layer_index, tile_location);
+            let terrain = if tile.get_surface().get_linked_destination().is_some() {
+                MinimapTerrain::Link
+            } else if tile.is_traversable() {
+                MinimapTerrain::Floor
+            } else {
+                MinimapTerrain::Wall
+            };
+            cells[tile_location.i as usize][tile_location.j as usize] =
+                if field_of_view.is_visible(location) {
+                    MinimapCell::Visible(terrain)
+                } else if field_of_view.is_remembered(location) {
+                    MinimapCell::Remembered(terrain)
+                } else {
+                    MinimapCell::Unexplored
+                };
+        }
+        Self(cells)
+    }
+
+    pub fn width(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.0.first().map_or(0, |column| column.len())
+    }
+
+    pub fn get(&self, location: TileLocation) -> Option<MinimapCell> {
+        if location.i < 0 || location.j < 0 {
+            return None;
+        }
+        self.0
+            .get(location.i as usize)
+            .and_then(|column| column.get(location.j as usize))
+            .cloned()
+    }
+
+    // Characters are only marked where the tile can currently be seen, except for the player.
+    pub fn mark(&mut self, location: TileLocation, cell: MinimapCell) {
+        let is_visible = match self.get(location) {
+            Some(MinimapCell::Visible(_)) => true,
+            Some(_) => cell == MinimapCell::Player,
+            None => return,
+        };
+        if is_visible {
+            self.0[location.i as usize][location.j as usize] = cell;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinimapCell {
+    Unexplored,
+    Remembered(MinimapTerrain),
+    Visible(MinimapTerrain),
+    Player,
+    NPC,
+    Enemy,
+}
+
+impl MinimapCell {
+    pub fn get_color(&self) -> Option<Color32> {
+        match self {
+            Self::Unexplored => None,
+            Self::Remembered(terrain) => Some(terrain.get_color().gamma_multiply(0.5)),
+            Self::Visible(terrain) => Some(terrain.get_color()),
+            Self::Player => Some(Color32::YELLOW),
+            Self::NPC => Some(Color32::GREEN),
+            Self::Enemy => Some(Color32::RED),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinimapTerrain {
+    Floor,
+    Wall,
+    Link,
+}
+
+impl MinimapTerrain {
+    pub fn get_color(&self) -> Color32 {
+        match self {
+            Self::Floor => Color32::from_gray(90),
+            Self::Wall => Color32::from_gray(200),
+            Self::Link => Color32::LIGHT_BLUE,
+        }
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+fn get_minimap(
+    map: &LoadedMap,
+    field_of_view: &FieldOfView,
+    player_query: &Query<&LocationComponent, With<PlayerComponent>>,
+    character_query: &Query<
+        (&LocationComponent, Option<&NPCComponent>),
+        Or<(With<NPCComponent>, With<EnemyComponent>)>,
+    >,
+) -> Option<Minimap> {
+    let player_location = player_query.get_single().ok()?.0;
+    let map_layer_index = player_location.get_map_layer();
+    let map_layer = map.0.get_layer(map_layer_index).ok()?;
+
+    let mut minimap = Minimap::new(map_layer, map_layer_index, field_of_view);
+    for (location, maybe_npc) in character_query.iter() {
+        if location.0.get_map_layer() != map_layer_index {
+            continue;
+        }
+        let cell = match maybe_npc {
+            Some(_) => MinimapCell::NPC,
+            None => MinimapCell::Enemy,
+        };
+        minimap.mark(location.0.get_tile_location(), cell);
+    }
+    minimap.mark(player_location.get_tile_location(), MinimapCell::Player);
+    Some(minimap)
+}
+
+// The bottom left and top right tiles the camera can see.
+fn get_view(
+    camera_query: &Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    window_query: &Query<&Window>,
+) -> Option<(TileLocation, TileLocation)> {
+    let (camera_transform, projection) = camera_query.get_single().ok()?;
+    let window = window_query.get_single().ok()?;
+    let center = camera_transform.translation.truncate();
+    let half_size = Vec2::new(window.width(), window.height()) * projection.scale / 2.;
+    Some((
+        TileGrid::world_to_tile_coordinates(center - half_size),
+        TileGrid::world_to_tile_coordinates(center + half_size),
+    ))
+}
+
+// Rows are flipped, since tiles count up from the bottom and the screen counts down from the top.
+fn paint_minimap(
+    ui: &mut Ui,
+    minimap: &Minimap,
+    cell_size: f32,
+    view: Option<(TileLocation, TileLocation)>,
+) {
+    let size = egui::vec2(
+        minimap.width() as f32 * cell_size,
+        minimap.height() as f32 * cell_size,
+    );
+    let (response, painter) = ui.allocate_painter(size, Sense::hover());
+    let origin = response.rect.min;
+    let get_cell_rect = |location: TileLocation| {
+        let top_left = origin
+            + egui::vec2(
+                location.i as f32 * cell_size,
+                (minimap.height() as i32 - 1 - location.j) as f32 * cell_size,
+            );
+        Rect::from_min_size(top_left, egui::vec2(cell_size, cell_size))
+    };
+
+    painter.rect_filled(response.rect, 0., Color32::BLACK);
+    for i in 0..minimap.width() as i32 {
+        for j in 0..minimap.height() as i32 {
+            let location = TileLocation::new(i, j);
+            if let Some(color) = minimap.get(location).and_then(|cell| cell.get_color()) {
+                painter.rect_filled(get_cell_rect(location), 0., color);
+            }
+        }
+    }
+
+    if let Some((bottom_left, top_right)) = view {
+        let view_rect = get_cell_rect(bottom_left)
+            .union(get_cell_rect(top_right))
+            .intersect(response.rect);
+        painter.rect_stroke(view_rect, 0., Stroke::new(1., Color32::WHITE));
+    }
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::Tile;
+
+    #[test]
+    fn test_minimap_shows_explored_tiles_and_visible_characters() {
+        let mut map_layer = MapLayer::fill(4, 3, Tile::empty_ground());
+        map_layer.update(Tile::wall(), 1, 0).unwrap();
+        let mut field_of_view = FieldOfView::default();
+        field_of_view.update(
+            [(0, 0), (1, 0), (2, 2)]
+                .into_iter()
+                .map(|(i, j)| MapLocation::new(0, TileLocation::new(i, j)))
+                .collect(),
+        );
+        field_of_view.update(
+            [(0, 0), (1, 0)]
+                .into_iter()
+                .map(|(i, j)| MapLocation::new(0, TileLocation::new(i, j)))
+                .collect(),
+        );
+
+        let mut minimap = Minimap::new(&map_layer, 0, &field_of_view);
+        assert_eq!((minimap.width(), minimap.height()), (4, 3));
+        assert_eq!(
+            minimap.get(TileLocation::new(1, 0)),
+            Some(MinimapCell::Visible(MinimapTerrain::Wall))
+        );
+        assert_eq!(
+            minimap.get(TileLocation::new(2, 2)),
+            Some(MinimapCell::Remembered(MinimapTerrain::Floor))
+        );
+        assert_eq!(
+            minimap.get(TileLocation::new(3, 1)),
+            Some(MinimapCell::Unexplored)
+        );
+        assert_eq!(minimap.get(TileLocation::new(4, 0)), None);
+
+        minimap.mark(TileLocation::new(0, 0), MinimapCell::NPC);
+        minimap.mark(TileLocation::new(2, 2), MinimapCell::Enemy);
+        minimap.mark(TileLocation::new(3, 1), MinimapCell::Player);
+        assert_eq!(minimap.get(TileLocation::new(0, 0)), Some(MinimapCell::NPC));
+        assert_eq!(
+            minimap.get(TileLocation::new(2, 2)),
+            Some(MinimapCell::Remembered(MinimapTerrain::Floor))
+        );
+        assert_eq!(
+            minimap.get(TileLocation::new(3, 1)),
+            Some(MinimapCell::Player)
+        );
+    }
+}
@@ -1,13 +1,30 @@
+pub mod ascii_map;
+pub mod body_plan;
 pub mod character;
+pub mod chunk;
+pub mod damage;
 pub mod dialog;
 pub mod enemy;
+pub mod environment;
 pub mod events;
 pub mod exploring;
+pub mod generation;
+pub mod health;
 pub mod interacting;
+pub mod light;
 pub mod map;
+pub mod map_file;
+pub mod map_metadata;
+pub mod map_validation;
+pub mod minimap;
 pub mod npc;
 pub mod particle;
+pub mod pathfinding;
 pub mod pause;
 pub mod player;
 pub mod resources;
+pub mod terrain;
+pub mod tileset;
+pub mod trap;
+pub mod vision;
 pub mod world;
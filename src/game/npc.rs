@@ -2,7 +2,8 @@ use bevy::{ecs::system::EntityCommands, prelude::*};
 use serde::{Deserialize, Serialize};
 
 use super::{
-    character::{BodyComponent, BodyPartTreeNode, LocationComponent},
+    body_plan::BodyPlanRegistry,
+    character::{BloodComponent, BodyComponent, LocationComponent},
     dialog::Dialog,
     interacting::{Interactable, InteractableComponent},
     map::MapLocation,
@@ -18,24 +19,35 @@ pub struct NPCComponent;
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct NPC {
     pub name: String,
-    pub location: MapLocation,
     pub dialog: Dialog,
+    // Humanoid when left out, see BodyPlanRegistry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_plan: Option<String>,
 }
 
 impl NPC {
-    pub fn new(name: String, location: MapLocation, dialog: Dialog) -> Self {
+    pub fn new(name: String, dialog: Dialog) -> Self {
         Self {
             name,
-            location,
             dialog,
+            body_plan: None,
         }
     }
 
-    pub fn spawn(&self, entity_commands: &mut EntityCommands) -> Entity {
+    pub fn get_body_plan(&self) -> Option<&str> {
+        self.body_plan.as_deref()
+    }
+
+    // Where NPCs stand is up to the map, see MapMetadata.
+    pub fn spawn(&self, entity_commands: &mut EntityCommands, location: MapLocation) -> Entity {
         entity_commands
             .insert(NPCComponent)
-            .insert(LocationComponent(self.location))
-            .insert(BodyComponent(BodyPartTreeNode::new_humanoid()))
+            .insert(Name::new(self.name.clone()))
+            .insert(LocationComponent(location))
+            .insert(BodyComponent(
+                BodyPlanRegistry::global().build(self.body_plan.as_deref()),
+            ))
+            .insert(BloodComponent::default())
             .insert(InteractableComponent(Interactable::Dialog(
                 self.dialog.clone(),
             )))
@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 
 use super::events::BoundStateComponent;
 use super::resources::GameState;
+use super::tileset::RenderSettings;
 use super::{
     events::Direction,
     map::{AsciiTileAppearance, TileAppearance, TileGrid, TileLocation},
@@ -30,6 +31,7 @@ impl ParticleEmitterComponent {
         &self,
         commands: &mut Commands,
         font: Handle<Font>,
+        render_settings: &RenderSettings,
         bound_state: GameState,
         origin: Vec2,
     ) -> Entity {
@@ -37,7 +39,7 @@ impl ParticleEmitterComponent {
         let appearance = self.spec.appearance.clone();
         let timer = movement.get_timer();
 
-        let initial_appearance = appearance.get_appearance();
+        let initial_appearance = appearance.get_appearance(render_settings);
         let location = self.spec.emission_location.get_relative_location();
 
         let position = origin + TileGrid::tile_to_world_coordinates(location);
@@ -105,13 +107,11 @@ impl ParticleSpec {
         &self,
         commands: &mut Commands,
         font: Handle<Font>,
+        render_settings: &RenderSettings,
         bound_state: GameState,
         location: TileLocation,
     ) -> Entity {
-        let initial_appearance = match &self.appearance {
-            ParticleAppearance::Constant(appearance) => appearance.clone(),
-        };
-        let entity = TileAppearance::Ascii(initial_appearance).render(
+        let entity = self.appearance.get_appearance(render_settings).render(
             &mut commands
                 .spawn_empty()
                 .insert(BoundStateComponent(bound_state)),
@@ -230,9 +230,11 @@ pub enum ParticleAppearance {
 }
 
 impl ParticleAppearance {
-    pub fn get_appearance(&self) -> TileAppearance {
+    pub fn get_appearance(&self, render_settings: &RenderSettings) -> TileAppearance {
         match self {
-            Self::Constant(appearance) => TileAppearance::Ascii(appearance.clone()),
+            Self::Constant(appearance) => {
+                render_settings.get_particle_appearance(appearance.clone())
+            }
         }
     }
 }
@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+
+use petgraph::algo::astar;
+use petgraph::graphmap::DiGraphMap;
+use petgraph::visit::EdgeRef;
+
+use super::events::Direction;
+use super::map::{MapError, MapLayer, MapLocation, TileLocation};
+
+// Structs
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OccupiedLocations(HashSet<MapLocation>);
+
+impl FromIterator<MapLocation> for OccupiedLocations {
+    fn from_iter<I: IntoIterator<Item = MapLocation>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl OccupiedLocations {
+    pub fn new(locations: HashSet<MapLocation>) -> Self {
+        Self(locations)
+    }
+
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, location: MapLocation) -> bool {
+        self.0.contains(&location)
+    }
+
+    pub fn insert(&mut self, location: MapLocation) {
+        self.0.insert(location);
+    }
+
+    pub fn remove(&mut self, location: MapLocation) {
+        self.0.remove(&location);
+    }
+
+    pub fn on_layer(&self, map_layer: usize) -> HashSet<TileLocation> {
+        self.0
+            .iter()
+            .filter(|location| location.get_map_layer() == map_layer)
+            .map(|location| location.get_tile_location())
+            .collect()
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+// Finds the shortest path using the same rules as movement: any of the eight directions onto a
+// traversable, unoccupied tile.  The start and goal are allowed to be occupied so that an entity
+// can path from where it stands towards another entity, but the goal still has to be traversable.
+pub fn find_path(
+    map_layer: &MapLayer,
+    start: TileLocation,
+    goal: TileLocation,
+    occupied: &HashSet<TileLocation>,
+) -> Result<Vec<Direction>, MapError> {
+    map_layer.get_from_location(start)?;
+    if !map_layer.get_from_location(goal)?.is_traversable() {
+        return Err(MapError::NoPathExists);
+    }
+    if start == goal {
+        return Ok(Vec::new());
+    }
+
+    let graph = build_traversal_graph(map_layer, occupied, &[start, goal]);
+    let (_cost, path) = astar(
+        &graph,
+        start,
+        |location| location == goal,
+        |edge| *edge.weight(),
+        |location| location.get_chebyshev_distance(goal),
+    )
+    .ok_or(MapError::NoPathExists)?;
+
+    Ok(path
+        .windows(2)
+        .map(|step| {
+            let offset = TileLocation::new(step[1].i - step[0].i, step[1].j - step[0].j);
+            Direction::from_tile_location(offset)
+                .expect("Edges are only added between neighbouring tiles.")
+        })
+        .collect())
+}
+
+fn build_traversal_graph(
+    map_layer: &MapLayer,
+    occupied: &HashSet<TileLocation>,
+    occupiable: &[TileLocation],
+) -> DiGraphMap<TileLocation, u32> {
+    let is_node = |location: TileLocation| {
+        map_layer
+            .get_from_location(location)
+            .map_or(false, |tile| tile.is_traversable())
+            && (occupiable.contains(&location) || !occupied.contains(&location))
+    };
+
+    let mut graph = DiGraphMap::new();
+    for (location, _tile) in map_layer.as_location_and_tile_vector() {
+        if !is_node(location) {
+            continue;
+        }
+        graph.add_node(location);
+        for direction in Direction::all() {
+            let neighbour = location + direction.as_tile_location();
+            if is_node(neighbour) {
+                // Stepping onto a tile costs that tile's terrain movement cost.
+                let cost = map_layer
+                    .get_from_location(neighbour)
+                    .map_or(1, |tile| tile.get_movement_cost());
+                graph.add_edge(location, neighbour, cost);
+            }
+        }
+    }
+    graph
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::{Map, Tile};
+
+    fn walk(start: TileLocation, path: &Vec<Direction>) -> TileLocation {
+        let mut location = start;
+        path.iter().for_each(|direction| location.step(*direction));
+        location
+    }
+
+    #[test]
+    fn test_find_path_in_open_room_moves_diagonally() {
+        let map_layer = MapLayer::fill(10, 10, Tile::empty_ground());
+        let start = TileLocation::new(1, 1);
+        let goal = TileLocation::new(5, 3);
+
+        let path = find_path(&map_layer, start, goal, &HashSet::new()).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(walk(start, &path), goal);
+
+        assert_eq!(
+            find_path(&map_layer, start, start, &HashSet::new()).unwrap(),
+            Vec::new()
+        );
+    }
+
+    #[test]
+    fn test_find_path_avoids_walls_and_occupied_tiles() {
+        let mut map_layer = MapLayer::fill(7, 7, Tile::empty_ground());
+        (0..6).for_each(|j| map_layer.update(Tile::wall(), 3, j).unwrap());
+        let start = TileLocation::new(1, 1);
+        let goal = TileLocation::new(5, 1);
+
+        let path = find_path(&map_layer, start, goal, &HashSet::new()).unwrap();
+        assert_eq!(walk(start, &path), goal);
+        let mut location = start;
+        for direction in path.iter() {
+            location.step(*direction);
+            assert!(map_layer
+                .get_from_location(location)
+                .unwrap()
+                .is_traversable());
+        }
+
+        let occupied = vec![TileLocation::new(3, 6)].into_iter().collect();
+        assert!(matches!(
+            find_path(&map_layer, start, goal, &occupied),
+            Err(MapError::NoPathExists)
+        ));
+
+        let occupied_goal = vec![goal].into_iter().collect();
+        assert!(find_path(&map_layer, start, goal, &occupied_goal).is_ok());
+
+        assert!(matches!(
+            find_path(&map_layer, start, TileLocation::new(3, 1), &HashSet::new()),
+            Err(MapError::NoPathExists)
+        ));
+    }
+
+    #[test]
+    fn test_map_find_path_respects_layers() {
+        let map = Map::new(vec![
+            MapLayer::fill(5, 5, Tile::empty_ground()),
+            MapLayer::fill(5, 5, Tile::empty_ground()),
+        ]);
+        let start = MapLocation::new(0, TileLocation::new(0, 0));
+
+        let blocker = MapLocation::new(1, TileLocation::new(1, 1));
+        let occupied: OccupiedLocations = vec![blocker].into_iter().collect();
+        let path = map
+            .find_path(
+                start,
+                MapLocation::new(0, TileLocation::new(2, 2)),
+                &occupied,
+            )
+            .unwrap();
+        assert_eq!(path, vec![Direction::UpRight, Direction::UpRight]);
+
+        assert!(matches!(
+            map.find_path(start, blocker, &occupied),
+            Err(MapError::DifferentLayers)
+        ));
+        assert!(matches!(
+            map.find_path(
+                start,
+                MapLocation::new(0, TileLocation::new(9, 9)),
+                &occupied
+            ),
+            Err(MapError::OutOfBounds)
+        ));
+    }
+}
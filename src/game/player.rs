@@ -3,9 +3,12 @@ use bevy::prelude::*;
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::character::BloodComponent;
 use super::character::BodyComponent;
 use super::character::BodyPartTreeNode;
+use super::character::InventoryComponent;
 use super::character::LocationComponent;
+use super::light::{LightSourceComponent, LightSpec};
 use super::map::MapLocation;
 
 // Components
@@ -32,6 +35,9 @@ impl Player {
             .insert(PlayerComponent)
             .insert(LocationComponent(self.location))
             .insert(BodyComponent(BodyPartTreeNode::new_humanoid()))
+            .insert(BloodComponent::default())
+            .insert(InventoryComponent::default())
+            .insert(LightSourceComponent(LightSpec::lamp()))
             .id()
     }
 }
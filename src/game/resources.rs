@@ -5,7 +5,7 @@ use rand::rngs::StdRng;
 
 use crate::specs::SpecLookup;
 
-use super::{map::Map, npc::NPC};
+use super::{enemy::Enemy, map::Map, npc::NPC};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Resource)]
 pub struct PausedState(pub GameState);
@@ -15,12 +15,13 @@ pub enum GameState {
     #[default]
     LoadingAssets,
     LoadingUI,
-    InitializingWorld,
     LoadingMap,
+    InitializingWorld,
     EditingMapMenu,
     EditingMap,
     Exploring,
     NonPlayerTurns,
+    ViewingMap,
     Interacting,
     PlayerMenu,
     Paused,
@@ -31,6 +32,16 @@ pub enum GameState {
 #[derive(Debug, Resource)]
 pub struct RngResource(pub RwLock<StdRng>);
 
+#[cfg(test)]
+impl RngResource {
+    // Rolls the same way on every run.
+    pub fn seeded(seed: u64) -> Self {
+        use rand::SeedableRng;
+
+        Self(RwLock::new(StdRng::seed_from_u64(seed)))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Resource)]
 pub struct LoadedFont(pub Handle<Font>);
 
@@ -64,4 +75,13 @@ impl NPCSpecs {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Resource)]
+pub struct EnemySpecs(pub SpecLookup<Enemy>);
+
+impl EnemySpecs {
+    pub fn from_vec(enemies: Vec<Enemy>) -> Self {
+        Self(SpecLookup::from_vec(enemies, |enemy| enemy.name.clone()))
+    }
+}
+
 // End Specs
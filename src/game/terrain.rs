@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use super::character::{Damage, DamageError, DamageSource, Probability};
+use super::damage::{DamageLoadError, DamageRegistry};
+use super::events::LayerChange;
+use super::light::LightSpec;
+use super::map::{AsciiTileAppearance, ColorCode};
+use super::particle::ParticleSpec;
+use crate::assets::FileSystem;
+use crate::constants::*;
+
+static TERRAIN_REGISTRY: OnceLock<TerrainRegistry> = OnceLock::new();
+
+// Structs
+
+// Maps refer to terrain by id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerrainSpec {
+    name: String,
+    glyph: char,
+    color: ColorCode,
+    traversable: bool,
+    opaque: bool,
+    movement_cost: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    particle: Option<ParticleSpec>,
+    // The layer changes that follow this terrain's destination, e.g. stairs down only descend.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    links: Vec<LayerChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    door: Option<DoorSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    light: Option<LightSpec>,
+    // The terrain this turns into when fire reaches it, e.g. a bookshelf becomes a burning one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    burns_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fire: Option<FireSpec>,
+}
+
+impl TerrainSpec {
+    pub fn new(
+        name: &str,
+        glyph: char,
+        color: ColorCode,
+        traversable: bool,
+        opaque: bool,
+        movement_cost: u32,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            glyph,
+            color,
+            traversable,
+            opaque,
+            movement_cost,
+            particle: None,
+            links: Vec::new(),
+            door: None,
+            light: None,
+            burns_to: None,
+            fire: None,
+        }
+    }
+
+    // Stands in for ids that aren't in the registry so a bad map still renders.
+    pub fn unknown() -> Self {
+        Self::new("unknown terrain", '?', ColorCode::Red, false, false, 1)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_appearance(&self) -> AsciiTileAppearance {
+        AsciiTileAppearance::new(self.glyph, self.color.clone())
+    }
+
+    pub fn is_traversable(&self) -> bool {
+        self.traversable
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
+
+    // Pathfinding relies on every step costing at least one.
+    pub fn get_movement_cost(&self) -> u32 {
+        self.movement_cost.max(1)
+    }
+
+    pub fn get_particle_spec(&self) -> Option<ParticleSpec> {
+        self.particle.clone()
+    }
+
+    pub fn links(&self, layer_change: LayerChange) -> bool {
+        self.links.contains(&layer_change)
+    }
+
+    pub fn get_door(&self) -> Option<&DoorSpec> {
+        self.door.as_ref()
+    }
+
+    pub fn get_light(&self) -> Option<&LightSpec> {
+        self.light.as_ref()
+    }
+
+    pub fn get_burns_to(&self) -> Option<&str> {
+        self.burns_to.as_deref()
+    }
+
+    pub fn get_fire(&self) -> Option<&FireSpec> {
+        self.fire.as_ref()
+    }
+}
+
+// Burning terrain.  Every turn it may set its flammable neighbours alight, burn out and give off
+// smoke, and it hurts whoever stands in it.  Fires with nothing to burn out to burn forever.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FireSpec {
+    spread: Probability,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    burns_out_to: Option<String>,
+    #[serde(default = "Probability::one")]
+    burn_out: Probability,
+    #[serde(default)]
+    smoke: u8,
+    message: String,
+    damage: DamageSource,
+    // Filled in from damage when the registry loads, see TerrainRegistry::from_directory.
+    #[serde(skip)]
+    resolved_damage: Option<Damage>,
+}
+
+impl FireSpec {
+    pub fn get_spread(&self) -> Probability {
+        self.spread
+    }
+
+    pub fn get_burns_out_to(&self) -> Option<&str> {
+        self.burns_out_to.as_deref()
+    }
+
+    pub fn get_burn_out(&self) -> Probability {
+        self.burn_out
+    }
+
+    pub fn get_smoke(&self) -> u8 {
+        self.smoke
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_damage(&self) -> Damage {
+        self.resolved_damage
+            .clone()
+            .expect("Fire damage is resolved when the registry loads it.")
+    }
+
+    fn resolve_damage(&mut self, registry: &DamageRegistry) -> Result<(), DamageError> {
+        self.resolved_damage = Some(self.damage.resolve(registry)?);
+        Ok(())
+    }
+}
+
+// Doors are pairs of terrain that turn into each other when used, e.g. a closed door toggles to an
+// open one and back.  A locked door only toggles for someone carrying a key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DoorSpec {
+    toggles_to: String,
+    #[serde(default)]
+    locked: bool,
+    message: String,
+}
+
+impl DoorSpec {
+    pub fn new(toggles_to: &str, locked: bool, message: &str) -> Self {
+        Self {
+            toggles_to: toggles_to.to_string(),
+            locked,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn get_toggles_to(&self) -> &str {
+        &self.toggles_to
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerrainRegistry {
+    terrains: HashMap<String, TerrainSpec>,
+    unknown: TerrainSpec,
+}
+
+impl TerrainRegistry {
+    pub fn new(terrains: HashMap<String, TerrainSpec>) -> Self {
+        Self {
+            terrains,
+            unknown: TerrainSpec::unknown(),
+        }
+    }
+
+    pub fn from_directory(directory_name: &str) -> Result<Self, DamageLoadError> {
+        let mut terrains =
+            FileSystem::<TerrainSpec>::new_directory(directory_name).load_all_by_id()?;
+        for (id, terrain) in terrains.iter_mut() {
+            if let Some(fire) = terrain.fire.as_mut() {
+                fire.resolve_damage(DamageRegistry::global())
+                    .map_err(|e| DamageLoadError::InvalidDamage(id.clone(), e))?;
+            }
+        }
+        Ok(Self::new(terrains))
+    }
+
+    // Loaded up front by load_assets_system, after the damage profiles its fires name.
+    pub fn global() -> &'static Self {
+        TERRAIN_REGISTRY.get_or_init(|| {
+            Self::from_directory(TERRAIN_DIRECTORY)
+                .expect("We should be able to load the terrain specs.")
+        })
+    }
+
+    pub fn get(&self, id: &str) -> &TerrainSpec {
+        self.terrains.get(id).unwrap_or(&self.unknown)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.terrains.contains_key(id)
+    }
+
+    pub fn get_ids(&self) -> Vec<String> {
+        let mut ids = self.terrains.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+}
+
+// End Structs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terrain_registry_loads_built_in_terrain() {
+        let registry = TerrainRegistry::global();
+        for id in [
+            GROUND_TERRAIN,
+            WALL_TERRAIN,
+            FIREPLACE_TERRAIN,
+            STAIRS_UP_TERRAIN,
+            STAIRS_DOWN_TERRAIN,
+            PORTAL_TERRAIN,
+            DOOR_CLOSED_TERRAIN,
+            DOOR_OPEN_TERRAIN,
+            DOOR_LOCKED_TERRAIN,
+            BOOKSHELF_TERRAIN,
+            BURNING_BOOKSHELF_TERRAIN,
+            ASH_TERRAIN,
+        ] {
+            assert!(registry.contains(id), "Missing terrain {}", id);
+        }
+
+        let wall = registry.get(WALL_TERRAIN);
+        assert!(!wall.is_traversable());
+        assert!(wall.is_opaque());
+        assert!(registry
+            .get(FIREPLACE_TERRAIN)
+            .get_particle_spec()
+            .is_some());
+        assert!(registry.get(FIREPLACE_TERRAIN).get_light().is_some());
+        assert!(registry
+            .get(FIREPLACE_TERRAIN)
+            .get_fire()
+            .map_or(false, |fire| fire.get_burns_out_to().is_none()));
+        assert_eq!(
+            registry.get(BOOKSHELF_TERRAIN).get_burns_to(),
+            Some(BURNING_BOOKSHELF_TERRAIN)
+        );
+        assert_eq!(
+            registry
+                .get(BURNING_BOOKSHELF_TERRAIN)
+                .get_fire()
+                .and_then(|fire| fire.get_burns_out_to()),
+            Some(ASH_TERRAIN)
+        );
+        assert!(registry
+            .get(STAIRS_DOWN_TERRAIN)
+            .links(LayerChange::Descend));
+        assert!(!registry.get(STAIRS_DOWN_TERRAIN).links(LayerChange::Ascend));
+        assert!(registry.get(PORTAL_TERRAIN).links(LayerChange::Ascend));
+        assert!(registry
+            .get(DOOR_LOCKED_TERRAIN)
+            .get_door()
+            .map_or(false, |door| door.is_locked()));
+    }
+
+    #[test]
+    fn test_terrain_registry_falls_back_for_unknown_ids() {
+        let mut terrains = HashMap::new();
+        terrains.insert(
+            "mud".to_string(),
+            TerrainSpec::new("mud", '~', ColorCode::Gray, true, false, 0),
+        );
+        let registry = TerrainRegistry::new(terrains);
+
+        assert_eq!(registry.get("mud").get_name(), "mud");
+        assert_eq!(registry.get("mud").get_movement_cost(), 1);
+        assert_eq!(registry.get("lava"), &TerrainSpec::unknown());
+        assert_eq!(registry.get_ids(), vec!["mud".to_string()]);
+    }
+}
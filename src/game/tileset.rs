@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::events::ToggleRenderModeEvent;
+use super::exploring::ShouldSpawnMap;
+use super::map::{AsciiTileAppearance, Tile, TileAppearance};
+use super::resources::GameState;
+use crate::assets::FileSystem;
+use crate::constants::*;
+use crate::ui::LogState;
+
+pub struct TilesetPlugin;
+
+impl Plugin for TilesetPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RenderSettings::default())
+            .add_systems(OnEnter(GameState::LoadingAssets), load_tileset_system)
+            .add_systems(
+                Update,
+                toggle_render_mode_system.run_if(on_event::<ToggleRenderModeEvent>()),
+            );
+    }
+}
+
+// Resources
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub struct RenderSettings {
+    mode: RenderMode,
+    tileset: Option<Tileset>,
+}
+
+impl RenderSettings {
+    pub fn new(mode: RenderMode, tileset: Option<Tileset>) -> Self {
+        Self { mode, tileset }
+    }
+
+    pub fn get_mode(&self) -> RenderMode {
+        self.mode
+    }
+
+    // The tileset only applies while sprites are switched on.
+    pub fn get_active_tileset(&self) -> Option<&Tileset> {
+        match self.mode {
+            RenderMode::Ascii => None,
+            RenderMode::Sprite => self.tileset.as_ref(),
+        }
+    }
+
+    // Anything the tileset doesn't cover keeps its ASCII appearance.
+    pub fn get_tile_appearance(&self, tile: &Tile) -> TileAppearance {
+        let index = self.get_active_tileset().and_then(|tileset| {
+            match tile.get_top_visible_object() {
+                Some(object_tile) => tileset.spec.objects.get(object_tile.get_kind()),
+                None => tileset
+                    .spec
+                    .surfaces
+                    .get(tile.get_surface().get_terrain_id()),
+            }
+            .map(|index| tileset.get_appearance(*index))
+        });
+        index.unwrap_or_else(|| TileAppearance::from_tile(tile))
+    }
+
+    pub fn get_entity_appearance(
+        &self,
+        entity_name: &str,
+        ascii_appearance: AsciiTileAppearance,
+    ) -> TileAppearance {
+        self.get_active_tileset()
+            .and_then(|tileset| {
+                tileset
+                    .spec
+                    .entities
+                    .get(entity_name)
+                    .map(|index| tileset.get_appearance(*index))
+            })
+            .unwrap_or(TileAppearance::Ascii(ascii_appearance))
+    }
+
+    pub fn get_particle_appearance(&self, ascii_appearance: AsciiTileAppearance) -> TileAppearance {
+        self.get_active_tileset()
+            .and_then(|tileset| {
+                tileset
+                    .spec
+                    .particles
+                    .get(&ascii_appearance.get_character())
+                    .map(|index| tileset.get_appearance(*index))
+            })
+            .unwrap_or(TileAppearance::Ascii(ascii_appearance))
+    }
+}
+
+// End Resources
+
+// Systems
+
+fn load_tileset_system(
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut render_settings: ResMut<RenderSettings>,
+) {
+    let filesystem = FileSystem::<TilesetSpec>::new_directory(TILESET_DIRECTORY);
+    let spec = match filesystem.load(DEFAULT_TILESET_FILENAME) {
+        Ok(spec) => spec,
+        // Sprites are optional, so without a tileset the game just stays in ASCII.
+        Err(_) => return,
+    };
+
+    let image = asset_server.load(spec.image.as_str());
+    let atlas = TextureAtlas::from_grid(
+        image,
+        Vec2::new(spec.tile_width as f32, spec.tile_height as f32),
+        spec.columns,
+        spec.rows,
+        None,
+        None,
+    );
+    render_settings.tileset = Some(Tileset::new(spec, texture_atlases.add(atlas)));
+}
+
+fn toggle_render_mode_system(
+    mut toggle_event_reader: EventReader<ToggleRenderModeEvent>,
+    mut render_settings: ResMut<RenderSettings>,
+    should_spawn: Option<ResMut<ShouldSpawnMap>>,
+    mut log: ResMut<LogState>,
+) {
+    // Several presses in one frame cancel out in pairs.
+    if toggle_event_reader.iter().count() % 2 == 0 {
+        return;
+    }
+    if render_settings.tileset.is_none() {
+        log.log_string("No tileset is loaded, so only ASCII is available.");
+        return;
+    }
+
+    render_settings.mode = render_settings.mode.toggled();
+    log.log_string(&format!(
+        "Switched to {} rendering.",
+        render_settings.mode.to_string()
+    ));
+    if let Some(mut should_spawn) = should_spawn {
+        should_spawn.0 = true;
+    }
+}
+
+// End Systems
+
+// Structs
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderMode {
+    #[default]
+    Ascii,
+    Sprite,
+}
+
+impl RenderMode {
+    pub fn toggled(&self) -> Self {
+        match self {
+            Self::Ascii => Self::Sprite,
+            Self::Sprite => Self::Ascii,
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::Ascii => "ASCII",
+            Self::Sprite => "sprite",
+        }
+        .to_string()
+    }
+}
+
+// Surfaces are keyed by terrain id, objects by their kind, entities by "player", "npc" and
+// "enemy", and particles by the glyph they're drawn with in ASCII.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TilesetSpec {
+    pub image: String,
+    pub tile_width: usize,
+    pub tile_height: usize,
+    pub columns: usize,
+    pub rows: usize,
+    pub surfaces: HashMap<String, usize>,
+    pub objects: HashMap<String, usize>,
+    pub entities: HashMap<String, usize>,
+    pub particles: HashMap<char, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tileset {
+    spec: TilesetSpec,
+    atlas: Handle<TextureAtlas>,
+}
+
+impl Tileset {
+    pub fn new(spec: TilesetSpec, atlas: Handle<TextureAtlas>) -> Self {
+        Self { spec, atlas }
+    }
+
+    pub fn get_appearance(&self, index: usize) -> TileAppearance {
+        TileAppearance::Sprite(SpriteTileAppearance::new(self.atlas.clone(), index))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpriteTileAppearance {
+    atlas: Handle<TextureAtlas>,
+    index: usize,
+}
+
+impl SpriteTileAppearance {
+    pub fn new(atlas: Handle<TextureAtlas>, index: usize) -> Self {
+        Self { atlas, index }
+    }
+
+    pub fn get_atlas(&self) -> Handle<TextureAtlas> {
+        self.atlas.clone()
+    }
+
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+}
+
+// End Structs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::{ColorCode, ObjectTile, SurfaceTile};
+    use crate::game::trap::Trap;
+
+    fn get_test_settings(mode: RenderMode) -> RenderSettings {
+        let spec = TilesetSpec {
+            image: "tilesets/test.png".to_string(),
+            tile_width: 32,
+            tile_height: 32,
+            columns: 4,
+            rows: 1,
+            surfaces: vec![("ground".to_string(), 0)].into_iter().collect(),
+            objects: vec![("trap".to_string(), 1)].into_iter().collect(),
+            entities: vec![("player".to_string(), 2)].into_iter().collect(),
+            particles: vec![('S', 3)].into_iter().collect(),
+        };
+        RenderSettings::new(mode, Some(Tileset::new(spec, Handle::default())))
+    }
+
+    fn get_index(appearance: TileAppearance) -> Option<usize> {
+        match appearance {
+            TileAppearance::Sprite(sprite) => Some(sprite.get_index()),
+            TileAppearance::Ascii(_) => None,
+        }
+    }
+
+    #[test]
+    fn test_render_settings_use_tileset_indices() {
+        let settings = get_test_settings(RenderMode::Sprite);
+        assert_eq!(
+            get_index(settings.get_tile_appearance(&Tile::empty_ground())),
+            Some(0)
+        );
+        assert_eq!(
+            get_index(
+                settings
+                    .get_tile_appearance(&Tile::object(ObjectTile::Trap(Trap::new(SPIKE_TRAP))))
+            ),
+            Some(1)
+        );
+        assert_eq!(
+            get_index(settings.get_entity_appearance("player", '@'.into())),
+            Some(2)
+        );
+        assert_eq!(
+            get_index(
+                settings.get_particle_appearance(AsciiTileAppearance::new('S', ColorCode::Gray))
+            ),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_render_settings_fall_back_to_ascii() {
+        let settings = get_test_settings(RenderMode::Sprite);
+        assert_eq!(
+            settings.get_tile_appearance(&Tile::wall()),
+            TileAppearance::from_tile(&Tile::wall())
+        );
+        assert_eq!(
+            settings.get_entity_appearance("enemy", 's'.into()),
+            TileAppearance::Ascii('s'.into())
+        );
+
+        let settings = get_test_settings(RenderMode::Ascii);
+        assert_eq!(
+            settings.get_tile_appearance(&Tile::empty(SurfaceTile::ground())),
+            TileAppearance::from_tile(&Tile::empty_ground())
+        );
+        assert_eq!(settings.get_mode().toggled(), RenderMode::Sprite);
+        assert_eq!(RenderSettings::default().get_active_tileset(), None);
+    }
+}
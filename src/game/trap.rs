@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use super::character::{Damage, DamageError, DamageSource};
+use super::damage::{DamageLoadError, DamageRegistry};
+use crate::assets::FileSystem;
+use crate::constants::*;
+
+static TRAP_REGISTRY: OnceLock<TrapRegistry> = OnceLock::new();
+
+// Structs
+
+// Trap objects refer to their spec by id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrapSpec {
+    name: String,
+    message: String,
+    damage: DamageSource,
+    // Filled in from damage when the registry loads, see TrapRegistry::from_directory.
+    #[serde(skip)]
+    resolved_damage: Option<Damage>,
+    // Hidden traps aren't drawn or described until they go off.
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    reveal_on_trigger: bool,
+    // One-shot traps are disarmed, and removed from their tile, once they go off.
+    #[serde(default)]
+    one_shot: bool,
+}
+
+impl TrapSpec {
+    pub fn new(name: &str, message: &str, damage: Damage) -> Self {
+        Self {
+            name: name.to_string(),
+            message: message.to_string(),
+            damage: damage.clone().into(),
+            resolved_damage: Some(damage),
+            hidden: false,
+            reveal_on_trigger: false,
+            one_shot: false,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_damage(&self) -> Damage {
+        self.resolved_damage
+            .clone()
+            .expect("Trap damage is resolved when the registry loads it.")
+    }
+
+    fn resolve_damage(&mut self, registry: &DamageRegistry) -> Result<(), DamageError> {
+        self.resolved_damage = Some(self.damage.resolve(registry)?);
+        Ok(())
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn reveals_on_trigger(&self) -> bool {
+        self.reveal_on_trigger
+    }
+
+    pub fn is_one_shot(&self) -> bool {
+        self.one_shot
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrapRegistry(HashMap<String, TrapSpec>);
+
+impl TrapRegistry {
+    pub fn new(traps: HashMap<String, TrapSpec>) -> Self {
+        Self(traps)
+    }
+
+    pub fn from_directory(directory_name: &str) -> Result<Self, DamageLoadError> {
+        let mut traps = FileSystem::<TrapSpec>::new_directory(directory_name).load_all_by_id()?;
+        for (id, trap) in traps.iter_mut() {
+            trap.resolve_damage(DamageRegistry::global())
+                .map_err(|e| DamageLoadError::InvalidDamage(id.clone(), e))?;
+        }
+        Ok(Self::new(traps))
+    }
+
+    // Loaded up front by load_assets_system, after the damage profiles it names.
+    pub fn global() -> &'static Self {
+        TRAP_REGISTRY.get_or_init(|| {
+            Self::from_directory(TRAP_DIRECTORY).expect("We should be able to load the trap specs.")
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TrapSpec> {
+        self.0.get(id)
+    }
+}
+
+// A trap placed on a tile.  Whether it has been revealed is kept per trap, so two traps from the
+// same spec can be found separately.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Trap {
+    spec: String,
+    revealed: bool,
+}
+
+impl Trap {
+    pub fn new(spec: &str) -> Self {
+        let revealed = TrapRegistry::global()
+            .get(spec)
+            .map_or(true, |trap_spec| !trap_spec.is_hidden());
+        Self {
+            spec: spec.to_string(),
+            revealed,
+        }
+    }
+
+    pub fn get_spec_id(&self) -> &str {
+        &self.spec
+    }
+
+    // Traps with no spec do nothing when stepped on.
+    pub fn get_spec(&self) -> Option<&'static TrapSpec> {
+        TrapRegistry::global().get(&self.spec)
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        self.revealed
+    }
+
+    pub fn reveal(&mut self) {
+        self.revealed = true;
+    }
+
+    pub fn to_string(&self) -> String {
+        self.get_spec()
+            .map_or("trap".to_string(), |spec| spec.get_name().to_string())
+    }
+}
+
+// End Structs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trap_registry_loads_built_in_traps() {
+        let registry = TrapRegistry::global();
+        let spike_trap = registry.get(SPIKE_TRAP).expect("Spike traps are built in.");
+        assert!(!spike_trap.is_hidden());
+        assert!(!spike_trap.is_one_shot());
+
+        let dart_trap = registry.get("dart_trap").expect("Dart traps are built in.");
+        assert!(dart_trap.is_hidden());
+        assert!(dart_trap.reveals_on_trigger());
+        assert!(!dart_trap.is_one_shot());
+        assert!(registry
+            .get("falling_rock_trap")
+            .map_or(false, |spec| spec.is_one_shot()));
+
+        assert!(Trap::new(SPIKE_TRAP).is_revealed());
+        assert!(!Trap::new("dart_trap").is_revealed());
+        assert_eq!(Trap::new("dart_trap").to_string(), "dart trap");
+        assert!(Trap::new("no_such_trap").get_spec().is_none());
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use super::character::{BodyComponent, LocationComponent};
+use super::environment::Smoke;
+use super::light::{update_light_map_system, LightMap};
+use super::map::{MapLayer, MapLocation, MapTileComponent, TileGrid, TileLocation};
+use super::particle::{ParticleComponent, ParticleEmitterComponent};
+use super::player::PlayerComponent;
+use super::resources::{GameState, LoadedMap};
+use crate::constants::*;
+
+pub struct VisionPlugin;
+
+impl Plugin for VisionPlugin {
+    fn build(&self, app: &mut App) {
+        let generalized_exploring =
+            || in_state(GameState::Exploring).or_else(in_state(GameState::NonPlayerTurns));
+        app.insert_resource(FieldOfView::default())
+            .add_systems(
+                Update,
+                update_field_of_view_system
+                    .after(update_light_map_system)
+                    .run_if(generalized_exploring()),
+            )
+            .add_systems(
+                Update,
+                render_field_of_view_system
+                    .after(update_field_of_view_system)
+                    .run_if(generalized_exploring()),
+            );
+    }
+}
+
+// Resources
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Resource)]
+pub struct FieldOfView {
+    visible: HashSet<MapLocation>,
+    remembered: HashSet<MapLocation>,
+}
+
+impl FieldOfView {
+    pub fn update(&mut self, visible: HashSet<MapLocation>) {
+        self.remembered.extend(visible.iter().cloned());
+        self.visible = visible;
+    }
+
+    pub fn is_visible(&self, location: MapLocation) -> bool {
+        self.visible.contains(&location)
+    }
+
+    pub fn is_remembered(&self, location: MapLocation) -> bool {
+        self.remembered.contains(&location)
+    }
+}
+
+// End Resources
+
+// Systems
+
+fn update_field_of_view_system(
+    mut field_of_view: ResMut<FieldOfView>,
+    player_query: Query<(&LocationComponent, &BodyComponent), With<PlayerComponent>>,
+    map: Res<LoadedMap>,
+    light_map: Res<LightMap>,
+    smoke: Res<Smoke>,
+) {
+    let (player_location, player_body) = match player_query.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    let map_layer_index = player_location.0.get_map_layer();
+    let map_layer = match map.0.get_layer(map_layer_index) {
+        Ok(map_layer) => map_layer,
+        Err(_) => return,
+    };
+
+    // Beyond arm's reach, only lit tiles can be made out.
+    let origin = player_location.0.get_tile_location();
+    let visible = compute_obscured_field_of_view(
+        map_layer,
+        origin,
+        player_body.0.get_sight_radius(),
+        &smoke.get_opaque_locations(map_layer_index),
+    )
+    .into_iter()
+    .filter(|tile_location| {
+        light_map.is_lit(*tile_location)
+            || tile_location.get_chebyshev_distance(origin) <= DARK_SIGHT_RADIUS
+    })
+    .map(|tile_location| MapLocation::new(map_layer_index, tile_location))
+    .collect::<HashSet<_>>();
+
+    // Only touch the resource when something changed so that change detection stays meaningful.
+    if field_of_view.visible != visible {
+        field_of_view.update(visible);
+    }
+}
+
+fn render_field_of_view_system(
+    field_of_view: Res<FieldOfView>,
+    player_query: Query<&LocationComponent, With<PlayerComponent>>,
+    mut tile_query: Query<(
+        &MapTileComponent,
+        Option<&mut Text>,
+        Option<&mut TextureAtlasSprite>,
+        &mut Visibility,
+    )>,
+    mut character_query: Query<
+        (&LocationComponent, &mut Visibility),
+        (Without<MapTileComponent>, Without<PlayerComponent>),
+    >,
+    mut particle_query: Query<
+        (&Transform, &mut Visibility),
+        (
+            Or<(With<ParticleComponent>, With<ParticleEmitterComponent>)>,
+            Without<MapTileComponent>,
+            Without<LocationComponent>,
+        ),
+    >,
+) {
+    let map_layer = match player_query.get_single() {
+        Ok(player_location) => player_location.0.get_map_layer(),
+        Err(_) => return,
+    };
+
+    for (MapTileComponent(tile_location), maybe_text, maybe_sprite, mut visibility) in
+        tile_query.iter_mut()
+    {
+        let location = MapLocation::new(map_layer, *tile_location);
+        let (new_visibility, alpha) = if field_of_view.is_visible(location) {
+            (Visibility::Inherited, 1.)
+        } else if field_of_view.is_remembered(location) {
+            (Visibility::Inherited, REMEMBERED_TILE_ALPHA)
+        } else {
+            (Visibility::Hidden, 1.)
+        };
+
+        if *visibility != new_visibility {
+            *visibility = new_visibility;
+        }
+        if let Some(mut text) = maybe_text {
+            if text
+                .sections
+                .iter()
+                .any(|section| section.style.color.a() != alpha)
+            {
+                text.sections.iter_mut().for_each(|section| {
+                    section.style.color.set_a(alpha);
+                });
+            }
+        }
+        if let Some(mut sprite) = maybe_sprite {
+            if sprite.color.a() != alpha {
+                sprite.color.set_a(alpha);
+            }
+        }
+    }
+
+    for (location, mut visibility) in character_query.iter_mut() {
+        set_visible(&mut visibility, field_of_view.is_visible(location.0));
+    }
+
+    for (transform, mut visibility) in particle_query.iter_mut() {
+        let tile_location = TileGrid::world_to_tile_coordinates(transform.translation.truncate());
+        set_visible(
+            &mut visibility,
+            field_of_view.is_visible(MapLocation::new(map_layer, tile_location)),
+        );
+    }
+}
+
+// End Systems
+
+// Helper Functions
+
+pub fn compute_field_of_view(
+    map_layer: &MapLayer,
+    origin: TileLocation,
+    radius: u32,
+) -> HashSet<TileLocation> {
+    compute_obscured_field_of_view(map_layer, origin, radius, &HashSet::new())
+}
+
+// Sight also stops at the obscured tiles, e.g. thick smoke, which are seen like walls.
+pub fn compute_obscured_field_of_view(
+    map_layer: &MapLayer,
+    origin: TileLocation,
+    radius: u32,
+    obscured: &HashSet<TileLocation>,
+) -> HashSet<TileLocation> {
+    let mut visible = HashSet::new();
+    if map_layer.get_from_location(origin).is_err() {
+        return visible;
+    }
+    visible.insert(origin);
+
+    for quadrant in Quadrant::all() {
+        let shadowcaster = Shadowcaster {
+            map_layer,
+            obscured,
+            origin,
+            radius: radius as i32,
+            quadrant,
+        };
+        shadowcaster.scan(
+            &mut visible,
+            Row::new(1, Slope::new(-1, 1), Slope::new(1, 1)),
+        );
+    }
+    visible
+}
+
+fn set_visible(visibility: &mut Mut<Visibility>, is_visible: bool) {
+    let new_visibility = if is_visible {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    if **visibility != new_visibility {
+        **visibility = new_visibility;
+    }
+}
+
+// End Helper Functions
+
+// Helper Structs
+
+// Symmetric shadowcasting, see https://www.albertford.com/shadowcasting/
+struct Shadowcaster<'a> {
+    map_layer: &'a MapLayer,
+    obscured: &'a HashSet<TileLocation>,
+    origin: TileLocation,
+    radius: i32,
+    quadrant: Quadrant,
+}
+
+impl<'a> Shadowcaster<'a> {
+    fn scan(&self, visible: &mut HashSet<TileLocation>, mut row: Row) {
+        if row.depth > self.radius {
+            return;
+        }
+
+        let mut previous_is_wall = None;
+        for column in row.get_min_column()..=row.get_max_column() {
+            let location = self.quadrant.transform(self.origin, row.depth, column);
+            let is_wall = self.is_wall(location);
+            if (is_wall || row.is_symmetric(column)) && self.is_in_radius(row.depth, column) {
+                if self.map_layer.get_from_location(location).is_ok() {
+                    visible.insert(location);
+                }
+            }
+            if previous_is_wall == Some(true) && !is_wall {
+                row.start_slope = Slope::from_column(row.depth, column);
+            }
+            if previous_is_wall == Some(false) && is_wall {
+                let mut next_row = row.next();
+                next_row.end_slope = Slope::from_column(row.depth, column);
+                self.scan(visible, next_row);
+            }
+            previous_is_wall = Some(is_wall);
+        }
+
+        if previous_is_wall == Some(false) {
+            self.scan(visible, row.next());
+        }
+    }
+
+    fn is_wall(&self, location: TileLocation) -> bool {
+        self.obscured.contains(&location) || self.map_layer.is_opaque(location).unwrap_or(true)
+    }
+
+    fn is_in_radius(&self, depth: i32, column: i32) -> bool {
+        depth * depth + column * column <= self.radius * self.radius
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    fn all() -> Vec<Self> {
+        vec![Self::North, Self::East, Self::South, Self::West]
+    }
+
+    fn transform(&self, origin: TileLocation, depth: i32, column: i32) -> TileLocation {
+        match self {
+            Self::North => TileLocation::new(origin.i + column, origin.j + depth),
+            Self::South => TileLocation::new(origin.i + column, origin.j - depth),
+            Self::East => TileLocation::new(origin.i + depth, origin.j + column),
+            Self::West => TileLocation::new(origin.i - depth, origin.j + column),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Row {
+    depth: i32,
+    start_slope: Slope,
+    end_slope: Slope,
+}
+
+impl Row {
+    fn new(depth: i32, start_slope: Slope, end_slope: Slope) -> Self {
+        Self {
+            depth,
+            start_slope,
+            end_slope,
+        }
+    }
+
+    fn next(&self) -> Self {
+        Self::new(self.depth + 1, self.start_slope, self.end_slope)
+    }
+
+    fn get_min_column(&self) -> i32 {
+        // Round depth * start_slope, with ties going up
+        let numerator = 2 * self.depth * self.start_slope.numerator + self.start_slope.denominator;
+        numerator.div_euclid(2 * self.start_slope.denominator)
+    }
+
+    fn get_max_column(&self) -> i32 {
+        // Round depth * end_slope, with ties going down
+        let numerator = self.end_slope.denominator - 2 * self.depth * self.end_slope.numerator;
+        -numerator.div_euclid(2 * self.end_slope.denominator)
+    }
+
+    fn is_symmetric(&self, column: i32) -> bool {
+        column * self.start_slope.denominator >= self.depth * self.start_slope.numerator
+            && column * self.end_slope.denominator <= self.depth * self.end_slope.numerator
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Slope {
+    numerator: i32,
+    denominator: i32,
+}
+
+impl Slope {
+    fn new(numerator: i32, denominator: i32) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    fn from_column(depth: i32, column: i32) -> Self {
+        Self::new(2 * column - 1, 2 * depth)
+    }
+}
+
+// End Helper Structs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::Tile;
+
+    #[test]
+    fn test_field_of_view_in_open_room() {
+        let map_layer = MapLayer::fill(11, 11, Tile::empty_ground());
+        let origin = TileLocation::new(5, 5);
+
+        let visible = compute_field_of_view(&map_layer, origin, 3);
+        assert!(visible.contains(&origin));
+        assert!(visible.contains(&TileLocation::new(8, 5)));
+        assert!(visible.contains(&TileLocation::new(5, 2)));
+        assert!(visible.contains(&TileLocation::new(7, 7)));
+        assert!(!visible.contains(&TileLocation::new(9, 5)));
+        assert!(!visible.contains(&TileLocation::new(8, 8)));
+
+        let visible = compute_field_of_view(&map_layer, origin, 0);
+        assert_eq!(visible, vec![origin].into_iter().collect());
+    }
+
+    #[test]
+    fn test_field_of_view_is_blocked_by_walls() {
+        let mut map_layer = MapLayer::fill(11, 11, Tile::empty_ground());
+        (0..11).for_each(|j| map_layer.update(Tile::wall(), 7, j).unwrap());
+        let origin = TileLocation::new(5, 5);
+
+        let visible = compute_field_of_view(&map_layer, origin, 10);
+        assert!(visible.contains(&TileLocation::new(6, 5)));
+        assert!(visible.contains(&TileLocation::new(7, 5)));
+        assert!(!visible.contains(&TileLocation::new(8, 5)));
+        assert!(!visible.contains(&TileLocation::new(10, 0)));
+        assert!(visible.contains(&TileLocation::new(0, 0)));
+    }
+
+    #[test]
+    fn test_field_of_view_remembers_seen_tiles() {
+        let first = MapLocation::new(0, TileLocation::new(1, 1));
+        let second = MapLocation::new(0, TileLocation::new(2, 2));
+
+        let mut field_of_view = FieldOfView::default();
+        field_of_view.update(vec![first].into_iter().collect());
+        assert!(field_of_view.is_visible(first));
+        assert!(field_of_view.is_remembered(first));
+
+        field_of_view.update(vec![second].into_iter().collect());
+        assert!(!field_of_view.is_visible(first));
+        assert!(field_of_view.is_remembered(first));
+        assert!(field_of_view.is_visible(second));
+        assert!(!field_of_view.is_remembered(TileLocation::new(3, 3).into()));
+    }
+}
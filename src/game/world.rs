@@ -1,62 +1,85 @@
 use bevy::prelude::*;
 
 use super::{
-    enemy::{AICommand, Enemy},
-    map::{MapLocation, TileLocation},
+    map::{Map, MapLocation, TileLocation},
+    map_metadata::PlacementKind,
+    pathfinding::OccupiedLocations,
     player::Player,
-    resources::{GameState, NPCSpecs},
+    resources::{EnemySpecs, GameState, LoadedMap, NPCSpecs},
 };
+use crate::ui::LogState;
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            OnEnter(GameState::InitializingWorld),
-            initialize_player_system,
-        )
-        .add_systems(
-            OnEnter(GameState::InitializingWorld),
-            initialize_npcs_system,
-        )
-        .add_systems(
-            OnEnter(GameState::InitializingWorld),
-            initialize_enemies_system,
-        );
+        app.add_systems(OnEnter(GameState::InitializingWorld), populate_world_system);
     }
 }
 
 // Systems
 
-fn initialize_player_system(mut commands: Commands) {
-    let player = Player::new(
-        "Player".to_string(),
-        MapLocation::new(0, TileLocation::new(1, 1)),
-    );
-    player.spawn(&mut commands);
-
-    commands.insert_resource(NextState(Some(GameState::LoadingMap)));
-}
-
-fn initialize_npcs_system(mut commands: Commands, npc_specs: Res<NPCSpecs>) {
-    for (_name, npc) in npc_specs.0.as_vec().into_iter() {
-        npc.spawn(&mut commands.spawn_empty());
+// Spawns the player, NPCs, enemies and items where the loaded map's metadata places them.
+fn populate_world_system(
+    mut commands: Commands,
+    mut map: ResMut<LoadedMap>,
+    npc_specs: Res<NPCSpecs>,
+    enemy_specs: Res<EnemySpecs>,
+    mut log: ResMut<LogState>,
+) {
+    let metadata = map.0.get_metadata().clone();
+    let mut occupied = OccupiedLocations::empty();
+
+    // Every spawn is tried in turn, since the map can't be explored without a player.
+    let default_spawn = MapLocation::new(0, TileLocation::new(1, 1));
+    let location = metadata
+        .get_player_spawns()
+        .iter()
+        .copied()
+        .chain(std::iter::once(default_spawn))
+        .find_map(|spawn| claim_open_location(&map.0, spawn, &mut occupied))
+        .expect("The map should have an open tile for the player to spawn on.");
+    Player::new("Player".to_string(), location).spawn(&mut commands);
+
+    for placement in metadata.get_placements() {
+        let spec = placement.get_spec();
+        match placement.get_kind() {
+            PlacementKind::NPC => match npc_specs.0.get(spec) {
+                Some(npc) => {
+                    if let Some(location) =
+                        claim_open_location(&map.0, placement.get_location(), &mut occupied)
+                    {
+                        npc.spawn(&mut commands.spawn_empty(), location);
+                    }
+                }
+                None => log.log_string(&format!("No NPC named {}.", spec)),
+            },
+            PlacementKind::Enemy => match enemy_specs.0.get(spec) {
+                Some(enemy) => {
+                    if let Some(location) =
+                        claim_open_location(&map.0, placement.get_location(), &mut occupied)
+                    {
+                        enemy.spawn(&mut commands.spawn_empty(), location);
+                    }
+                }
+                None => log.log_string(&format!("No enemy named {}.", spec)),
+            },
+            PlacementKind::Item => match placement.get_object() {
+                Some(object) => {
+                    if map
+                        .0
+                        .place_object(placement.get_location(), object)
+                        .is_err()
+                    {
+                        log.log_string(&format!("Couldn't place {} off the map.", spec));
+                    }
+                }
+                None => log.log_string(&format!("No item named {}.", spec)),
+            },
+        }
     }
-}
 
-fn initialize_enemies_system(mut commands: Commands) {
-    let enemies = vec![Enemy::new(vec![
-        AICommand::Speak("Fear me!".to_string()),
-        AICommand::Wait(30),
-    ])];
-
-    // TODO Don't spawn all the enemies in the same place
-    for enemy in enemies {
-        enemy.spawn(
-            &mut commands.spawn_empty(),
-            MapLocation::new(0, TileLocation::new(9, 9)).into(),
-        );
-    }
+    commands.insert_resource(NextState(Some(GameState::Exploring)));
 }
 
 // End Systems
@@ -67,6 +90,23 @@ fn initialize_enemies_system(mut commands: Commands) {
 
 // Helper Functions
 
+// Characters can't share a tile or stand in a wall, so a placement that would moves to the nearest
+// open tile instead.
+fn claim_open_location(
+    map: &Map,
+    location: MapLocation,
+    occupied: &mut OccupiedLocations,
+) -> Option<MapLocation> {
+    let is_open = map.is_traversable(location).unwrap_or(false) && !occupied.contains(location);
+    let location = if is_open {
+        Some(location)
+    } else {
+        map.find_nearest_open_location(location, occupied)
+    }?;
+    occupied.insert(location);
+    Some(location)
+}
+
 // End Helper Functions
 
 // Helper Structs
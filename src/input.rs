@@ -9,11 +9,13 @@ use super::constants::*;
 use super::menu::MenuType;
 use crate::game::events::{
     CameraMovementEvent, CameraZoomEvent, ChooseDirectionEvent, DespawnBoundEntitiesEvent,
-    Direction, OpenMenuEvent, ProgressPromptEvent, StateChangeEvent, TryMoveEvent,
+    Direction, LayerChange, OpenMenuEvent, ProgressPromptEvent, StateChangeEvent, TileChangedEvent,
+    ToggleRenderModeEvent, TryChangeLayerEvent, TryMoveEvent,
 };
 use crate::game::events::{DamageEvent, MenuInputEvent};
 use crate::game::player::PlayerComponent;
 use crate::game::resources::GameState;
+use crate::map_editor::map_editor::{ExportAsciiMapEvent, ToggleLayerMenuEvent};
 use crate::menu::ExploringMenuType;
 
 pub struct InputPlugin;
@@ -26,18 +28,25 @@ impl Plugin for InputPlugin {
             .add_event::<CameraMovementEvent>()
             .add_event::<CameraZoomEvent>()
             .add_event::<TryMoveEvent>()
+            .add_event::<TryChangeLayerEvent>()
             .add_event::<DamageEvent>()
+            .add_event::<TileChangedEvent>()
             .add_event::<StateChangeEvent>()
             .add_event::<ChooseDirectionEvent>()
             .add_event::<ProgressPromptEvent>()
             .add_event::<DespawnBoundEntitiesEvent>()
+            .add_event::<ToggleRenderModeEvent>()
             .insert_resource(KeyHoldTimer::default())
             .add_systems(
                 First,
                 update_raycast_with_cursor.before(RaycastSystem::BuildRays::<MouseoverRaycastSet>),
             )
             //.add_systems(Update, print_intersections::<MouseoverRaycastSet>)
-            .add_systems(Update, input_system);
+            .add_systems(Update, input_system)
+            .add_systems(
+                Update,
+                map_editor_input_system.run_if(in_state(GameState::EditingMap)),
+            );
     }
 }
 
@@ -163,8 +172,10 @@ pub fn input_system(
     open_menu_event_writer: EventWriter<OpenMenuEvent>,
     menu_input_event_writer: EventWriter<MenuInputEvent>,
     movement_event_writer: EventWriter<TryMoveEvent>,
+    change_layer_event_writer: EventWriter<TryChangeLayerEvent>,
     choose_direction_event_writer: EventWriter<ChooseDirectionEvent>,
     progress_prompt_event_writer: EventWriter<ProgressPromptEvent>,
+    toggle_render_mode_event_writer: EventWriter<ToggleRenderModeEvent>,
     player_entity_query: Query<Entity, With<PlayerComponent>>,
 ) {
     if keyboard_input.get_just_released().count() > 0 {
@@ -179,6 +190,8 @@ pub fn input_system(
         state_change_event_writer.send(StateChangeEvent(GameState::EditingMapMenu));
     }
 
+    handle_toggle_render_mode(&keyboard_input, toggle_render_mode_event_writer);
+
     match state.get() {
         GameState::Exploring => {
             handle_camera_movement(
@@ -193,11 +206,33 @@ pub fn input_system(
                 &mut timer,
                 time.delta(),
                 movement_event_writer,
-                player_entity_query,
+                &player_entity_query,
+            );
+            handle_change_layer(
+                &keyboard_input,
+                change_layer_event_writer,
+                &player_entity_query,
+            );
+            handle_interact(&keyboard_input, &mut state_change_event_writer);
+            handle_toggle_overview(
+                &keyboard_input,
+                &mut state_change_event_writer,
+                GameState::ViewingMap,
             );
-            handle_interact(&keyboard_input, state_change_event_writer);
             handle_open_menu(&keyboard_input, open_menu_event_writer);
         }
+        GameState::ViewingMap => {
+            handle_toggle_overview(
+                &keyboard_input,
+                &mut state_change_event_writer,
+                GameState::Exploring,
+            );
+            handle_exit(
+                &keyboard_input,
+                state_change_event_writer,
+                GameState::Exploring,
+            );
+        }
         GameState::Interacting => {
             handle_progress_prompt(&keyboard_input, progress_prompt_event_writer);
             handle_exit(
@@ -223,11 +258,27 @@ pub fn input_system(
                 camera_movement_event_writer,
             );
             handle_camera_zoom(&keyboard_input, &mut timer, time.delta(), zoom_event_writer);
+            // For the layer menu, which is the only one shown while editing.
+            handle_menu_input(&keyboard_input, menu_input_event_writer);
         }
         _ => {}
     }
 }
 
+// Kept apart from input_system, which already takes as many parameters as a system can.
+fn map_editor_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut export_ascii_map_event_writer: EventWriter<ExportAsciiMapEvent>,
+    mut toggle_layer_menu_event_writer: EventWriter<ToggleLayerMenuEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::E) && keyboard_input.pressed(KeyCode::ControlLeft) {
+        export_ascii_map_event_writer.send(ExportAsciiMapEvent);
+    }
+    if keyboard_input.just_pressed(KeyCode::L) && keyboard_input.pressed(KeyCode::ControlLeft) {
+        toggle_layer_menu_event_writer.send(ToggleLayerMenuEvent);
+    }
+}
+
 // Update our `RaycastSource` with the current cursor position every frame.
 fn update_raycast_with_cursor(
     mut cursor: EventReader<CursorMoved>,
@@ -256,7 +307,7 @@ fn handle_movement(
     timer: &mut KeyHoldTimer,
     delta: Duration,
     mut movement_event_writer: EventWriter<TryMoveEvent>,
-    player_query: Query<Entity, With<PlayerComponent>>,
+    player_query: &Query<Entity, With<PlayerComponent>>,
 ) {
     let player_entity = player_query
         .get_single()
@@ -273,6 +324,34 @@ fn handle_movement(
     }
 }
 
+fn handle_change_layer(
+    keyboard_input: &Res<Input<KeyCode>>,
+    mut change_layer_event_writer: EventWriter<TryChangeLayerEvent>,
+    player_query: &Query<Entity, With<PlayerComponent>>,
+) {
+    if !keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        return;
+    }
+
+    let player_entity = player_query
+        .get_single()
+        .expect("Handle change layer should only be run once a player exists.");
+    if keyboard_input.just_pressed(KeyCode::Period) {
+        change_layer_event_writer.send(TryChangeLayerEvent(player_entity, LayerChange::Descend));
+    } else if keyboard_input.just_pressed(KeyCode::Comma) {
+        change_layer_event_writer.send(TryChangeLayerEvent(player_entity, LayerChange::Ascend));
+    }
+}
+
+fn handle_toggle_render_mode(
+    keyboard_input: &Res<Input<KeyCode>>,
+    mut toggle_render_mode_event_writer: EventWriter<ToggleRenderModeEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::T) && keyboard_input.pressed(KeyCode::ControlLeft) {
+        toggle_render_mode_event_writer.send(ToggleRenderModeEvent);
+    }
+}
+
 fn handle_camera_zoom(
     keyboard_input: &Res<Input<KeyCode>>,
     timer: &mut KeyHoldTimer,
@@ -324,13 +403,24 @@ fn handle_camera_movement(
 
 fn handle_interact(
     keyboard_input: &Res<Input<KeyCode>>,
-    mut state_change_event_writer: EventWriter<StateChangeEvent>,
+    state_change_event_writer: &mut EventWriter<StateChangeEvent>,
 ) {
     if keyboard_input.just_pressed(KeyCode::Space) {
         state_change_event_writer.send(StateChangeEvent(GameState::Interacting));
     }
 }
 
+// Tab opens the overview map from exploring and closes it again.
+fn handle_toggle_overview(
+    keyboard_input: &Res<Input<KeyCode>>,
+    state_change_event_writer: &mut EventWriter<StateChangeEvent>,
+    state: GameState,
+) {
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        state_change_event_writer.send(StateChangeEvent(state));
+    }
+}
+
 fn handle_open_menu(
     keyboard_input: &Res<Input<KeyCode>>,
     mut open_menu_event_writer: EventWriter<OpenMenuEvent>,
@@ -339,6 +429,10 @@ fn handle_open_menu(
         && keyboard_input.just_pressed(KeyCode::Key2)
     {
         open_menu_event_writer.send(OpenMenuEvent(ExploringMenuType::Character));
+    } else if keyboard_input.just_pressed(KeyCode::T)
+        && !keyboard_input.pressed(KeyCode::ControlLeft)
+    {
+        open_menu_event_writer.send(OpenMenuEvent(ExploringMenuType::Treatment));
     }
 }
 
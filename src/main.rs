@@ -12,10 +12,17 @@ use bevy::prelude::*;
 use bevy_mod_raycast::DefaultRaycastingPlugin;
 
 use assets::AssetsPlugin;
+use game::chunk::ChunkPlugin;
+use game::environment::EnvironmentPlugin;
 use game::events::EventsPlugin;
 use game::exploring::ExploringPlugin;
+use game::health::HealthPlugin;
 use game::interacting::InteractingPlugin;
+use game::light::LightPlugin;
+use game::minimap::MinimapPlugin;
 use game::pause::PausePlugin;
+use game::tileset::TilesetPlugin;
+use game::vision::VisionPlugin;
 use game::world::WorldPlugin;
 use input::InputPlugin;
 use menu::MenuPlugin;
@@ -40,6 +47,13 @@ fn main() {
         .add_plugins(PausePlugin)
         .add_plugins(WorldPlugin)
         .add_plugins(ExploringPlugin)
+        .add_plugins(VisionPlugin)
+        .add_plugins(ChunkPlugin)
+        .add_plugins(LightPlugin)
+        .add_plugins(EnvironmentPlugin)
+        .add_plugins(HealthPlugin)
+        .add_plugins(MinimapPlugin)
+        .add_plugins(TilesetPlugin)
         .add_plugins(InteractingPlugin)
         .add_plugins(MenuPlugin)
         .add_plugins(MapEditorPlugin)
@@ -0,0 +1,394 @@
+use std::collections::BTreeMap;
+
+use crate::game::map::{Map, MapLocation, Tile, TileLocation};
+use crate::game::map_metadata::MapMetadata;
+
+use super::map_editor::Transaction;
+
+// Structs
+
+// The outcome of merging two edited copies of the same map.  Applying the transactions to the base
+// map gives the merged map.  Where both copies changed the same thing differently, our change is
+// kept and the clash is reported as a conflict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapMerge {
+    transactions: Vec<Transaction>,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl MapMerge {
+    pub fn get_transactions(&self) -> &Vec<Transaction> {
+        &self.transactions
+    }
+
+    pub fn get_conflicts(&self) -> &Vec<MergeConflict> {
+        &self.conflicts
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeConflict {
+    Tile {
+        location: MapLocation,
+        base: Tile,
+        ours: Tile,
+        theirs: Tile,
+    },
+    Name {
+        base: String,
+        ours: String,
+        theirs: String,
+    },
+}
+
+impl MergeConflict {
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::Tile {
+                location,
+                ours,
+                theirs,
+                ..
+            } => {
+                let tile_location = location.get_tile_location();
+                format!(
+                    "Layer {} ({}, {}) was changed to {} and to {}",
+                    location.get_map_layer(),
+                    tile_location.x(),
+                    tile_location.y(),
+                    ours.get_description(),
+                    theirs.get_description()
+                )
+            }
+            Self::Name { ours, theirs, .. } => {
+                format!("The map was renamed to {} and to {}", ours, theirs)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapDiffError {
+    DifferentLayerCount(usize, usize),
+    DifferentLayerSize(usize),
+}
+
+impl MapDiffError {
+    pub fn to_string(&self) -> String {
+        match self {
+            Self::DifferentLayerCount(before, after) => {
+                format!("The maps have {} and {} layers.", before, after)
+            }
+            Self::DifferentLayerSize(layer) => {
+                format!("Layer {} is a different size in each map.", layer)
+            }
+        }
+    }
+}
+
+// End Structs
+
+// Helper Functions
+
+// Changed tiles are keyed by layer and then tile location so that they come out in a stable order.
+type TileKey = (usize, TileLocation);
+
+// The transactions that turn before into after: an update for every tile that differs, in layer
+// then column order, and the new metadata if it changed.  Only maps of the same shape can be
+// diffed.
+pub fn diff_maps(before: &Map, after: &Map) -> Result<Vec<Transaction>, MapDiffError> {
+    let mut transactions = get_changed_tiles(before, after)?
+        .into_iter()
+        .map(|(key, tile)| Transaction::Update(tile, get_location(key)))
+        .collect::<Vec<_>>();
+    if before.get_metadata() != after.get_metadata() {
+        transactions.push(Transaction::Metadata(after.get_metadata().clone()));
+    }
+    Ok(transactions)
+}
+
+// Merges our and their edits of base.  A tile or the map's name only conflicts when both sides
+// changed it to different things.  Spawns and placements are merged as sets, so each side's
+// additions and removals are all kept.
+pub fn merge_maps(base: &Map, ours: &Map, theirs: &Map) -> Result<MapMerge, MapDiffError> {
+    let our_tiles = get_changed_tiles(base, ours)?;
+    let mut their_tiles = get_changed_tiles(base, theirs)?;
+    let mut transactions = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for (key, our_tile) in our_tiles {
+        let location = get_location(key);
+        if let Some(their_tile) = their_tiles.remove(&key) {
+            if their_tile != our_tile {
+                conflicts.push(MergeConflict::Tile {
+                    location,
+                    base: get_tile(base, location),
+                    ours: our_tile.clone(),
+                    theirs: their_tile,
+                });
+            }
+        }
+        transactions.push(Transaction::Update(our_tile, location));
+    }
+    transactions.extend(
+        their_tiles
+            .into_iter()
+            .map(|(key, tile)| Transaction::Update(tile, get_location(key))),
+    );
+    transactions.sort_by_key(|transaction| match transaction {
+        Transaction::Update(_tile, location) => {
+            Some((location.get_map_layer(), location.get_tile_location()))
+        }
+        _ => None,
+    });
+
+    let (base_metadata, our_metadata, their_metadata) = (
+        base.get_metadata(),
+        ours.get_metadata(),
+        theirs.get_metadata(),
+    );
+    let (base_name, our_name, their_name) = (
+        base_metadata.get_name(),
+        our_metadata.get_name(),
+        their_metadata.get_name(),
+    );
+    let name = if our_name != base_name {
+        if their_name != base_name && their_name != our_name {
+            conflicts.push(MergeConflict::Name {
+                base: base_name.to_string(),
+                ours: our_name.to_string(),
+                theirs: their_name.to_string(),
+            });
+        }
+        our_name
+    } else {
+        their_name
+    };
+    let mut metadata = MapMetadata::new(name);
+    merge_sets(
+        base_metadata.get_player_spawns(),
+        our_metadata.get_player_spawns(),
+        their_metadata.get_player_spawns(),
+    )
+    .into_iter()
+    .for_each(|spawn| metadata.add_player_spawn(spawn));
+    merge_sets(
+        base_metadata.get_placements(),
+        our_metadata.get_placements(),
+        their_metadata.get_placements(),
+    )
+    .into_iter()
+    .for_each(|placement| metadata.add_placement(placement));
+    if &metadata != base_metadata {
+        transactions.push(Transaction::Metadata(metadata));
+    }
+
+    Ok(MapMerge {
+        transactions,
+        conflicts,
+    })
+}
+
+fn get_changed_tiles(before: &Map, after: &Map) -> Result<BTreeMap<TileKey, Tile>, MapDiffError> {
+    let (before_layers, after_layers) = (before.get_layers(), after.get_layers());
+    if before_layers.len() != after_layers.len() {
+        return Err(MapDiffError::DifferentLayerCount(
+            before_layers.len(),
+            after_layers.len(),
+        ));
+    }
+
+    let mut changed = BTreeMap::new();
+    for (index, (before_layer, after_layer)) in
+        before_layers.iter().zip(after_layers.iter()).enumerate()
+    {
+        if before_layer.width() != after_layer.width()
+            || before_layer.height() != after_layer.height()
+        {
+            return Err(MapDiffError::DifferentLayerSize(index));
+        }
+        for ((tile_location, before_tile), (_, after_tile)) in before_layer
+            .as_location_and_tile_vector()
+            .into_iter()
+            .zip(after_layer.as_location_and_tile_vector())
+        {
+            if before_tile != after_tile {
+                changed.insert((index, tile_location), after_tile.clone());
+            }
+        }
+    }
+    Ok(changed)
+}
+
+// Keeps everything either side added and drops everything either side removed, in our order and
+// then theirs.
+fn merge_sets<T: Clone + PartialEq>(base: &[T], ours: &[T], theirs: &[T]) -> Vec<T> {
+    let removed =
+        |item: &T| base.contains(item) && (!ours.contains(item) || !theirs.contains(item));
+    let mut merged: Vec<T> = Vec::new();
+    for item in ours.iter().chain(theirs.iter()) {
+        if !removed(item) && !merged.contains(item) {
+            merged.push(item.clone());
+        }
+    }
+    merged
+}
+
+fn get_location((map_layer, tile_location): TileKey) -> MapLocation {
+    MapLocation::new(map_layer, tile_location)
+}
+
+fn get_tile(map: &Map, location: MapLocation) -> Tile {
+    map.get(location)
+        .expect("Both maps have the same shape as the base.")
+        .clone()
+}
+
+// End Helper Functions
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::{MapLayer, ObjectTile};
+    use crate::game::map_metadata::{Placement, PlacementKind};
+    use crate::map_editor::map_editor::TransactionStore;
+
+    fn get_base_map() -> Map {
+        Map::new(vec![
+            MapLayer::fill(4, 3, Tile::empty_ground()),
+            MapLayer::fill(2, 2, Tile::wall()),
+        ])
+    }
+
+    fn apply(base: &Map, transactions: &Vec<Transaction>) -> Map {
+        let mut store = TransactionStore::from_snapshot(base.clone());
+        transactions
+            .iter()
+            .for_each(|transaction| store.add(transaction.clone()));
+        store.compile().unwrap()
+    }
+
+    #[test]
+    fn test_diff_maps_replays_onto_the_original() {
+        let base = get_base_map();
+        let mut edited = base.clone();
+        let wall_location = MapLocation::new(0, TileLocation::new(2, 1));
+        let key_location = MapLocation::new(1, TileLocation::new(0, 1));
+        edited.update(Tile::wall(), wall_location).unwrap();
+        edited
+            .update(Tile::object(ObjectTile::Key), key_location)
+            .unwrap();
+        edited.set_metadata(MapMetadata::new("Edited"));
+
+        let transactions = diff_maps(&base, &edited).unwrap();
+        assert_eq!(
+            transactions,
+            vec![
+                Transaction::Update(Tile::wall(), wall_location),
+                Transaction::Update(Tile::object(ObjectTile::Key), key_location),
+                Transaction::Metadata(MapMetadata::new("Edited")),
+            ]
+        );
+        assert_eq!(apply(&base, &transactions), edited);
+        assert_eq!(diff_maps(&edited, &edited), Ok(vec![]));
+
+        let smaller = Map::new(vec![MapLayer::fill(4, 3, Tile::empty_ground())]);
+        assert_eq!(
+            diff_maps(&base, &smaller),
+            Err(MapDiffError::DifferentLayerCount(2, 1))
+        );
+        let resized = Map::new(vec![
+            MapLayer::fill(4, 3, Tile::empty_ground()),
+            MapLayer::fill(3, 2, Tile::wall()),
+        ]);
+        assert_eq!(
+            diff_maps(&base, &resized),
+            Err(MapDiffError::DifferentLayerSize(1))
+        );
+    }
+
+    #[test]
+    fn test_merge_maps_combines_edits_and_reports_conflicts() {
+        let base = get_base_map();
+        let only_ours = MapLocation::new(0, TileLocation::new(0, 0));
+        let only_theirs = MapLocation::new(0, TileLocation::new(3, 2));
+        let agreed = MapLocation::new(1, TileLocation::new(1, 1));
+        let contested = MapLocation::new(0, TileLocation::new(1, 1));
+
+        let mut ours = base.clone();
+        ours.update(Tile::wall(), only_ours).unwrap();
+        ours.update(Tile::empty_ground(), agreed).unwrap();
+        ours.update(Tile::wall(), contested).unwrap();
+        let mut theirs = base.clone();
+        theirs
+            .update(Tile::object(ObjectTile::Item), only_theirs)
+            .unwrap();
+        theirs.update(Tile::empty_ground(), agreed).unwrap();
+        theirs
+            .update(Tile::object(ObjectTile::Key), contested)
+            .unwrap();
+        theirs.set_metadata(MapMetadata::new("Theirs"));
+
+        let merge = merge_maps(&base, &ours, &theirs).unwrap();
+        assert_eq!(
+            merge.get_conflicts(),
+            &vec![MergeConflict::Tile {
+                location: contested,
+                base: Tile::empty_ground(),
+                ours: Tile::wall(),
+                theirs: Tile::object(ObjectTile::Key),
+            }]
+        );
+
+        let mut expected = ours.clone();
+        expected
+            .update(Tile::object(ObjectTile::Item), only_theirs)
+            .unwrap();
+        expected.set_metadata(MapMetadata::new("Theirs"));
+        assert_eq!(apply(&base, merge.get_transactions()), expected);
+
+        ours.set_metadata(MapMetadata::new("Ours"));
+        let merge = merge_maps(&base, &ours, &theirs).unwrap();
+        assert_eq!(merge.get_conflicts().len(), 2);
+        assert!(!merge_maps(&base, &ours, &base).unwrap().has_conflicts());
+    }
+
+    #[test]
+    fn test_merge_maps_merges_metadata_by_field() {
+        let kept = MapLocation::new(0, TileLocation::new(0, 0));
+        let dropped = MapLocation::new(0, TileLocation::new(1, 0));
+        let added = MapLocation::new(0, TileLocation::new(2, 0));
+        let mut base = get_base_map();
+        let mut base_metadata = MapMetadata::new("Base");
+        base_metadata.add_player_spawn(kept);
+        base_metadata.add_player_spawn(dropped);
+        base.set_metadata(base_metadata.clone());
+
+        // We rename the map and drop a spawn while they add a spawn and a placement.
+        let mut ours = base.clone();
+        let mut our_metadata = MapMetadata::new("Ours");
+        our_metadata.add_player_spawn(kept);
+        ours.set_metadata(our_metadata);
+        let mut theirs = base.clone();
+        let mut their_metadata = base_metadata;
+        their_metadata.add_player_spawn(added);
+        let placement = Placement::new(PlacementKind::Enemy, "Example Enemy", added);
+        their_metadata.add_placement(placement.clone());
+        theirs.set_metadata(their_metadata);
+
+        let merge = merge_maps(&base, &ours, &theirs).unwrap();
+        assert!(!merge.has_conflicts());
+        let mut expected = MapMetadata::new("Ours");
+        expected.add_player_spawn(kept);
+        expected.add_player_spawn(added);
+        expected.add_placement(placement);
+        assert_eq!(
+            merge.get_transactions(),
+            &vec![Transaction::Metadata(expected)]
+        );
+    }
+}
@@ -3,16 +3,25 @@ use std::ffi::OsStr;
 use std::fs;
 use std::path::Path;
 use std::result::Result;
+use std::str::FromStr;
 
 use bevy::prelude::*;
 use bevy_egui::EguiContexts;
+use rand::Rng;
 
 use crate::assets::FileSystem;
 use crate::constants::*;
+use crate::game::ascii_map::{load_ascii_maps, save_ascii_map};
+use crate::game::chunk::RenderedTileGrid;
 use crate::game::events::{DespawnBoundEntitiesEvent, MenuInputEvent};
+use crate::game::generation::generator::{MapGenerator, MapGeneratorType};
 use crate::game::map::MapLocation;
-use crate::game::map::{Map, MapLayer, Tile, TileGrid};
-use crate::game::resources::{GameState, LoadedFont};
+use crate::game::map::{Map, MapError, MapLayer, MapTileComponent, Tile, TileGrid, TileLocation};
+use crate::game::map_metadata::MapMetadata;
+use crate::game::map_validation::{validate_map, MapValidationReport};
+use crate::game::particle::{ParticleComponent, ParticleEmitterComponent};
+use crate::game::resources::{GameState, LoadedFont, RngResource};
+use crate::game::tileset::RenderSettings;
 use crate::menu::{MenuType, MenuUIState};
 use crate::ui::ToastMessageEvent;
 
@@ -21,9 +30,12 @@ use crate::ui::ToastMessageEvent;
 //  1. Load existing or new?
 //  2. (If new) pick a name
 //  3. (If new) pick a size
-//  4. Use vim-like keybindings to move around, insert, copy, etc.
+//  4. (If new) pick a generator
+//  5. Use vim-like keybindings to move around, insert, copy, etc.
+//  6. Ctrl+L toggles the layer menu for switching, resizing, cropping, shifting and reordering layers
 //
 //  Everything should save on any change
+//  Text maps are only written by Ctrl+E and only read by "Import ASCII Map"
 //  But, within the current session, undo and redo should be possible.
 //  Recording macros should be possible
 //  Block mode should create rectangular blocks rather than be line-based etc.
@@ -34,6 +46,8 @@ impl Plugin for MapEditorPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<MapEditorSwitchMenuEvent>()
             .add_event::<AddTransactionEvent>()
+            .add_event::<ExportAsciiMapEvent>()
+            .add_event::<ToggleLayerMenuEvent>()
             .add_systems(
                 OnEnter(GameState::EditingMapMenu),
                 initialize_map_editor_menu_system,
@@ -50,12 +64,40 @@ impl Plugin for MapEditorPlugin {
                 ),
             )
             .add_systems(OnEnter(GameState::EditingMap), spawn_map_system)
+            .add_systems(
+                Update,
+                spawn_map_system.run_if(
+                    in_state(GameState::EditingMap).and_then(
+                        resource_changed::<RenderSettings>()
+                            .or_else(resource_changed::<MapEditorEditingUIState>()),
+                    ),
+                ),
+            )
             .add_systems(
                 Update,
                 add_transaction_and_save_system.run_if(
                     in_state(GameState::EditingMap).and_then(on_event::<AddTransactionEvent>()),
                 ),
-            );
+            )
+            .add_systems(
+                Update,
+                export_ascii_map_system.run_if(
+                    in_state(GameState::EditingMap).and_then(on_event::<ExportAsciiMapEvent>()),
+                ),
+            )
+            .add_systems(
+                Update,
+                toggle_layer_menu_system.run_if(
+                    in_state(GameState::EditingMap).and_then(on_event::<ToggleLayerMenuEvent>()),
+                ),
+            )
+            .add_systems(
+                Update,
+                render_layer_menu_system.run_if(
+                    in_state(GameState::EditingMap).and_then(resource_exists::<LayerMenuToShow>()),
+                ),
+            )
+            .add_systems(OnExit(GameState::EditingMap), tear_down_layer_menu_system);
     }
 }
 
@@ -85,6 +127,7 @@ impl MapEditorMenuUIState {
             MapEditorMenuType::NewOrLoadMenu => MenuUIState::new(MenuType::SelectFinite(vec![
                 "New Map".to_string(),
                 "Load Map".to_string(),
+                "Import ASCII Map".to_string(),
             ])),
             MapEditorMenuType::NewMapNameMenu => {
                 MenuUIState::new(MenuType::TextInput("Map Name:".to_string()))
@@ -92,10 +135,21 @@ impl MapEditorMenuUIState {
             MapEditorMenuType::NewMapSizeMenu(_buffer) => {
                 MenuUIState::new(MenuType::TextInput("Map Size:".to_string()))
             }
+            MapEditorMenuType::NewMapGeneratorMenu(_buffer, _size) => {
+                MenuUIState::new(MenuType::SelectFinite(
+                    MapGeneratorType::all()
+                        .iter()
+                        .map(|generator_type| generator_type.to_string())
+                        .collect(),
+                ))
+            }
             MapEditorMenuType::LoadMapMenu(maps) => MenuUIState::new(MenuType::SearchAndSelect(
                 "Load Map".to_string(),
                 maps.to_vec(),
             )),
+            MapEditorMenuType::ImportAsciiMapMenu(maps) => MenuUIState::new(
+                MenuType::SearchAndSelect("Import ASCII Map".to_string(), maps.to_vec()),
+            ),
         };
         Self::new(menu_type, menu_state)
     }
@@ -115,7 +169,53 @@ pub enum MapEditorMenuType {
     NewOrLoadMenu,
     NewMapNameMenu,
     NewMapSizeMenu(String),
+    NewMapGeneratorMenu(String, usize),
     LoadMapMenu(Vec<String>),
+    // Importing replaces the JSON map of the same name once the editor saves.
+    ImportAsciiMapMenu(Vec<String>),
+}
+
+// Drawn over the map while editing, rather than switching to the editor's menu state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Resource)]
+pub struct LayerMenuToShow(MenuUIState, LayerMenuType);
+
+impl LayerMenuToShow {
+    pub fn from_type(menu_type: LayerMenuType) -> Self {
+        let menu_state = match menu_type {
+            LayerMenuType::Main => MenuUIState::new(MenuType::SelectFinite(vec![
+                "Next Layer".to_string(),
+                "Previous Layer".to_string(),
+                "Resize Layer".to_string(),
+                "Crop Layer".to_string(),
+                "Shift Layer".to_string(),
+                "Insert Layer".to_string(),
+                "Remove Layer".to_string(),
+                "Move Layer".to_string(),
+            ])),
+            LayerMenuType::Resize => {
+                MenuUIState::new(MenuType::TextInput("New width and height:".to_string()))
+            }
+            LayerMenuType::Crop => MenuUIState::new(MenuType::TextInput(
+                "Left column, bottom row, width and height to keep:".to_string(),
+            )),
+            LayerMenuType::Shift => MenuUIState::new(MenuType::TextInput(
+                "Columns and rows to shift by:".to_string(),
+            )),
+            LayerMenuType::Move => {
+                MenuUIState::new(MenuType::TextInput("Move to layer:".to_string()))
+            }
+        };
+        Self(menu_state, menu_type)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LayerMenuType {
+    Main,
+    Resize,
+    Crop,
+    Shift,
+    Move,
 }
 
 #[derive(Debug, Resource)]
@@ -124,6 +224,8 @@ pub struct MapEditorEditingUIState {
     store: TransactionStore,
     current_layer: usize,
     mode: EditingMode,
+    // The report from the last save, so that unchanged problems aren't shown on every edit.
+    validation_report: Option<MapValidationReport>,
 }
 
 impl MapEditorEditingUIState {
@@ -139,6 +241,7 @@ impl MapEditorEditingUIState {
             store,
             current_layer,
             mode,
+            validation_report: None,
         })
     }
 
@@ -168,6 +271,10 @@ pub enum EditingMode {
 
 #[derive(Debug, Resource)]
 pub struct LoadedMaps(HashMap<String, Map>);
+
+// Text maps are only read when importing, so that the JSON maps stay the one source of truth.
+#[derive(Debug, Resource)]
+pub struct LoadedAsciiMaps(HashMap<String, Map>);
 // End Resources
 
 // Events
@@ -177,14 +284,23 @@ pub struct MapEditorSwitchMenuEvent(MapEditorMenuType);
 #[derive(Debug, PartialEq, Eq, Event)]
 pub struct AddTransactionEvent(Option<Transaction>);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct ExportAsciiMapEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct ToggleLayerMenuEvent;
+
 // End Events
 
 // Systems
-fn initialize_map_editor_menu_system(mut commands: Commands) {
+fn initialize_map_editor_menu_system(
+    mut commands: Commands,
+    mut toast_message_event_writer: EventWriter<ToastMessageEvent>,
+) {
     commands.insert_resource(MapEditorMenuUIState::default());
 
     let filesystem = FileSystem::<Map>::new_directory(MAP_DIRECTORY);
-    let maps = filesystem
+    let mut maps: HashMap<String, Map> = filesystem
         .load_all()
         .expect("Error parsing maps.")
         .into_iter()
@@ -198,8 +314,25 @@ fn initialize_map_editor_menu_system(mut commands: Commands) {
             )
         })
         .collect();
+
+    let mut ascii_maps = HashMap::new();
+    for (name, ascii_map) in load_ascii_maps(ASCII_MAP_DIRECTORY) {
+        match ascii_map {
+            Ok(map) => {
+                ascii_maps.insert(name, map);
+            }
+            Err(e) => toast_message_event_writer.send(ToastMessageEvent(format!(
+                "{}.{}: {}",
+                name,
+                ASCII_MAP_EXTENSION,
+                e.to_string()
+            ))),
+        }
+    }
+
     commands.insert_resource(filesystem);
     commands.insert_resource(LoadedMaps(maps));
+    commands.insert_resource(LoadedAsciiMaps(ascii_maps));
 }
 
 fn switch_menu_system(
@@ -215,6 +348,8 @@ fn render_map_editor_menu_system(
     mut commands: Commands,
     mut contexts: EguiContexts,
     loaded_maps: Res<LoadedMaps>,
+    loaded_ascii_maps: Res<LoadedAsciiMaps>,
+    rng: Res<RngResource>,
     mut input_event_reader: EventReader<MenuInputEvent>,
     mut switch_menu_event_writer: EventWriter<MapEditorSwitchMenuEvent>,
     mut toast_message_event_writer: EventWriter<ToastMessageEvent>,
@@ -232,7 +367,12 @@ fn render_map_editor_menu_system(
                 Some("Load Map") => switch_menu_event_writer.send(MapEditorSwitchMenuEvent(
                     MapEditorMenuType::LoadMapMenu(maps),
                 )),
-                Some(_) => panic!("There are only two options."),
+                Some("Import ASCII Map") => switch_menu_event_writer.send(
+                    MapEditorSwitchMenuEvent(MapEditorMenuType::ImportAsciiMapMenu(
+                        loaded_ascii_maps.0.keys().cloned().collect(),
+                    )),
+                ),
+                Some(_) => panic!("There are only three options."),
                 None => {}
             };
         }
@@ -250,25 +390,9 @@ fn render_map_editor_menu_system(
         MapEditorMenuType::NewMapSizeMenu(map_name) => match response {
             Some(map_size) => match map_size.parse() {
                 Ok(size) => {
-                    let filename = format!("{}.json", map_name);
-                    let map = MapLayer::fill(size, size, Tile::empty_ground()).into();
-                    let current_layer = 0;
-                    match MapEditorEditingUIState::new(
-                        filename,
-                        TransactionStore::from_snapshot(map),
-                        current_layer,
-                        EditingMode::Normal,
-                    ) {
-                        Ok(ui_state) => {
-                            despawn_event_writer
-                                .send(DespawnBoundEntitiesEvent(GameState::Exploring));
-                            add_transaction_event_writer.send(AddTransactionEvent(None));
-                            commands.insert_resource(ui_state);
-                            commands.insert_resource(NextState(Some(GameState::EditingMap)));
-                        }
-                        Err(e) => toast_message_event_writer
-                            .send(ToastMessageEvent(format!("TransactionStoreError: {:?}", e))),
-                    };
+                    switch_menu_event_writer.send(MapEditorSwitchMenuEvent(
+                        MapEditorMenuType::NewMapGeneratorMenu(map_name.clone(), size),
+                    ));
                 }
                 Err(e) => {
                     toast_message_event_writer.send(ToastMessageEvent(e.to_string()));
@@ -276,6 +400,52 @@ fn render_map_editor_menu_system(
             },
             None => {}
         },
+        MapEditorMenuType::NewMapGeneratorMenu(map_name, size) => match response {
+            Some(generator_name) => {
+                let generator_type = MapGeneratorType::from_string(&generator_name)
+                    .expect("The only options are the generator types.");
+                // Generators that learn from maps are trained on everything saved so far.
+                let samples = loaded_maps
+                    .0
+                    .values()
+                    .map(|map| map.get_layers().clone())
+                    .flatten()
+                    .collect::<Vec<_>>();
+                let seed: u64 = rng
+                    .0
+                    .write()
+                    .expect("If a thread somewhere panicked, we should panic.")
+                    .gen();
+                let map_layer = generator_type
+                    .build(*size, *size, &samples)
+                    .and_then(|generator| generator.generate_layer(seed));
+                match map_layer {
+                    Ok(map_layer) => {
+                        let filename = format!("{}.json", map_name);
+                        let current_layer = 0;
+                        match MapEditorEditingUIState::new(
+                            filename,
+                            TransactionStore::from_snapshot(map_layer.into()),
+                            current_layer,
+                            EditingMode::Normal,
+                        ) {
+                            Ok(ui_state) => {
+                                despawn_event_writer
+                                    .send(DespawnBoundEntitiesEvent(GameState::Exploring));
+                                add_transaction_event_writer.send(AddTransactionEvent(None));
+                                commands.insert_resource(ui_state);
+                                commands.insert_resource(NextState(Some(GameState::EditingMap)));
+                            }
+                            Err(e) => toast_message_event_writer
+                                .send(ToastMessageEvent(format!("TransactionStoreError: {:?}", e))),
+                        };
+                    }
+                    Err(e) => toast_message_event_writer
+                        .send(ToastMessageEvent(format!("GenerationError: {:?}", e))),
+                }
+            }
+            None => {}
+        },
         MapEditorMenuType::LoadMapMenu(_maps) => match response {
             Some(map_name) => {
                 let filename = format!("{}.json", map_name);
@@ -302,6 +472,32 @@ fn render_map_editor_menu_system(
             }
             None => {}
         },
+        MapEditorMenuType::ImportAsciiMapMenu(_maps) => match response {
+            Some(map_name) => {
+                let filename = format!("{}.json", map_name);
+                let map = loaded_ascii_maps
+                    .0
+                    .get(&map_name)
+                    .expect("The only options are what we loaded.");
+                let current_layer = 0;
+                match MapEditorEditingUIState::new(
+                    filename,
+                    TransactionStore::from_snapshot(map.clone()),
+                    current_layer,
+                    EditingMode::Normal,
+                ) {
+                    Ok(ui_state) => {
+                        despawn_event_writer.send(DespawnBoundEntitiesEvent(GameState::Exploring));
+                        add_transaction_event_writer.send(AddTransactionEvent(None));
+                        commands.insert_resource(ui_state);
+                        commands.insert_resource(NextState(Some(GameState::EditingMap)));
+                    }
+                    Err(e) => toast_message_event_writer
+                        .send(ToastMessageEvent(format!("TransactionStoreError: {:?}", e))),
+                };
+            }
+            None => {}
+        },
     }
 }
 
@@ -309,8 +505,21 @@ fn spawn_map_system(
     mut commands: Commands,
     ui_state: Res<MapEditorEditingUIState>,
     font: Res<LoadedFont>,
+    render_settings: Res<RenderSettings>,
+    previous_map_query: Query<
+        Entity,
+        Or<(
+            With<MapTileComponent>,
+            With<ParticleEmitterComponent>,
+            With<ParticleComponent>,
+        )>,
+    >,
     mut toast_message_event_writer: EventWriter<ToastMessageEvent>,
 ) {
+    for entity in previous_map_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
     let map_layer = match ui_state.get_current_map_layer() {
         Err(e) => {
             toast_message_event_writer
@@ -319,8 +528,10 @@ fn spawn_map_system(
         }
         Ok(map_layer) => map_layer,
     };
-    let tile_grid = TileGrid::from_map_layer(map_layer.clone());
-    tile_grid.render(&mut commands, font.0.clone(), GameState::EditingMap);
+    commands.insert_resource(RenderedTileGrid::new(
+        TileGrid::from_map_layer(&map_layer, &render_settings),
+        GameState::EditingMap,
+    ));
 
     map_layer
         .as_location_and_tile_vector()
@@ -330,6 +541,7 @@ fn spawn_map_system(
                 particle_spec.render(
                     &mut commands,
                     font.0.clone(),
+                    &render_settings,
                     GameState::EditingMap,
                     location,
                 );
@@ -348,6 +560,18 @@ fn add_transaction_and_save_system(
 
         if maybe_transaction.is_some() {
             ui_state.store.add(maybe_transaction.clone().unwrap());
+            // Transactions that can't be applied are dropped so they don't break the undo history.
+            if let Err(e) = ui_state.store.compile() {
+                toast_message_event_writer.send(ToastMessageEvent(format!(
+                    "Couldn't apply transaction: {:?}",
+                    e
+                )));
+                ui_state
+                    .store
+                    .undo()
+                    .expect("We just added the transaction.");
+                continue;
+            }
         }
 
         match &ui_state.store.compile() {
@@ -357,25 +581,190 @@ fn add_transaction_and_save_system(
                     e
                 )));
             }
-            Ok(map) => match filesystem.save(&ui_state.get_filename(), map.clone()) {
-                Err(e) => {
-                    toast_message_event_writer
-                        .send(ToastMessageEvent(format!("Error saving map: {:?}", e)));
+            Ok(map) => {
+                // Removing a layer can leave the one being edited past the end of the map.
+                let last_layer = map.get_layers().len().saturating_sub(1);
+                if ui_state.current_layer > last_layer {
+                    ui_state.current_layer = last_layer;
+                }
+
+                match filesystem.save(&ui_state.get_filename(), map.clone()) {
+                    Err(e) => {
+                        toast_message_event_writer
+                            .send(ToastMessageEvent(format!("Error saving map: {:?}", e)));
+                    }
+                    Ok(()) => {
+                        // Do nothing
+                    }
                 }
-                Ok(()) => {
-                    // Do nothing
+
+                let report = validate_map(map);
+                if ui_state.validation_report.as_ref() != Some(&report) {
+                    if !report.is_empty() {
+                        toast_message_event_writer.send(ToastMessageEvent(report.to_string()));
+                    }
+                    ui_state.validation_report = Some(report);
                 }
+            }
+        }
+    }
+}
+
+fn export_ascii_map_system(
+    ui_state: Res<MapEditorEditingUIState>,
+    mut event_reader: EventReader<ExportAsciiMapEvent>,
+    mut toast_message_event_writer: EventWriter<ToastMessageEvent>,
+) {
+    for _event in event_reader.iter() {
+        let map = match ui_state.get_map() {
+            Ok(map) => map,
+            Err(e) => {
+                toast_message_event_writer.send(ToastMessageEvent(format!(
+                    "Error compiling map from transactions: {:?}",
+                    e
+                )));
+                continue;
+            }
+        };
+        let filename = ui_state.get_filename();
+        let map_name = Path::new(&filename)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .expect("Filenames come from sanitized map names.");
+        let message = match save_ascii_map(ASCII_MAP_DIRECTORY, map_name, &map) {
+            Ok(()) => format!("Exported {}.{}", map_name, ASCII_MAP_EXTENSION),
+            Err(e) => e.to_string(),
+        };
+        toast_message_event_writer.send(ToastMessageEvent(message));
+    }
+}
+
+fn toggle_layer_menu_system(
+    mut commands: Commands,
+    layer_menu: Option<Res<LayerMenuToShow>>,
+    mut event_reader: EventReader<ToggleLayerMenuEvent>,
+) {
+    event_reader.clear();
+    if layer_menu.is_some() {
+        commands.remove_resource::<LayerMenuToShow>();
+    } else {
+        commands.insert_resource(LayerMenuToShow::from_type(LayerMenuType::Main));
+    }
+}
+
+// Layer transactions apply to the layer being edited.  The menu stays open on bad input so it can
+// be corrected.
+fn render_layer_menu_system(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut input_event_reader: EventReader<MenuInputEvent>,
+    mut layer_menu: ResMut<LayerMenuToShow>,
+    mut ui_state: ResMut<MapEditorEditingUIState>,
+    mut add_transaction_event_writer: EventWriter<AddTransactionEvent>,
+    mut toast_message_event_writer: EventWriter<ToastMessageEvent>,
+) {
+    let response = match layer_menu.0.render(&mut contexts, &mut input_event_reader) {
+        Some(response) => response,
+        None => return,
+    };
+    let current_layer = ui_state.current_layer;
+    let transaction = match layer_menu.1 {
+        LayerMenuType::Main => match response.as_str() {
+            "Next Layer" | "Previous Layer" => match ui_state.get_map() {
+                Ok(map) => {
+                    let last_layer = map.get_layers().len().saturating_sub(1);
+                    ui_state.current_layer = if response == "Next Layer" {
+                        (current_layer + 1).min(last_layer)
+                    } else {
+                        current_layer.saturating_sub(1)
+                    };
+                    commands.remove_resource::<LayerMenuToShow>();
+                    return;
+                }
+                Err(e) => Err(format!("TransactionStoreError: {:?}", e)),
             },
+            "Resize Layer" => {
+                *layer_menu = LayerMenuToShow::from_type(LayerMenuType::Resize);
+                return;
+            }
+            "Crop Layer" => {
+                *layer_menu = LayerMenuToShow::from_type(LayerMenuType::Crop);
+                return;
+            }
+            "Shift Layer" => {
+                *layer_menu = LayerMenuToShow::from_type(LayerMenuType::Shift);
+                return;
+            }
+            "Move Layer" => {
+                *layer_menu = LayerMenuToShow::from_type(LayerMenuType::Move);
+                return;
+            }
+            // New layers start as empty ground the size of the one being edited.
+            "Insert Layer" => ui_state
+                .get_current_map_layer()
+                .map(|map_layer| {
+                    Transaction::InsertLayer(
+                        current_layer,
+                        MapLayer::fill(map_layer.width(), map_layer.height(), Tile::empty_ground()),
+                    )
+                })
+                .map_err(|e| format!("TransactionStoreError: {:?}", e)),
+            "Remove Layer" => Ok(Transaction::RemoveLayer(current_layer)),
+            _ => panic!("There are only eight options."),
+        },
+        LayerMenuType::Resize => parse_numbers(&response, 2)
+            .map(|numbers| {
+                Transaction::ResizeLayer(
+                    current_layer,
+                    numbers[0],
+                    numbers[1],
+                    Tile::empty_ground(),
+                )
+            })
+            .map_err(|e| e.to_string()),
+        LayerMenuType::Crop => parse_numbers(&response, 4)
+            .map(|numbers| {
+                Transaction::CropLayer(
+                    current_layer,
+                    TileLocation::new(numbers[0] as i32, numbers[1] as i32),
+                    numbers[2],
+                    numbers[3],
+                )
+            })
+            .map_err(|e| e.to_string()),
+        LayerMenuType::Shift => parse_numbers(&response, 2)
+            .map(|numbers| {
+                Transaction::ShiftLayer(
+                    current_layer,
+                    TileLocation::new(numbers[0], numbers[1]),
+                    Tile::empty_ground(),
+                )
+            })
+            .map_err(|e| e.to_string()),
+        LayerMenuType::Move => parse_numbers(&response, 1)
+            .map(|numbers| Transaction::MoveLayer(current_layer, numbers[0]))
+            .map_err(|e| e.to_string()),
+    };
+    match transaction {
+        Ok(transaction) => {
+            add_transaction_event_writer.send(AddTransactionEvent(Some(transaction)));
+            commands.remove_resource::<LayerMenuToShow>();
         }
+        Err(message) => toast_message_event_writer.send(ToastMessageEvent(message)),
     }
 }
 
+fn tear_down_layer_menu_system(mut commands: Commands) {
+    commands.remove_resource::<LayerMenuToShow>();
+}
+
 // End Systems
 
 // Helper Structs
 #[derive(Debug, Clone, Hash)]
 pub enum InputError {
     InvalidFileStem(String),
+    InvalidNumbers(usize, String),
 }
 
 impl InputError {
@@ -384,6 +773,9 @@ impl InputError {
             Self::InvalidFileStem(s) => {
                 format!("Invalid Filename: {}", s)
             }
+            Self::InvalidNumbers(count, s) => {
+                format!("Expected {} numbers separated by spaces: {}", count, s)
+            }
         }
     }
 }
@@ -425,7 +817,7 @@ impl TransactionStore {
     pub fn compile(&self) -> Result<Map, TransactionStoreError> {
         let (mut result, from_last_snapshot) = self.get_from_last_snapshot()?;
         for transaction in from_last_snapshot.iter() {
-            Self::apply_transaction(&mut result, transaction);
+            Self::apply_transaction(&mut result, transaction)?;
         }
         Ok(result)
     }
@@ -455,7 +847,28 @@ impl TransactionStore {
                 *map = new_map.clone();
             }
             Transaction::Update(tile, location) => {
-                map.update(tile.clone(), location.clone());
+                map.update(tile.clone(), location.clone())?;
+            }
+            Transaction::Metadata(metadata) => {
+                map.set_metadata(metadata.clone());
+            }
+            Transaction::ResizeLayer(map_layer, width, height, fill) => {
+                map.resize_layer(*map_layer, *width, *height, fill)?;
+            }
+            Transaction::CropLayer(map_layer, origin, width, height) => {
+                map.crop_layer(*map_layer, *origin, *width, *height)?;
+            }
+            Transaction::ShiftLayer(map_layer, offset, fill) => {
+                map.shift_layer(*map_layer, *offset, fill)?;
+            }
+            Transaction::InsertLayer(map_layer, layer) => {
+                map.insert_layer(*map_layer, layer.clone())?;
+            }
+            Transaction::RemoveLayer(map_layer) => {
+                map.remove_layer(*map_layer)?;
+            }
+            Transaction::MoveLayer(from, to) => {
+                map.move_layer(*from, *to)?;
             }
         };
         Ok(())
@@ -466,6 +879,17 @@ impl TransactionStore {
 pub enum Transaction {
     Snapshot(Map),
     Update(Tile, MapLocation),
+    Metadata(MapMetadata),
+    // Layer, new width, new height and the tile to fill new space with.
+    ResizeLayer(usize, usize, usize, Tile),
+    // Layer, bottom left corner, width and height of the part to keep.
+    CropLayer(usize, TileLocation, usize, usize),
+    // Layer, how far to move its tiles and the tile to fill the space left behind with.
+    ShiftLayer(usize, TileLocation, Tile),
+    InsertLayer(usize, MapLayer),
+    RemoveLayer(usize),
+    // From and to layer indices.
+    MoveLayer(usize, usize),
 }
 
 impl Transaction {
@@ -483,11 +907,35 @@ pub enum TransactionStoreError {
     NoSnapshotTransaction,
     NoTransactionsToUndo,
     CurrentLayerDoesntExist,
+    // A layer transaction that doesn't fit the map it's applied to, e.g. cropping past its edge.
+    Map(MapError),
+}
+
+impl From<MapError> for TransactionStoreError {
+    fn from(value: MapError) -> Self {
+        Self::Map(value)
+    }
 }
 
 // End Helper Structs
 
 // Helper Functions
+
+// Reads exactly count whitespace separated numbers, as typed into the layer menu.
+fn parse_numbers<T: FromStr>(input: &str, count: usize) -> Result<Vec<T>, InputError> {
+    let invalid = || InputError::InvalidNumbers(count, input.to_string());
+    let numbers = input
+        .split_whitespace()
+        .map(|word| word.parse())
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|_| invalid())?;
+    if numbers.len() == count {
+        Ok(numbers)
+    } else {
+        Err(invalid())
+    }
+}
+
 fn sanitize_map_name<S: AsRef<OsStr> + Into<String>>(name: S) -> Result<S, InputError> {
     let name_as_str = name.as_ref();
 
@@ -530,10 +978,8 @@ fn sanitize_map_name<S: AsRef<OsStr> + Into<String>>(name: S) -> Result<S, Input
 
 #[cfg(test)]
 mod tests {
-
-    use crate::game::map::TileLocation;
-
     use super::*;
+    use crate::game::map_metadata::{Placement, PlacementKind};
 
     #[test]
     fn test_transactions_compile() {
@@ -559,4 +1005,91 @@ mod tests {
 
         assert_eq!(store.compile(), Ok(expected));
     }
+
+    #[test]
+    fn test_layer_transactions_compile_and_undo() {
+        let mut map = Map::new(vec![
+            MapLayer::fill(4, 4, Tile::empty_ground()),
+            MapLayer::fill(2, 2, Tile::wall()),
+        ]);
+        map.update(Tile::wall(), TileLocation::new(1, 1).into())
+            .unwrap();
+        let mut metadata = MapMetadata::new("Layers");
+        metadata.add_player_spawn(MapLocation::new(0, TileLocation::new(1, 2)));
+        metadata.add_placement(Placement::new(
+            PlacementKind::Item,
+            "key",
+            MapLocation::new(1, TileLocation::new(1, 1)),
+        ));
+        map.set_metadata(metadata);
+        let mut store = TransactionStore::from_snapshot(map.clone());
+
+        store.add(Transaction::ShiftLayer(
+            0,
+            TileLocation::new(1, -1),
+            Tile::empty_ground(),
+        ));
+        let shifted = store.compile_layer(0).unwrap();
+        assert_eq!(shifted.get(2, 0).unwrap(), &Tile::wall());
+        assert_eq!(shifted.get(1, 1).unwrap(), &Tile::empty_ground());
+        assert_eq!(
+            store.compile().unwrap().get_metadata().get_player_spawns(),
+            &vec![MapLocation::new(0, TileLocation::new(2, 1))]
+        );
+
+        store.add(Transaction::CropLayer(0, TileLocation::new(2, 0), 2, 3));
+        store.add(Transaction::ResizeLayer(0, 3, 4, Tile::wall()));
+        let resized = store.compile_layer(0).unwrap();
+        assert_eq!((resized.width(), resized.height()), (3, 4));
+        assert_eq!(resized.get(0, 0).unwrap(), &Tile::wall());
+        assert_eq!(resized.get(1, 0).unwrap(), &Tile::empty_ground());
+        assert_eq!(resized.get(2, 3).unwrap(), &Tile::wall());
+
+        store.add(Transaction::InsertLayer(
+            0,
+            MapLayer::fill(1, 1, Tile::empty_ground()),
+        ));
+        store.add(Transaction::MoveLayer(0, 2));
+        store.add(Transaction::RemoveLayer(1));
+        let compiled = store.compile().unwrap();
+        assert_eq!(compiled.get_layers().len(), 2);
+        assert_eq!(compiled.get_layer(0).unwrap(), &resized);
+        assert!(compiled.get_metadata().get_placements().is_empty());
+        assert_eq!(
+            compiled.get_metadata().get_player_spawns(),
+            &vec![MapLocation::new(0, TileLocation::new(0, 1))]
+        );
+
+        (0..6).for_each(|_| store.undo().unwrap());
+        assert_eq!(store.compile(), Ok(map));
+
+        store.add(Transaction::CropLayer(1, TileLocation::new(1, 1), 4, 4));
+        assert_eq!(
+            store.compile(),
+            Err(TransactionStoreError::Map(MapError::OutOfBounds))
+        );
+    }
+
+    #[test]
+    fn test_update_out_of_bounds_fails_to_compile() {
+        let map: Map = MapLayer::fill(10, 20, Tile::empty_ground()).into();
+        let mut store = TransactionStore::from_snapshot(map);
+        store.add(Transaction::Update(
+            Tile::wall(),
+            TileLocation::new(10, 5).into(),
+        ));
+        assert_eq!(
+            store.compile(),
+            Err(TransactionStoreError::Map(MapError::OutOfBounds))
+        );
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse_numbers::<usize>(" 3  4 ", 2).unwrap(), vec![3, 4]);
+        assert_eq!(parse_numbers::<i32>("-1 2", 2).unwrap(), vec![-1, 2]);
+        assert!(parse_numbers::<usize>("-1 2", 2).is_err());
+        assert!(parse_numbers::<usize>("3", 2).is_err());
+        assert!(parse_numbers::<usize>("3 4 5", 2).is_err());
+    }
 }
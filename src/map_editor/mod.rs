@@ -1 +1,2 @@
+pub mod map_diff;
 pub mod map_editor;
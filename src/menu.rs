@@ -8,9 +8,15 @@ use egui::{Align2, Color32, Frame, Response, RichText, Ui};
 use crate::constants::*;
 use crate::game::events::MenuInputEvent;
 use crate::game::{
-    character::BodyComponent, events::OpenMenuEvent, player::PlayerComponent, resources::GameState,
+    character::{BloodComponent, BodyComponent, InventoryComponent},
+    events::OpenMenuEvent,
+    exploring::end_turn,
+    health::{treat, Treatment},
+    map::ObjectTile,
+    player::PlayerComponent,
+    resources::GameState,
 };
-use crate::ui::{get_default_text, get_underlined_text};
+use crate::ui::{get_default_text, get_underlined_text, LogState};
 
 pub struct MenuPlugin;
 
@@ -27,12 +33,13 @@ impl Plugin for MenuPlugin {
 
 // Resources
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Resource)]
-pub struct MenuToShow(pub MenuUIState);
+pub struct MenuToShow(pub MenuUIState, pub ExploringMenuType);
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MenuUIState {
     menu_type: MenuType,
     buffer: String,
+    selected: Option<usize>,
 }
 
 impl MenuUIState {
@@ -40,9 +47,15 @@ impl MenuUIState {
         Self {
             menu_type,
             buffer: "".to_string(),
+            selected: None,
         }
     }
 
+    // The number of the last option chosen from a SelectFinite menu, for when options repeat.
+    pub fn get_selected(&self) -> Option<usize> {
+        self.selected
+    }
+
     pub fn render(
         &mut self,
         contexts: &mut EguiContexts,
@@ -69,7 +82,10 @@ impl MenuUIState {
                     match get_digit_from_keycode(input_event.0) {
                         None => {}
                         Some(digit) => match labelled_options.get(&digit) {
-                            Some(option) => return Some(option.to_string()),
+                            Some(option) => {
+                                self.selected = Some(digit);
+                                return Some(option.to_string());
+                            }
                             None => {}
                         },
                     };
@@ -182,26 +198,91 @@ pub enum MenuType {
 fn open_exploring_menu_system(
     mut commands: Commands,
     mut open_menu_event_reader: EventReader<OpenMenuEvent>,
-    player_query: Query<&BodyComponent, With<PlayerComponent>>,
+    player_query: Query<
+        (&BodyComponent, &BloodComponent, &InventoryComponent),
+        With<PlayerComponent>,
+    >,
+    mut log: ResMut<LogState>,
 ) {
     for event in open_menu_event_reader.iter() {
-        commands.insert_resource(NextState(Some(GameState::PlayerMenu)));
-        match event.0 {
+        let (player_body_component, player_blood_component, player_inventory) =
+            player_query.single();
+        let (menu_state, menu_type) = match event.0 {
             ExploringMenuType::Character => {
-                let player_body_component = player_query.single();
-                let options = player_body_component.0.get_menu_text();
-                commands.insert_resource(MenuToShow(MenuUIState::new(MenuType::Info(options))));
+                let mut options = vec![player_blood_component.get_menu_text()];
+                options.append(&mut player_body_component.0.get_menu_text());
+                (
+                    MenuUIState::new(MenuType::Info(options)),
+                    ExploringMenuType::Character,
+                )
             }
-        }
+            ExploringMenuType::Treatment | ExploringMenuType::TreatmentTarget(_) => {
+                let held = get_held_treatments(player_inventory);
+                if held.is_empty() {
+                    log.log_string("You have nothing to treat yourself with.");
+                    continue;
+                }
+                let options = held
+                    .iter()
+                    .map(|treatment| {
+                        format!(
+                            "{} ({})",
+                            treatment.to_string(),
+                            player_inventory.count(&ObjectTile::Treatment(*treatment))
+                        )
+                    })
+                    .collect();
+                (
+                    MenuUIState::new(MenuType::SelectFinite(options)),
+                    ExploringMenuType::Treatment,
+                )
+            }
+        };
+        commands.insert_resource(NextState(Some(GameState::PlayerMenu)));
+        commands.insert_resource(MenuToShow(menu_state, menu_type));
     }
 }
 
 fn render_menu(
+    mut commands: Commands,
     mut contexts: EguiContexts,
     mut event_reader: EventReader<MenuInputEvent>,
     mut menu: ResMut<MenuToShow>,
+    mut player_query: Query<(&mut BodyComponent, &mut InventoryComponent), With<PlayerComponent>>,
+    mut log: ResMut<LogState>,
 ) {
-    let _response = menu.0.render(&mut contexts, &mut event_reader);
+    if menu.0.render(&mut contexts, &mut event_reader).is_none() {
+        return;
+    }
+    let selected = match menu.0.get_selected() {
+        Some(selected) => selected,
+        None => return,
+    };
+    let (mut body, mut inventory) = player_query.single_mut();
+    match menu.1 {
+        ExploringMenuType::Character => {}
+        // Once there's a treatment, pick the body part to use it on.
+        ExploringMenuType::Treatment => {
+            if let Some(treatment) = get_held_treatments(&inventory).get(selected) {
+                *menu = MenuToShow(
+                    MenuUIState::new(MenuType::SelectFinite(body.0.get_menu_text())),
+                    ExploringMenuType::TreatmentTarget(*treatment),
+                );
+            }
+        }
+        ExploringMenuType::TreatmentTarget(treatment) => {
+            match treat(&mut body.0, &mut inventory, selected, treatment) {
+                Ok(message) => {
+                    log.log_string(&message);
+                    end_turn(&mut commands, TREATMENT_TICKS);
+                }
+                Err(e) => {
+                    log.log_string(&e.to_string());
+                    commands.insert_resource(NextState(Some(GameState::Exploring)));
+                }
+            }
+        }
+    }
 }
 
 fn tear_down_menu_system(mut commands: Commands) {
@@ -213,11 +294,20 @@ fn tear_down_menu_system(mut commands: Commands) {
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ExploringMenuType {
     Character,
+    Treatment,
+    TreatmentTarget(Treatment),
 }
 // End Helper Structs
 
 // Helper Functions
 
+fn get_held_treatments(inventory: &InventoryComponent) -> Vec<Treatment> {
+    Treatment::all()
+        .into_iter()
+        .filter(|treatment| inventory.contains(ObjectTile::Treatment(*treatment)))
+        .collect()
+}
+
 fn get_digit_from_keycode(keycode: KeyCode) -> Option<usize> {
     match keycode {
         KeyCode::Key0 => Some(0),
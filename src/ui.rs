@@ -79,7 +79,7 @@ fn ui_load_system(mut commands: Commands, asset_server: Res<AssetServer>) {
         .spawn(Camera2dBundle::default())
         .insert(RaycastSource::<MouseoverRaycastSet>::new());
 
-    commands.insert_resource(NextState(Some(GameState::InitializingWorld)));
+    commands.insert_resource(NextState(Some(GameState::LoadingMap)));
 }
 
 fn update_camera_zoom(